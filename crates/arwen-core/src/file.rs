@@ -0,0 +1,125 @@
+//! Read binaries without copying them into memory, and replace them without
+//! leaving a truncated file behind when writing fails.
+
+use std::{
+    fs,
+    io::{self, Write},
+    ops::Deref,
+    path::{Path, PathBuf},
+};
+
+use memmap2::Mmap;
+
+/// The contents of a file, mapped into memory.
+///
+/// Parsing borrows from the mapping, so large files are not read into memory up
+/// front. The file must not be modified while it is mapped; write the result with
+/// [`write_atomically`], which replaces the file instead of truncating it.
+#[derive(Debug)]
+pub struct MappedFile {
+    map: Mmap,
+}
+
+impl MappedFile {
+    /// Map the file at `path` into memory.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = fs::File::open(path)?;
+        // SAFETY: the mapping is read-only, and arwen replaces files rather than
+        // writing to them, so the mapped pages keep the contents of the old file.
+        let map = unsafe { Mmap::map(&file)? };
+        Ok(Self { map })
+    }
+}
+
+impl Deref for MappedFile {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.map
+    }
+}
+
+impl AsRef<[u8]> for MappedFile {
+    fn as_ref(&self) -> &[u8] {
+        &self.map
+    }
+}
+
+/// Write a file through a temporary file in the same directory, which is renamed
+/// over `path` once `write` succeeds.
+///
+/// If `write` fails, `path` is left as it was. An existing file keeps its
+/// permissions, and a symbolic link keeps pointing to the replaced file.
+pub fn write_atomically<E: From<io::Error>>(
+    path: &Path,
+    write: impl FnOnce(&mut dyn Write) -> Result<(), E>,
+) -> Result<(), E> {
+    StagedFile::write(path, write)?.replace()?;
+    Ok(())
+}
+
+/// The new contents of a file, written to a temporary file in the same directory.
+///
+/// Staging several files before replacing any of them lets a batch of edits fail
+/// without changing anything, while only one file is held in memory at a time.
+/// Dropping a staged file without replacing it deletes the temporary file.
+#[derive(Debug)]
+pub struct StagedFile {
+    temp_path: tempfile::TempPath,
+    path: PathBuf,
+}
+
+impl StagedFile {
+    /// Write the new contents of `path` to a temporary file next to it.
+    ///
+    /// An existing file keeps its permissions when it is replaced, and a symbolic
+    /// link keeps pointing to the replaced file.
+    pub fn write<E: From<io::Error>>(
+        path: &Path,
+        write: impl FnOnce(&mut dyn Write) -> Result<(), E>,
+    ) -> Result<Self, E> {
+        let path = resolve_link(path)?;
+        let directory = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let mut file = tempfile::Builder::new()
+            .prefix(".arwen-")
+            .tempfile_in(directory)?;
+        if let Ok(metadata) = fs::metadata(&path) {
+            file.as_file().set_permissions(metadata.permissions())?;
+        }
+
+        let mut writer = io::BufWriter::new(file.as_file_mut());
+        write(&mut writer)?;
+        writer.flush()?;
+        drop(writer);
+
+        // Close the file, so that staging many files doesn't keep them all open.
+        Ok(Self {
+            temp_path: file.into_temp_path(),
+            path,
+        })
+    }
+
+    /// The file that [`replace`](Self::replace) replaces.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Rename the temporary file over the file it replaces.
+    pub fn replace(self) -> io::Result<()> {
+        self.temp_path
+            .persist(&self.path)
+            .map_err(|error| error.error)
+    }
+}
+
+/// Follow symbolic links to the file they point to, if it exists.
+fn resolve_link(path: &Path) -> io::Result<PathBuf> {
+    match fs::canonicalize(path) {
+        Ok(path) => Ok(path),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(path.to_path_buf()),
+        Err(error) => Err(error),
+    }
+}
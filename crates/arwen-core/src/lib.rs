@@ -1,3 +1,9 @@
 //! Core types and traits for arwen binary patching tools.
 //!
 //! This crate provides shared utilities for the arwen ecosystem.
+
+pub mod file;
+pub mod prefix;
+
+pub use file::*;
+pub use prefix::*;
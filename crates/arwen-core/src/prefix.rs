@@ -0,0 +1,92 @@
+//! Replace an embedded prefix in the strings of a binary.
+//!
+//! Packages built under a long placeholder prefix embed it in C strings, for example
+//! in `.rodata` or `__cstring`. Relocating such a package means rewriting those strings
+//! in place: the file layout can't change, so the new prefix must not be longer than
+//! the old one, and the freed bytes are filled with NULs.
+
+use serde::Serialize;
+use thiserror::Error;
+
+/// An error while replacing a prefix.
+#[derive(Debug, Error)]
+pub enum PrefixError {
+    #[error("the prefix to replace is empty")]
+    EmptyPrefix,
+
+    #[error("new prefix is {new} bytes long, longer than the {old} bytes of the old prefix")]
+    NewPrefixTooLong { old: usize, new: usize },
+}
+
+/// A string whose prefix was replaced.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PrefixReplacement {
+    /// The offset of the first replaced byte in the file.
+    pub offset: u64,
+    /// The string from the first occurrence of the old prefix up to its NUL terminator.
+    pub old: String,
+    /// The string that replaced it.
+    pub new: String,
+}
+
+/// Replace every occurrence of `old` inside a NUL terminated string with `new`.
+///
+/// The rest of the string after each occurrence is moved up, and the bytes that
+/// are left over before the NUL terminator are zeroed. Occurrences that are not
+/// followed by a NUL terminator are left alone.
+pub fn replace_prefix(
+    data: &mut [u8],
+    old: &[u8],
+    new: &[u8],
+) -> Result<Vec<PrefixReplacement>, PrefixError> {
+    if old.is_empty() {
+        return Err(PrefixError::EmptyPrefix);
+    }
+    if new.len() > old.len() {
+        return Err(PrefixError::NewPrefixTooLong {
+            old: old.len(),
+            new: new.len(),
+        });
+    }
+
+    let mut replacements = Vec::new();
+    let mut position = 0;
+    while let Some(found) = find(&data[position..], old) {
+        let start = position + found;
+        let Some(length) = data[start..].iter().position(|&byte| byte == 0) else {
+            break;
+        };
+        let end = start + length;
+
+        let original = data[start..end].to_vec();
+        let replaced = replace_all(&original, old, new);
+        data[start..start + replaced.len()].copy_from_slice(&replaced);
+        data[start + replaced.len()..end].fill(0);
+
+        replacements.push(PrefixReplacement {
+            offset: start as u64,
+            old: String::from_utf8_lossy(&original).into_owned(),
+            new: String::from_utf8_lossy(&replaced).into_owned(),
+        });
+        position = end + 1;
+    }
+    Ok(replacements)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn replace_all(haystack: &[u8], old: &[u8], new: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(haystack.len());
+    let mut rest = haystack;
+    while let Some(found) = find(rest, old) {
+        result.extend_from_slice(&rest[..found]);
+        result.extend_from_slice(new);
+        rest = &rest[found + old.len()..];
+    }
+    result.extend_from_slice(rest);
+    result
+}
@@ -0,0 +1,87 @@
+#![cfg(unix)]
+
+use arwen_core::{write_atomically, MappedFile, StagedFile};
+use rstest::rstest;
+use std::{io, os::unix::fs::PermissionsExt};
+
+/// This test checks that a mapped file can be replaced while it is mapped, keeping
+/// the permissions and the contents that were mapped.
+#[rstest]
+fn test_write_atomically_replaces_mapped_file() {
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("binary");
+    std::fs::write(&path, b"old contents").unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let mapped = MappedFile::open(&path).unwrap();
+    write_atomically(&path, |writer| -> io::Result<()> {
+        writer.write_all(b"new")?;
+        writer.write_all(&mapped[3..])
+    })
+    .unwrap();
+
+    assert_eq!(&*mapped, b"old contents");
+    assert_eq!(std::fs::read(&path).unwrap(), b"new contents");
+    let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o755);
+}
+
+/// This test checks that a failed write leaves the file untouched and removes the
+/// temporary file.
+#[rstest]
+fn test_write_atomically_keeps_file_on_error() {
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("binary");
+    std::fs::write(&path, b"old contents").unwrap();
+
+    let result = write_atomically(&path, |writer| {
+        writer.write_all(b"partial")?;
+        Err(io::Error::other("edit failed"))
+    });
+
+    assert!(result.is_err());
+    assert_eq!(std::fs::read(&path).unwrap(), b"old contents");
+    assert_eq!(std::fs::read_dir(directory.path()).unwrap().count(), 1);
+}
+
+/// This test checks that writing through a symbolic link replaces the file it points
+/// to and keeps the link.
+#[rstest]
+fn test_write_atomically_follows_links() {
+    let directory = tempfile::tempdir().unwrap();
+    let target = directory.path().join("libfoo.so.1.2");
+    let link = directory.path().join("libfoo.so.1");
+    std::fs::write(&target, b"old").unwrap();
+    std::os::unix::fs::symlink("libfoo.so.1.2", &link).unwrap();
+
+    write_atomically(&link, |writer| writer.write_all(b"new")).unwrap();
+
+    assert!(std::fs::symlink_metadata(&link)
+        .unwrap()
+        .file_type()
+        .is_symlink());
+    assert_eq!(std::fs::read(&target).unwrap(), b"new");
+}
+
+/// This test checks that staged files only replace their files when replaced, and
+/// that dropping them removes the temporary files.
+#[rstest]
+fn test_staged_file() {
+    let directory = tempfile::tempdir().unwrap();
+    let kept = directory.path().join("kept");
+    let replaced = directory.path().join("replaced");
+    std::fs::write(&kept, b"old").unwrap();
+    std::fs::write(&replaced, b"old").unwrap();
+
+    let staged_kept = StagedFile::write(&kept, |writer| writer.write_all(b"new")).unwrap();
+    let staged_replaced = StagedFile::write(&replaced, |writer| writer.write_all(b"new")).unwrap();
+    assert_eq!(std::fs::read(&replaced).unwrap(), b"old");
+    assert_eq!(std::fs::read_dir(directory.path()).unwrap().count(), 4);
+
+    staged_replaced.replace().unwrap();
+    drop(staged_kept);
+
+    assert_eq!(std::fs::read(&kept).unwrap(), b"old");
+    assert_eq!(std::fs::read(&replaced).unwrap(), b"new");
+    assert_eq!(std::fs::read_dir(directory.path()).unwrap().count(), 2);
+}
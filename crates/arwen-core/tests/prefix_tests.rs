@@ -0,0 +1,74 @@
+use arwen_core::{replace_prefix, PrefixError, PrefixReplacement};
+use rstest::rstest;
+use std::path::PathBuf;
+
+/// This test checks that every NUL terminated occurrence is replaced and padded with NULs.
+#[rstest]
+fn test_replace_prefix_pads_with_nul() {
+    let mut data =
+        b"\x01/placeholder/lib\0/placeholder/bin:/placeholder/share\0/placeholder".to_vec();
+    let length = data.len();
+
+    let replacements = replace_prefix(&mut data, b"/placeholder", b"/opt").unwrap();
+
+    assert_eq!(
+        replacements,
+        vec![
+            PrefixReplacement {
+                offset: 1,
+                old: "/placeholder/lib".to_string(),
+                new: "/opt/lib".to_string(),
+            },
+            PrefixReplacement {
+                offset: 18,
+                old: "/placeholder/bin:/placeholder/share".to_string(),
+                new: "/opt/bin:/opt/share".to_string(),
+            },
+        ]
+    );
+    assert_eq!(data.len(), length);
+    assert_eq!(
+        data,
+        [
+            &b"\x01/opt/lib"[..],
+            &[0; 9],
+            b"/opt/bin:/opt/share",
+            &[0; 17],
+            // Not NUL terminated, so not a C string.
+            b"/placeholder",
+        ]
+        .concat()
+    );
+}
+
+/// This test checks that a longer prefix is rejected without changing anything.
+#[rstest]
+fn test_replace_prefix_rejects_longer_prefix() {
+    let mut data = b"/short/lib\0".to_vec();
+
+    let error = replace_prefix(&mut data, b"/short", b"/much/longer").unwrap_err();
+
+    assert!(matches!(
+        error,
+        PrefixError::NewPrefixTooLong { old: 6, new: 12 }
+    ));
+    assert_eq!(data, b"/short/lib\0");
+}
+
+/// This test checks that the runpath of an ELF file can be relocated in place.
+#[rstest]
+fn test_replace_prefix_elf(
+    #[files("../../tests/data/elf/x64/exec/linux-x64-bash")] bin_path: PathBuf,
+) {
+    let original = std::fs::read(&bin_path).unwrap();
+    let mut data = original.clone();
+
+    let replacements = replace_prefix(&mut data, b"path-graf", b"/lib").unwrap();
+
+    assert_eq!(replacements.len(), 1);
+    let offset = replacements[0].offset as usize;
+    assert_eq!(&data[offset..offset + 10], b"/lib\0\0\0\0\0\0");
+    // Nothing else changed.
+    assert_eq!(data[..offset], original[..offset]);
+    assert_eq!(data[offset + 10..], original[offset + 10..]);
+}
@@ -0,0 +1,375 @@
+//! Validate the layout of an ELF file, independently of the rewriter.
+//!
+//! The checks look for mistakes that make the dynamic loader reject the file or map
+//! it wrongly, such as overlapping segments or dynamic entries pointing at unmapped
+//! memory.
+
+use std::fmt;
+
+use object::{
+    elf,
+    read::elf::{Dyn, FileHeader, ProgramHeader, SectionHeader},
+    Endianness, FileKind, SectionIndex,
+};
+use serde::Serialize;
+
+use crate::rewriter::{is_address_tag, tag_name};
+
+/// A problem found by [`check`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "problem", rename_all = "snake_case")]
+pub enum Problem {
+    /// The file headers can't be read.
+    Malformed {
+        /// What couldn't be read.
+        message: String,
+    },
+    /// Two `PT_LOAD` segments map the same memory.
+    OverlappingSegments {
+        /// The index of the first segment in the program header table.
+        first: usize,
+        /// The index of the second segment in the program header table.
+        second: usize,
+    },
+    /// The file offset and the address of a `PT_LOAD` segment differ modulo its
+    /// alignment, so it can't be mapped.
+    MisalignedSegment {
+        /// The index of the segment in the program header table.
+        index: usize,
+        /// The file offset of the segment.
+        p_offset: u64,
+        /// The virtual address of the segment.
+        p_vaddr: u64,
+        /// The alignment of the segment.
+        p_align: u64,
+    },
+    /// The `PT_PHDR` segment isn't covered by a `PT_LOAD` segment.
+    UnmappedProgramHeaders,
+    /// A dynamic entry holds an address that no `PT_LOAD` segment maps.
+    UnmappedDynamicEntry {
+        /// The name of the dynamic tag.
+        tag: String,
+        /// The address in the entry.
+        address: u64,
+    },
+    /// A dynamic entry disagrees with the section header of the section it describes.
+    DynamicSectionMismatch {
+        /// The name of the dynamic tag.
+        tag: String,
+        /// The value of the entry.
+        value: u64,
+        /// The name of the section.
+        section: String,
+        /// The value given by the section header.
+        expected: u64,
+    },
+    /// The interpreter path in `PT_INTERP` doesn't end with a NUL byte.
+    UnterminatedInterpreter,
+    /// The alignment of a `PT_LOAD` segment isn't a multiple of the page size, which
+    /// the loader rejects.
+    AlignmentBelowPageSize {
+        /// The index of the segment in the program header table.
+        index: usize,
+        /// The alignment of the segment.
+        p_align: u64,
+        /// The page size that was checked.
+        page_size: u64,
+    },
+    /// The file offset and the address of a `PT_LOAD` segment differ modulo the page
+    /// size, so it can't be mapped.
+    MisalignedForPageSize {
+        /// The index of the segment in the program header table.
+        index: usize,
+        /// The file offset of the segment.
+        p_offset: u64,
+        /// The virtual address of the segment.
+        p_vaddr: u64,
+        /// The page size that was checked.
+        page_size: u64,
+    },
+    /// Two `PT_LOAD` segments map different contents or permissions to the same page,
+    /// so the segment mapped last overwrites the end of the other one.
+    SharedPage {
+        /// The index of the first segment in the program header table.
+        first: usize,
+        /// The index of the second segment in the program header table.
+        second: usize,
+        /// The page size that was checked.
+        page_size: u64,
+    },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::Malformed { message } => write!(f, "malformed file: {message}"),
+            Problem::OverlappingSegments { first, second } => {
+                write!(f, "LOAD segments [{first}] and [{second}] overlap")
+            }
+            Problem::MisalignedSegment {
+                index,
+                p_offset,
+                p_vaddr,
+                p_align,
+            } => write!(
+                f,
+                "LOAD segment [{index}] has offset {p_offset:#x} and address {p_vaddr:#x}, \
+                 which differ modulo its alignment {p_align:#x}"
+            ),
+            Problem::UnmappedProgramHeaders => {
+                write!(f, "PHDR segment is not covered by a LOAD segment")
+            }
+            Problem::UnmappedDynamicEntry { tag, address } => {
+                write!(f, "{tag} points to unmapped address {address:#x}")
+            }
+            Problem::DynamicSectionMismatch {
+                tag,
+                value,
+                section,
+                expected,
+            } => write!(
+                f,
+                "{tag} is {value:#x} but the {section} section header says {expected:#x}"
+            ),
+            Problem::UnterminatedInterpreter => {
+                write!(f, "INTERP segment is not NUL terminated")
+            }
+            Problem::AlignmentBelowPageSize {
+                index,
+                p_align,
+                page_size,
+            } => write!(
+                f,
+                "LOAD segment [{index}] has alignment {p_align:#x}, \
+                 which is not a multiple of the page size {page_size:#x}"
+            ),
+            Problem::MisalignedForPageSize {
+                index,
+                p_offset,
+                p_vaddr,
+                page_size,
+            } => write!(
+                f,
+                "LOAD segment [{index}] has offset {p_offset:#x} and address {p_vaddr:#x}, \
+                 which differ modulo the page size {page_size:#x}"
+            ),
+            Problem::SharedPage {
+                first,
+                second,
+                page_size,
+            } => write!(
+                f,
+                "LOAD segments [{first}] and [{second}] share a page of {page_size:#x} bytes"
+            ),
+        }
+    }
+}
+
+/// Check the layout of an ELF file and return the problems found.
+///
+/// This only reads the file; it doesn't depend on how the file was written.
+pub fn check(data: &[u8]) -> Vec<Problem> {
+    check_with(data, None)
+}
+
+/// Check the layout of an ELF file, and that it loads on a system with pages of
+/// `page_size` bytes.
+///
+/// On top of [`check`], the `PT_LOAD` segments must be aligned to the page size, and
+/// must not map different contents or permissions to the same page.
+pub fn check_page_size(data: &[u8], page_size: u64) -> Vec<Problem> {
+    check_with(data, Some(page_size))
+}
+
+fn check_with(data: &[u8], page_size: Option<u64>) -> Vec<Problem> {
+    let result = match FileKind::parse(data) {
+        Ok(FileKind::Elf32) => check_file::<elf::FileHeader32<Endianness>>(data, page_size),
+        Ok(FileKind::Elf64) => check_file::<elf::FileHeader64<Endianness>>(data, page_size),
+        _ => Err("not an ELF file".to_string()),
+    };
+    result.unwrap_or_else(|message| vec![Problem::Malformed { message }])
+}
+
+/// A `PT_LOAD` segment, with its index in the program header table.
+struct Load {
+    index: usize,
+    p_flags: u32,
+    p_offset: u64,
+    p_vaddr: u64,
+    p_filesz: u64,
+    p_memsz: u64,
+}
+
+impl Load {
+    fn maps(&self, address: u64) -> bool {
+        address >= self.p_vaddr && address < self.p_vaddr + self.p_memsz
+    }
+}
+
+fn check_file<Elf: FileHeader<Endian = Endianness>>(
+    data: &[u8],
+    page_size: Option<u64>,
+) -> Result<Vec<Problem>, String> {
+    let header = Elf::parse(data).map_err(|error| error.to_string())?;
+    let endian = header.endian().map_err(|error| error.to_string())?;
+    let segments = header
+        .program_headers(endian, data)
+        .map_err(|error| error.to_string())?;
+    let mut problems = Vec::new();
+
+    let mut loads = Vec::new();
+    for (index, segment) in segments.iter().enumerate() {
+        if segment.p_type(endian) != elf::PT_LOAD {
+            continue;
+        }
+        let load = Load {
+            index,
+            p_flags: segment.p_flags(endian),
+            p_offset: segment.p_offset(endian).into(),
+            p_vaddr: segment.p_vaddr(endian).into(),
+            p_filesz: segment.p_filesz(endian).into(),
+            p_memsz: segment.p_memsz(endian).into(),
+        };
+        let p_align: u64 = segment.p_align(endian).into();
+        if p_align > 1 && load.p_offset % p_align != load.p_vaddr % p_align {
+            problems.push(Problem::MisalignedSegment {
+                index,
+                p_offset: load.p_offset,
+                p_vaddr: load.p_vaddr,
+                p_align,
+            });
+        }
+        if let Some(page_size) = page_size {
+            if !p_align.is_multiple_of(page_size) {
+                problems.push(Problem::AlignmentBelowPageSize {
+                    index,
+                    p_align,
+                    page_size,
+                });
+            }
+            // With an alignment that is a multiple of the page size, a misaligned
+            // offset was reported above.
+            let aligned_to_page = p_align > 1 && p_align.is_multiple_of(page_size);
+            if !aligned_to_page && load.p_offset % page_size != load.p_vaddr % page_size {
+                problems.push(Problem::MisalignedForPageSize {
+                    index,
+                    p_offset: load.p_offset,
+                    p_vaddr: load.p_vaddr,
+                    page_size,
+                });
+            }
+        }
+        loads.push(load);
+    }
+
+    let mut sorted: Vec<&Load> = loads.iter().filter(|load| load.p_memsz > 0).collect();
+    sorted.sort_by_key(|load| load.p_vaddr);
+    for pair in sorted.windows(2) {
+        if pair[0].p_vaddr + pair[0].p_memsz > pair[1].p_vaddr {
+            problems.push(Problem::OverlappingSegments {
+                first: pair[0].index.min(pair[1].index),
+                second: pair[0].index.max(pair[1].index),
+            });
+        } else if let Some(page_size) = page_size {
+            let (first, second) = (pair[0], pair[1]);
+            let last_page = (first.p_vaddr + first.p_memsz - 1) / page_size;
+            // A page can be shared when both segments map it the same way.
+            let same_mapping = first.p_flags == second.p_flags
+                && first.p_vaddr.wrapping_sub(first.p_offset)
+                    == second.p_vaddr.wrapping_sub(second.p_offset);
+            if last_page >= second.p_vaddr / page_size && !same_mapping {
+                problems.push(Problem::SharedPage {
+                    first: first.index.min(second.index),
+                    second: first.index.max(second.index),
+                    page_size,
+                });
+            }
+        }
+    }
+
+    for segment in segments {
+        match segment.p_type(endian) {
+            elf::PT_PHDR => {
+                let p_offset: u64 = segment.p_offset(endian).into();
+                let p_vaddr: u64 = segment.p_vaddr(endian).into();
+                let p_filesz: u64 = segment.p_filesz(endian).into();
+                let covered = loads.iter().any(|load| {
+                    p_offset >= load.p_offset
+                        && p_offset + p_filesz <= load.p_offset + load.p_filesz
+                        && p_vaddr >= load.p_vaddr
+                        && p_vaddr + p_filesz <= load.p_vaddr + load.p_memsz
+                });
+                if !covered {
+                    problems.push(Problem::UnmappedProgramHeaders);
+                }
+            }
+            elf::PT_INTERP => {
+                let interp = segment.data(endian, data).unwrap_or(&[]);
+                if interp.last() != Some(&0) {
+                    problems.push(Problem::UnterminatedInterpreter);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let dynamic = segments
+        .iter()
+        .find_map(|segment| segment.dynamic(endian, data).ok().flatten())
+        .unwrap_or(&[]);
+    let mut entries = Vec::new();
+    for entry in dynamic {
+        let tag: u64 = entry.d_tag(endian).into();
+        if tag == u64::from(elf::DT_NULL) {
+            break;
+        }
+        let Ok(tag) = u32::try_from(tag) else {
+            continue;
+        };
+        let value: u64 = entry.d_val(endian).into();
+        if is_address_tag(tag) && !loads.iter().any(|load| load.maps(value)) {
+            problems.push(Problem::UnmappedDynamicEntry {
+                tag: name_of(tag),
+                address: value,
+            });
+        }
+        entries.push((tag, value));
+    }
+
+    // Stripped files may have no section headers; then there is nothing to compare.
+    let sections = header
+        .sections(endian, data)
+        .map_err(|error| error.to_string())?;
+    if let Some(dynsym) = sections
+        .iter()
+        .find(|section| section.sh_type(endian) == elf::SHT_DYNSYM)
+    {
+        let dynstr = sections
+            .section(SectionIndex(dynsym.sh_link(endian) as usize))
+            .ok();
+        let mut compare = |tag: u32, section: &Elf::SectionHeader, expected: u64| {
+            for &(entry_tag, value) in &entries {
+                if entry_tag == tag && value != expected {
+                    let name = sections.section_name(endian, section).unwrap_or_default();
+                    problems.push(Problem::DynamicSectionMismatch {
+                        tag: name_of(tag),
+                        value,
+                        section: String::from_utf8_lossy(name).into_owned(),
+                        expected,
+                    });
+                }
+            }
+        };
+        compare(elf::DT_SYMTAB, dynsym, dynsym.sh_addr(endian).into());
+        if let Some(dynstr) = dynstr {
+            compare(elf::DT_STRTAB, dynstr, dynstr.sh_addr(endian).into());
+            compare(elf::DT_STRSZ, dynstr, dynstr.sh_size(endian).into());
+        }
+    }
+
+    Ok(problems)
+}
+
+fn name_of(tag: u32) -> String {
+    tag_name(tag).map_or_else(|| format!("{tag:#x}"), str::to_string)
+}
@@ -5,23 +5,30 @@ use std::{
 
 use object::elf;
 
-use crate::rewriter::{ElfError, Writer};
+use crate::check::Problem;
+use crate::rewriter::{
+    DynamicEntry, DynamicFlags, ElfError, ElfHeader, ExportChange, GnuProperty, NeededPosition,
+    RunPath, RunPathKind, SegmentInfo, ShrinkReport, SymbolRename, VersionRequirement, Writer,
+};
 
 pub struct ElfContainer<'a> {
     /// The writer for Elf.
     pub inner: Writer<'a>,
 
     /// The raw bytes of the ELF file.
-    pub data: Vec<u8>,
+    pub data: &'a [u8],
 }
 
 impl<'a> ElfContainer<'a> {
-    /// Parse the given bytes and return a new `MachoContainer`.
+    /// Parse the given bytes and return a new `ElfContainer`.
+    ///
+    /// The bytes are borrowed, not copied, so they can be a memory-mapped
+    /// [`MappedFile`](arwen_core::MappedFile).
     pub fn parse(bytes_of_file: &'a [u8]) -> Result<Self, ElfError> {
         let rewriter = Writer::read(bytes_of_file)?;
         Ok(Self {
             inner: rewriter,
-            data: bytes_of_file.to_vec(),
+            data: bytes_of_file,
         })
     }
 
@@ -54,18 +61,31 @@ impl<'a> ElfContainer<'a> {
         }
     }
 
+    /// Print the DT_RPATH and DT_RUNPATH entries, prefixed with the tag they come from.
+    pub fn print_runpath_kind(&self) {
+        for runpath in self.inner.elf_runpaths() {
+            println!("{}: {runpath}", runpath.kind);
+        }
+    }
+
+    /// Get all DT_RPATH and DT_RUNPATH entries, in the order they appear in the file.
+    pub fn runpaths(&self) -> Vec<RunPath> {
+        self.inner.elf_runpaths()
+    }
+
     /// Get the RPATH/RUNPATH entries as a list of paths.
     /// Returns entries from DT_RUNPATH if present, otherwise DT_RPATH.
+    ///
+    /// This matches the dynamic linker, which ignores DT_RPATH when DT_RUNPATH is present.
     pub fn get_rpath(&self) -> Vec<String> {
-        if let Some(runpath) = self.inner.elf_runpath() {
-            let path_str = String::from_utf8_lossy(runpath);
-            if path_str.is_empty() {
-                return Vec::new();
-            }
-            path_str.split(':').map(|s| s.to_string()).collect()
-        } else {
-            Vec::new()
-        }
+        let runpaths = self.inner.elf_runpaths();
+        let runpath = runpaths
+            .iter()
+            .find(|runpath| runpath.kind == RunPathKind::Runpath)
+            .or_else(|| runpaths.first());
+        runpath
+            .map(|runpath| runpath.entries.clone())
+            .unwrap_or_default()
     }
 
     /// Force the ELF file to use the DT_RPATH instead of DT_RUNPATH.
@@ -102,13 +122,119 @@ impl<'a> ElfContainer<'a> {
         println!("{}", self.inner.header().os_abi);
     }
 
-    /// Set the SONAME of DT_SONAME.
+    /// Set the ABI version in the ELF file.
+    pub fn set_abi_version(&mut self, abi_version: u8) {
+        self.inner.elf_set_abi_version(abi_version);
+    }
+
+    /// Set the processor-specific flags in the ELF file.
+    pub fn set_e_flags(&mut self, e_flags: u32) {
+        self.inner.elf_set_e_flags(e_flags);
+    }
+
+    /// Return the decoded file header.
+    pub fn header(&self) -> ElfHeader {
+        self.inner.elf_header()
+    }
+
+    /// Print the decoded file header.
+    pub fn print_header(&self) {
+        print!("{}", self.inner.elf_header());
+    }
+
+    /// Return the program headers and the sections mapped into them.
+    pub fn segments(&self) -> Vec<SegmentInfo> {
+        self.inner.elf_segments()
+    }
+
+    /// Print the program headers and the sections mapped into them.
+    pub fn print_segments(&self) {
+        for segment in self.inner.elf_segments() {
+            println!("{segment}");
+        }
+    }
+
+    /// Set the flags of the segment at `index` in the program header table.
+    ///
+    /// Mapping a section writable and executable is refused unless `force` is set.
+    pub fn set_segment_flags(
+        &mut self,
+        index: usize,
+        p_flags: u32,
+        force: bool,
+    ) -> Result<(), ElfError> {
+        self.inner.elf_set_segment_flags(index, p_flags, force)
+    }
+
+    /// Get the properties of the GNU property notes, such as the CET and BTI features.
+    pub fn gnu_properties(&self) -> Vec<GnuProperty> {
+        self.inner.elf_gnu_properties()
+    }
+
+    /// Print the properties of the GNU property notes.
+    pub fn print_properties(&self) {
+        for property in self.inner.elf_gnu_properties() {
+            println!("{property}");
+        }
+    }
+
+    /// Clear a feature bit of the GNU property notes, e.g. `SHSTK` or `BTI`.
+    pub fn clear_gnu_property(&mut self, feature: &str) -> Result<(), ElfError> {
+        self.inner.elf_clear_gnu_property(feature)
+    }
+
+    /// Add a `.gnu.hash` table to a file that only has a `DT_HASH` table.
+    pub fn add_gnu_hash(&mut self) -> Result<(), ElfError> {
+        self.inner.elf_add_gnu_hash()
+    }
+
+    /// Set the SONAME of DT_SONAME, and the name of the base version definition.
     pub fn set_soname(&mut self, soname: impl AsRef<[u8]>) -> Result<(), ElfError> {
         self.inner.elf_set_soname(soname.as_ref().to_vec())?;
 
         Ok(())
     }
 
+    /// Set the name of the base version definition, which names the file for the
+    /// version requirements of its consumers.
+    ///
+    /// Files without version definitions are left unchanged.
+    pub fn set_version_base(&mut self, name: impl AsRef<[u8]>) -> Result<(), ElfError> {
+        self.inner.elf_set_version_base(name.as_ref().to_vec());
+
+        Ok(())
+    }
+
+    /// Follow the rename of a library this file needs: replace its DT_NEEDED entry
+    /// and the file name of the symbol versions required from it.
+    ///
+    /// Returns false, without changing anything, if this file doesn't need `old`.
+    pub fn rename_needed_library(&mut self, old: &str, new: &str) -> Result<bool, ElfError> {
+        let needed = self
+            .inner
+            .elf_needed()
+            .any(|needed| needed == old.as_bytes());
+        let versioned = self
+            .inner
+            .elf_version_requirements()
+            .iter()
+            .any(|requirement| requirement.library == old);
+        if !needed && !versioned {
+            return Ok(false);
+        }
+        let names = HashMap::from([(old.as_bytes().to_vec(), new.as_bytes().to_vec())]);
+        self.inner.elf_replace_needed(&names)?;
+
+        Ok(true)
+    }
+
+    /// Get the SONAME of DT_SONAME.
+    pub fn soname(&self) -> Option<String> {
+        self.inner
+            .elf_soname()
+            .map(|soname| String::from_utf8_lossy(soname).into_owned())
+    }
+
     /// Print the SONAME of DT_SONAME.
     pub fn print_soname(&mut self) {
         if let Some(soname) = self.inner.elf_soname() {
@@ -116,11 +242,15 @@ impl<'a> ElfContainer<'a> {
         }
     }
 
-    /// Remove RPATHs that don't point to the given prefixes.
-    pub fn shrink_rpath(&mut self, rpath_prefixes: Vec<String>) -> Result<(), ElfError> {
-        self.inner.elf_shrink_rpath(rpath_prefixes)?;
-
-        Ok(())
+    /// Remove RPATHs that don't point to the given prefixes or hold no needed library.
+    ///
+    /// `origin` is the directory that contains the file, used to expand `$ORIGIN`.
+    pub fn shrink_rpath(
+        &mut self,
+        rpath_prefixes: Vec<String>,
+        origin: Option<&Path>,
+    ) -> Result<ShrinkReport, ElfError> {
+        self.inner.elf_shrink_rpath(rpath_prefixes, origin)
     }
 
     /// Add DT_NEEDED to the ELF file.
@@ -134,6 +264,27 @@ impl<'a> ElfContainer<'a> {
         Ok(())
     }
 
+    /// Add DT_NEEDED to the ELF file at a position among the existing entries.
+    pub fn add_needed_at(
+        &mut self,
+        dt_needed: Vec<impl Into<Vec<u8>>>,
+        position: NeededPosition,
+    ) -> Result<(), ElfError> {
+        let dt_as_u8 = dt_needed
+            .into_iter()
+            .map(Into::into)
+            .collect::<Vec<Vec<u8>>>();
+        self.inner.elf_add_needed_at(&dt_as_u8, &position)?;
+
+        Ok(())
+    }
+
+    /// Reorder the DT_NEEDED entries, putting the given libraries first.
+    pub fn reorder_needed(&mut self, order: Vec<impl Into<Vec<u8>>>) -> Result<(), ElfError> {
+        let order = order.into_iter().map(Into::into).collect::<Vec<Vec<u8>>>();
+        self.inner.elf_reorder_needed(&order)
+    }
+
     /// Remove DT_NEEDED from the ELF file.
     pub fn remove_needed(&mut self, dt_needed: Vec<impl Into<Vec<u8>>>) -> Result<(), ElfError> {
         let dt_as_u8 = dt_needed
@@ -145,7 +296,8 @@ impl<'a> ElfContainer<'a> {
         Ok(())
     }
 
-    /// Replace DT_NEEDED in the ELF file.
+    /// Replace DT_NEEDED in the ELF file, and the library names of the symbol
+    /// version requirements.
     pub fn replace_needed(
         &mut self,
         dt_needed: &HashMap<impl Into<Vec<u8>> + Clone, impl Into<Vec<u8>> + Clone>,
@@ -163,6 +315,26 @@ impl<'a> ElfContainer<'a> {
         }
     }
 
+    /// Get the DT_NEEDED libraries, in search order.
+    pub fn needed(&self) -> Vec<String> {
+        self.inner
+            .elf_needed()
+            .map(|needed| String::from_utf8_lossy(needed).into_owned())
+            .collect()
+    }
+
+    /// Get all entries of the dynamic section.
+    pub fn dynamic_entries(&self) -> Vec<DynamicEntry> {
+        self.inner.elf_dynamic_entries()
+    }
+
+    /// Print all entries of the dynamic section.
+    pub fn print_dynamic(&self) {
+        for entry in self.inner.elf_dynamic_entries() {
+            println!("{entry}");
+        }
+    }
+
     /// Disable the default library search paths.
     pub fn no_default_lib(&mut self) -> Result<(), ElfError> {
         self.inner.elf_no_default_lib()?;
@@ -170,6 +342,40 @@ impl<'a> ElfContainer<'a> {
         Ok(())
     }
 
+    /// Set DT_FLAGS and DT_FLAGS_1 bits given by symbolic names such as `BIND_NOW` or `PIE`.
+    ///
+    /// The entries are created if they are missing.
+    pub fn set_dynamic_flags<S: AsRef<str>>(&mut self, names: &[S]) -> Result<(), ElfError> {
+        let flags = DynamicFlags::from_names(names)?;
+        self.inner.elf_set_dynamic_flags(flags)?;
+
+        Ok(())
+    }
+
+    /// Clear DT_FLAGS and DT_FLAGS_1 bits given by symbolic names such as `BIND_NOW` or `PIE`.
+    pub fn clear_dynamic_flags<S: AsRef<str>>(&mut self, names: &[S]) -> Result<(), ElfError> {
+        let flags = DynamicFlags::from_names(names)?;
+        self.inner.elf_clear_dynamic_flags(flags)?;
+
+        Ok(())
+    }
+
+    /// Get the current DT_FLAGS and DT_FLAGS_1 bits.
+    pub fn dynamic_flags(&self) -> DynamicFlags {
+        self.inner.elf_dynamic_flags()
+    }
+
+    /// Print the decoded DT_FLAGS and DT_FLAGS_1 bits.
+    pub fn print_flags(&self) {
+        let flags = self.inner.elf_dynamic_flags();
+        if flags.flags != 0 {
+            println!("DT_FLAGS: {}", flags.flags_names().join(" "));
+        }
+        if flags.flags_1 != 0 {
+            println!("DT_FLAGS_1: {}", flags.flags_1_names().join(" "));
+        }
+    }
+
     /// Clear the version from given symbol.
     pub fn clear_version_symbol(&mut self, symbol: &str) -> Result<(), ElfError> {
         self.inner.elf_clear_symbol_version(symbol)?;
@@ -177,6 +383,55 @@ impl<'a> ElfContainer<'a> {
         Ok(())
     }
 
+    /// Get the symbol versions required from each library.
+    pub fn version_requirements(&self) -> Vec<VersionRequirement> {
+        self.inner.elf_version_requirements()
+    }
+
+    /// Print the symbol versions required from each library.
+    pub fn print_version_requirements(&self) {
+        for requirement in self.inner.elf_version_requirements() {
+            println!("{requirement}");
+        }
+    }
+
+    /// Remove a symbol version requirement, optionally only the one on the given library.
+    ///
+    /// Symbols bound to the version become unversioned.
+    pub fn remove_version_requirement(
+        &mut self,
+        version: &str,
+        library: Option<&str>,
+    ) -> Result<(), ElfError> {
+        self.inner
+            .elf_remove_version_requirement(library.map(str::as_bytes), version.as_bytes())?;
+
+        Ok(())
+    }
+
+    /// Replace a symbol version requirement, optionally only the one on the given library.
+    pub fn replace_version_requirement(
+        &mut self,
+        old_version: &str,
+        new_version: &str,
+        library: Option<&str>,
+    ) -> Result<(), ElfError> {
+        self.inner.elf_replace_version_requirement(
+            library.map(str::as_bytes),
+            old_version.as_bytes(),
+            new_version.as_bytes(),
+        )?;
+
+        Ok(())
+    }
+
+    /// Remove all symbol versioning information.
+    pub fn strip_symbol_versions(&mut self) -> Result<(), ElfError> {
+        self.inner.elf_strip_symbol_versions()?;
+
+        Ok(())
+    }
+
     /// Add a debug tag to the ELF file.
     pub fn add_debug_tag(&mut self) -> Result<(), ElfError> {
         self.inner.elf_add_dynamic_debug()?;
@@ -221,6 +476,154 @@ impl<'a> ElfContainer<'a> {
         Ok(())
     }
 
+    /// Add `prefix` to the name of every symbol exported by this library.
+    ///
+    /// Returns the renames, to be applied to the consumers of the library with
+    /// [`ElfContainer::rename_imports`].
+    pub fn prefix_exports(&mut self, prefix: &str) -> Vec<SymbolRename> {
+        self.inner.elf_prefix_exports(prefix)
+    }
+
+    /// Rename the symbols imported from `library` after its exports were renamed.
+    ///
+    /// `library` is the name under which this file needs the library, usually its
+    /// SONAME. Returns the renames done in this file.
+    pub fn rename_imports(
+        &mut self,
+        library: &str,
+        renames: &[SymbolRename],
+    ) -> Result<Vec<SymbolRename>, ElfError> {
+        self.inner.elf_rename_imports(library.as_bytes(), renames)
+    }
+
+    /// Change how the defined dynamic symbols matching `patterns` are exported.
+    ///
+    /// Patterns may use the `*` and `?` wildcards. Returns the names of the symbols
+    /// that changed.
+    pub fn change_symbol_export(
+        &mut self,
+        patterns: &[impl AsRef<str>],
+        change: ExportChange,
+    ) -> Vec<String> {
+        let patterns: Vec<String> = patterns
+            .iter()
+            .map(|pattern| pattern.as_ref().to_string())
+            .collect();
+        self.inner.elf_change_symbol_export(&patterns, change)
+    }
+
+    /// Rename symbols in both the symbol table and the dynamic symbol table.
+    pub fn rename_symbols(
+        &mut self,
+        symbols: &HashMap<impl Into<Vec<u8>> + Clone, impl Into<Vec<u8>> + Clone>,
+    ) -> Result<(), ElfError> {
+        let symbols = transform_map(symbols);
+
+        self.inner.rename_symbols(&symbols);
+
+        Ok(())
+    }
+
+    /// Rename symbols in the symbol table only.
+    pub fn rename_static_symbols(
+        &mut self,
+        symbols: &HashMap<impl Into<Vec<u8>> + Clone, impl Into<Vec<u8>> + Clone>,
+    ) -> Result<(), ElfError> {
+        let symbols = transform_map(symbols);
+
+        self.inner.elf_rename_symbols(&symbols);
+
+        Ok(())
+    }
+
+    /// Delete symbols from both the symbol table and the dynamic symbol table.
+    ///
+    /// Fails if dynamic relocations still refer to one of the dynamic symbols.
+    pub fn delete_symbols(&mut self, symbols: Vec<impl Into<Vec<u8>>>) -> Result<(), ElfError> {
+        let symbols = transform_set(symbols);
+
+        self.inner.delete_symbols(&symbols)?;
+
+        Ok(())
+    }
+
+    /// Delete symbols from the symbol table only.
+    pub fn delete_static_symbols(
+        &mut self,
+        symbols: Vec<impl Into<Vec<u8>>>,
+    ) -> Result<(), ElfError> {
+        let symbols = transform_set(symbols);
+
+        self.inner.elf_delete_symbols(&symbols);
+
+        Ok(())
+    }
+
+    /// Delete symbols from the dynamic symbol table only.
+    ///
+    /// Fails if dynamic relocations still refer to one of the symbols.
+    pub fn delete_dynamic_symbols(
+        &mut self,
+        symbols: Vec<impl Into<Vec<u8>>>,
+    ) -> Result<(), ElfError> {
+        let symbols = transform_set(symbols);
+
+        self.inner.elf_delete_dynamic_symbols(&symbols)?;
+
+        Ok(())
+    }
+
+    /// Delete sections from the ELF file, every section with one of the names.
+    ///
+    /// Sections referenced by the dynamic section or by a program header can't be deleted.
+    pub fn delete_sections(&mut self, sections: Vec<impl Into<Vec<u8>>>) -> Result<(), ElfError> {
+        let sections = transform_set(sections);
+
+        self.inner.delete_sections(&sections)?;
+
+        Ok(())
+    }
+
+    /// Rename sections in the ELF file.
+    pub fn rename_sections(
+        &mut self,
+        sections: &HashMap<impl Into<Vec<u8>> + Clone, impl Into<Vec<u8>> + Clone>,
+    ) -> Result<(), ElfError> {
+        let sections = transform_map(sections);
+
+        self.inner.rename_sections(&sections);
+
+        Ok(())
+    }
+
+    /// Add a new section with the given `SHF_*` flags and contents.
+    ///
+    /// Allocated sections are mapped into memory by a new `PT_LOAD` segment.
+    pub fn add_section(
+        &mut self,
+        name: &str,
+        flags: u64,
+        data: Vec<u8>,
+        alloc: bool,
+    ) -> Result<(), ElfError> {
+        self.inner
+            .elf_add_section(name.as_bytes().to_vec(), flags, data, alloc)?;
+
+        Ok(())
+    }
+
+    /// Replace the contents of an existing section.
+    pub fn update_section(&mut self, name: &str, data: Vec<u8>) -> Result<(), ElfError> {
+        self.inner.elf_update_section(name.as_bytes(), data)?;
+
+        Ok(())
+    }
+
+    /// Get the contents of a section.
+    pub fn section_data(&self, name: &str) -> Result<&[u8], ElfError> {
+        self.inner.elf_section_data(name.as_bytes())
+    }
+
     /// Set the page size for ELF file segment alignment.
     pub fn set_page_size(&mut self, page_size: u32) -> Result<(), ElfError> {
         self.inner.elf_set_page_size(page_size)?;
@@ -232,6 +635,17 @@ impl<'a> ElfContainer<'a> {
         self.inner.elf_get_page_size()
     }
 
+    /// Check the layout of the file as it was read.
+    pub fn check(&self) -> Vec<Problem> {
+        crate::check::check(self.data)
+    }
+
+    /// Check the file when it is written, and fail with [`ElfError::Check`] instead of
+    /// writing a broken file.
+    pub fn set_check_on_write(&mut self, check: bool) {
+        self.inner.set_check_on_write(check);
+    }
+
     pub fn write_to_path(&mut self, path: &Path) -> Result<(), ElfError> {
         self.inner.write_to_path(path)?;
         Ok(())
@@ -243,6 +657,10 @@ impl<'a> ElfContainer<'a> {
     }
 }
 
+fn transform_set(set: Vec<impl Into<Vec<u8>>>) -> HashSet<Vec<u8>> {
+    set.into_iter().map(Into::into).collect()
+}
+
 fn transform_map(
     map: &HashMap<impl Into<Vec<u8>> + Clone, impl Into<Vec<u8>> + Clone>,
 ) -> HashMap<Vec<u8>, Vec<u8>> {
@@ -3,8 +3,13 @@
 //! This crate provides tools for reading and modifying ELF binaries,
 //! including operations on runpaths, interpreters, sonames, and more.
 
+pub mod check;
 pub mod container;
+pub mod resolver;
 pub mod rewriter;
+mod util;
 
+pub use check::*;
 pub use container::*;
+pub use resolver::*;
 pub use rewriter::*;
@@ -0,0 +1,543 @@
+//! Emulate how the dynamic loader finds the dependencies of an ELF file.
+//!
+//! The binary is never executed, so this works for binaries built for other
+//! architectures, and with a `--sysroot` for binaries of other systems.
+//!
+//! The search order follows glibc's `ld.so`:
+//!
+//! 1. `DT_RPATH` of the object and of the objects that loaded it, unless the object
+//!    has a `DT_RUNPATH`;
+//! 2. `LD_LIBRARY_PATH`;
+//! 3. `DT_RUNPATH` of the object;
+//! 4. the directories listed in `/etc/ld.so.conf` and the files it includes;
+//! 5. the default directories.
+//!
+//! The last two are skipped for objects that have `DF_1_NODEFLIB` set.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use object::{
+    build, elf,
+    read::elf::{Dyn, FileHeader},
+    Endianness, FileKind,
+};
+use serde::Serialize;
+
+use crate::{
+    rewriter::{ElfError, Result},
+    util::{expand_tokens, find_token, wildcard_match, ElfIdentity},
+};
+
+/// Where a dependency was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchSource {
+    /// The `DT_NEEDED` entry contains a slash and is used as a path.
+    Path,
+    /// A `DT_RPATH` entry.
+    Rpath,
+    /// The `LD_LIBRARY_PATH` given to the resolver.
+    LdLibraryPath,
+    /// A `DT_RUNPATH` entry.
+    Runpath,
+    /// A directory from `/etc/ld.so.conf`.
+    LdSoConf,
+    /// One of the default directories.
+    DefaultDirs,
+}
+
+impl fmt::Display for SearchSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            SearchSource::Path => "path",
+            SearchSource::Rpath => "RPATH",
+            SearchSource::LdLibraryPath => "LD_LIBRARY_PATH",
+            SearchSource::Runpath => "RUNPATH",
+            SearchSource::LdSoConf => "ld.so.conf",
+            SearchSource::DefaultDirs => "default",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A resolved `DT_NEEDED` entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Dependency {
+    /// The name from the `DT_NEEDED` entry.
+    pub name: String,
+    /// The file the loader would use, or `None` if it wasn't found.
+    pub path: Option<PathBuf>,
+    /// Where the file was found.
+    pub source: Option<SearchSource>,
+    /// The dependencies of the file.
+    ///
+    /// Libraries are only expanded the first time they are loaded.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<Dependency>,
+}
+
+/// The dependencies of an ELF file, as the dynamic loader would resolve them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DependencyTree {
+    /// The file whose dependencies were resolved.
+    pub path: PathBuf,
+    /// The resolved `DT_NEEDED` entries of the file.
+    pub dependencies: Vec<Dependency>,
+}
+
+impl fmt::Display for DependencyTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.path.display())?;
+        write_dependencies(f, &self.dependencies, 1)
+    }
+}
+
+fn write_dependencies(
+    f: &mut fmt::Formatter<'_>,
+    dependencies: &[Dependency],
+    depth: usize,
+) -> fmt::Result {
+    for dependency in dependencies {
+        let indent = "    ".repeat(depth);
+        match &dependency.path {
+            Some(path) => writeln!(f, "{indent}{} => {}", dependency.name, path.display())?,
+            None => writeln!(f, "{indent}{} => not found", dependency.name)?,
+        }
+        write_dependencies(f, &dependency.dependencies, depth + 1)?;
+    }
+    Ok(())
+}
+
+/// Resolves the dependencies of ELF files the way the dynamic loader does.
+#[derive(Debug, Clone, Default)]
+pub struct Resolver {
+    sysroot: Option<PathBuf>,
+    library_path: Vec<String>,
+    platform: Option<String>,
+}
+
+impl Resolver {
+    /// Create a resolver that searches the root filesystem, without `LD_LIBRARY_PATH`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Search for absolute paths inside `sysroot` instead of `/`.
+    pub fn with_sysroot(mut self, sysroot: impl Into<PathBuf>) -> Self {
+        self.sysroot = Some(sysroot.into());
+        self
+    }
+
+    /// Search the directories of a colon separated `LD_LIBRARY_PATH`.
+    pub fn with_library_path(mut self, library_path: &str) -> Self {
+        self.library_path = split_path_list(library_path);
+        self
+    }
+
+    /// Set the value of `$PLATFORM`.
+    ///
+    /// By default it is derived from the machine of the file being resolved.
+    pub fn with_platform(mut self, platform: impl Into<String>) -> Self {
+        self.platform = Some(platform.into());
+        self
+    }
+
+    /// Resolve the dependencies of the ELF file at `path`, recursively.
+    pub fn resolve(&self, path: &Path) -> Result<DependencyTree> {
+        let data = std::fs::read(path)?;
+        let info = ObjectInfo::parse(&data)?;
+        let path = std::path::absolute(path)?;
+
+        let search = SearchDirs {
+            ld_so_conf: self.ld_so_conf_dirs(),
+            default: self.default_dirs(&info),
+            lib: if info.identity.is_64 { "lib64" } else { "lib" },
+            platform: self
+                .platform
+                .clone()
+                .or_else(|| platform_name(info.identity.machine).map(str::to_string)),
+        };
+
+        let mut objects = vec![LoadedObject {
+            path: path.clone(),
+            info,
+            names: Vec::new(),
+            parent: None,
+            source: None,
+            needed: Vec::new(),
+        }];
+
+        // Load breadth-first, like the dynamic loader does.
+        let mut index = 0;
+        while index < objects.len() {
+            let needed_names = objects[index].info.needed.clone();
+            for name in needed_names {
+                if let Some(loaded) = objects.iter().position(|object| object.provides(&name)) {
+                    objects[index].needed.push(Needed {
+                        name,
+                        object: Some(loaded),
+                        first: false,
+                    });
+                    continue;
+                }
+                let Some((path, info, source)) = self.find(&name, &objects, index, &search) else {
+                    objects[index].needed.push(Needed {
+                        name,
+                        object: None,
+                        first: false,
+                    });
+                    continue;
+                };
+                if let Some(loaded) = objects.iter().position(|object| object.path == path) {
+                    // The same file under another name.
+                    objects[loaded].names.push(name.clone());
+                    objects[index].needed.push(Needed {
+                        name,
+                        object: Some(loaded),
+                        first: false,
+                    });
+                    continue;
+                }
+                let loaded = objects.len();
+                objects.push(LoadedObject {
+                    path,
+                    info,
+                    names: vec![name.clone()],
+                    parent: Some(index),
+                    source: Some(source),
+                    needed: Vec::new(),
+                });
+                objects[index].needed.push(Needed {
+                    name,
+                    object: Some(loaded),
+                    first: true,
+                });
+            }
+            index += 1;
+        }
+
+        Ok(DependencyTree {
+            path,
+            dependencies: build_tree(&objects, 0),
+        })
+    }
+
+    /// Find the file for a `DT_NEEDED` entry of `objects[index]`.
+    fn find(
+        &self,
+        name: &str,
+        objects: &[LoadedObject],
+        index: usize,
+        search: &SearchDirs,
+    ) -> Option<(PathBuf, ObjectInfo, SearchSource)> {
+        let root = &objects[0];
+        let object = &objects[index];
+
+        if name.contains('/') {
+            let path = self.in_sysroot(name);
+            return self
+                .try_file(&path, &root.info)
+                .map(|info| (path, info, SearchSource::Path));
+        }
+
+        let mut candidates = Vec::new();
+        if object.info.runpath.is_empty() {
+            // The DT_RPATH of each object in the chain of loaders is used,
+            // as long as that object has no DT_RUNPATH.
+            let mut loader = Some(index);
+            while let Some(current) = loader {
+                let current = &objects[current];
+                if current.info.runpath.is_empty() {
+                    for entry in &current.info.rpath {
+                        candidates.push((self.expand(entry, current, search), SearchSource::Rpath));
+                    }
+                }
+                loader = current.parent;
+            }
+        }
+        for entry in &self.library_path {
+            candidates.push((
+                self.expand(entry, root, search),
+                SearchSource::LdLibraryPath,
+            ));
+        }
+        for entry in &object.info.runpath {
+            candidates.push((self.expand(entry, object, search), SearchSource::Runpath));
+        }
+        if !object.info.no_default_lib {
+            for dir in &search.ld_so_conf {
+                candidates.push((Some(dir.clone()), SearchSource::LdSoConf));
+            }
+            for dir in &search.default {
+                candidates.push((Some(dir.clone()), SearchSource::DefaultDirs));
+            }
+        }
+
+        candidates
+            .into_iter()
+            .filter_map(|(dir, source)| Some((dir?, source)))
+            .find_map(|(dir, source)| {
+                let path = dir.join(name);
+                self.try_file(&path, &root.info)
+                    .map(|info| (path, info, source))
+            })
+    }
+
+    /// Return the object at `path` if it exists and can be loaded together with `root`.
+    ///
+    /// Like the dynamic loader, files for another machine, class or byte order are skipped.
+    fn try_file(&self, path: &Path, root: &ObjectInfo) -> Option<ObjectInfo> {
+        let data = std::fs::read(path).ok()?;
+        let info = ObjectInfo::parse(&data).ok()?;
+        (info.identity == root.identity).then_some(info)
+    }
+
+    /// Expand the dynamic string tokens in a search path entry.
+    ///
+    /// Returns `None` if a token can't be expanded, in which case the loader ignores the entry.
+    fn expand(&self, entry: &str, object: &LoadedObject, search: &SearchDirs) -> Option<PathBuf> {
+        if entry.is_empty() {
+            return None;
+        }
+        let origin = object.path.parent()?.to_string_lossy().into_owned();
+        let uses_origin = find_token(entry, "ORIGIN").is_some();
+        let expanded = expand_tokens(
+            entry,
+            &[
+                ("ORIGIN", Some(origin.as_str())),
+                ("LIB", Some(search.lib)),
+                ("PLATFORM", search.platform.as_deref()),
+            ],
+        )?;
+        if uses_origin {
+            // $ORIGIN is already a path on this system.
+            Some(PathBuf::from(expanded))
+        } else {
+            Some(self.in_sysroot(&expanded))
+        }
+    }
+
+    /// Map an absolute path on the target system to a path on this system.
+    fn in_sysroot(&self, path: &str) -> PathBuf {
+        match (&self.sysroot, path.strip_prefix('/')) {
+            (Some(sysroot), Some(relative)) => sysroot.join(relative),
+            _ => PathBuf::from(path),
+        }
+    }
+
+    /// Read the directories listed in `/etc/ld.so.conf` and the files it includes.
+    fn ld_so_conf_dirs(&self) -> Vec<PathBuf> {
+        let mut dirs = Vec::new();
+        self.read_ld_so_conf(&self.in_sysroot("/etc/ld.so.conf"), &mut dirs, 0);
+        dirs
+    }
+
+    fn read_ld_so_conf(&self, path: &Path, dirs: &mut Vec<PathBuf>, depth: usize) {
+        // Guard against include loops.
+        if depth > 16 {
+            return;
+        }
+        let Ok(contents) = std::fs::read_to_string(path) else {
+            return;
+        };
+        for line in contents.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() || line.starts_with("hwcap ") {
+                continue;
+            }
+            if let Some(patterns) = line.strip_prefix("include ") {
+                for pattern in patterns.split_whitespace() {
+                    let pattern = if pattern.starts_with('/') {
+                        self.in_sysroot(pattern)
+                    } else {
+                        path.parent().unwrap_or(Path::new("")).join(pattern)
+                    };
+                    for include in expand_glob(&pattern) {
+                        self.read_ld_so_conf(&include, dirs, depth + 1);
+                    }
+                }
+                continue;
+            }
+            // Old configurations may give a library type after the directory.
+            let dir = line.split('=').next().unwrap_or_default().trim();
+            let dir = self.in_sysroot(dir);
+            if !dirs.contains(&dir) {
+                dirs.push(dir);
+            }
+        }
+    }
+
+    fn default_dirs(&self, info: &ObjectInfo) -> Vec<PathBuf> {
+        let dirs: &[&str] = if info.identity.is_64 {
+            &["/lib64", "/usr/lib64", "/lib", "/usr/lib"]
+        } else {
+            &["/lib", "/usr/lib"]
+        };
+        dirs.iter().map(|dir| self.in_sysroot(dir)).collect()
+    }
+}
+
+/// Directories and token values shared by all objects in a resolution.
+struct SearchDirs {
+    ld_so_conf: Vec<PathBuf>,
+    default: Vec<PathBuf>,
+    lib: &'static str,
+    platform: Option<String>,
+}
+
+struct LoadedObject {
+    path: PathBuf,
+    info: ObjectInfo,
+    /// The `DT_NEEDED` names this object was loaded for.
+    names: Vec<String>,
+    /// The object that caused this one to be loaded.
+    parent: Option<usize>,
+    source: Option<SearchSource>,
+    needed: Vec<Needed>,
+}
+
+impl LoadedObject {
+    /// Whether the loader would reuse this object for a `DT_NEEDED` entry.
+    fn provides(&self, name: &str) -> bool {
+        self.names.iter().any(|loaded| loaded == name) || self.info.soname.as_deref() == Some(name)
+    }
+}
+
+struct Needed {
+    name: String,
+    object: Option<usize>,
+    /// Whether the object was first loaded for this entry.
+    first: bool,
+}
+
+fn build_tree(objects: &[LoadedObject], index: usize) -> Vec<Dependency> {
+    objects[index]
+        .needed
+        .iter()
+        .map(|needed| {
+            let object = needed.object.map(|object| &objects[object]);
+            Dependency {
+                name: needed.name.clone(),
+                path: object.map(|object| object.path.clone()),
+                source: object.and_then(|object| object.source),
+                dependencies: match needed.object {
+                    Some(object) if needed.first => build_tree(objects, object),
+                    _ => Vec::new(),
+                },
+            }
+        })
+        .collect()
+}
+
+/// The parts of an ELF file that matter for finding its dependencies.
+#[derive(Debug, Clone, Default)]
+struct ObjectInfo {
+    identity: ElfIdentity,
+    soname: Option<String>,
+    needed: Vec<String>,
+    rpath: Vec<String>,
+    runpath: Vec<String>,
+    no_default_lib: bool,
+}
+
+impl ObjectInfo {
+    fn parse(data: &[u8]) -> Result<Self> {
+        let info = match FileKind::parse(data) {
+            Ok(FileKind::Elf32) => Self::parse_elf::<elf::FileHeader32<Endianness>>(data),
+            Ok(FileKind::Elf64) => Self::parse_elf::<elf::FileHeader64<Endianness>>(data),
+            Ok(_) => {
+                return Err(ElfError::Modify(
+                    "Not an ELF file; can't resolve its dependencies".to_string(),
+                ))
+            }
+            Err(error) => Err(error),
+        };
+        info.map_err(|error| ElfError::Parse(build::Error::from(error)))
+    }
+
+    fn parse_elf<Elf: FileHeader<Endian = Endianness>>(data: &[u8]) -> object::read::Result<Self> {
+        let header = Elf::parse(data)?;
+        let endian = header.endian()?;
+        let mut info = ObjectInfo {
+            identity: ElfIdentity::from_header(header, endian),
+            ..Default::default()
+        };
+
+        let sections = header.sections(endian, data)?;
+        let Some((dynamic, link)) = sections.dynamic(endian, data)? else {
+            return Ok(info);
+        };
+        let strings = sections.strings(endian, data, link)?;
+        for entry in dynamic {
+            let Some(tag) = entry.tag32(endian) else {
+                continue;
+            };
+            if tag == elf::DT_NULL {
+                break;
+            }
+            if tag == elf::DT_FLAGS_1 {
+                info.no_default_lib =
+                    entry.d_val(endian).into() & u64::from(elf::DF_1_NODEFLIB) != 0;
+                continue;
+            }
+            if !entry.is_string(endian) {
+                continue;
+            }
+            let value = String::from_utf8_lossy(entry.string(endian, strings)?).into_owned();
+            match tag {
+                elf::DT_NEEDED => info.needed.push(value),
+                elf::DT_SONAME => info.soname = Some(value),
+                elf::DT_RPATH => info.rpath.extend(split_path_list(&value)),
+                elf::DT_RUNPATH => info.runpath.extend(split_path_list(&value)),
+                _ => {}
+            }
+        }
+        Ok(info)
+    }
+}
+
+fn split_path_list(list: &str) -> Vec<String> {
+    list.split([':', ';']).map(str::to_string).collect()
+}
+
+/// Expand a path whose last component may contain `*` or `?` wildcards, in sorted order.
+fn expand_glob(pattern: &Path) -> Vec<PathBuf> {
+    let Some(file_pattern) = pattern.file_name().and_then(|name| name.to_str()) else {
+        return Vec::new();
+    };
+    if !file_pattern.contains(['*', '?']) {
+        return vec![pattern.to_path_buf()];
+    }
+    let dir = pattern.parent().unwrap_or(Path::new(""));
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .is_some_and(|name| wildcard_match(file_pattern, name))
+        })
+        .map(|entry| entry.path())
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// The value of `$PLATFORM` that the kernel passes for a machine.
+fn platform_name(machine: u16) -> Option<&'static str> {
+    match machine {
+        elf::EM_X86_64 => Some("x86_64"),
+        elf::EM_386 => Some("i686"),
+        elf::EM_AARCH64 => Some("aarch64"),
+        elf::EM_ARM => Some("v7l"),
+        elf::EM_PPC64 => Some("power8"),
+        elf::EM_S390 => Some("z13"),
+        _ => None,
+    }
+}
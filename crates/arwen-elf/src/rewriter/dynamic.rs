@@ -0,0 +1,503 @@
+use std::fmt;
+
+use object::{
+    build::{self, elf::SectionData},
+    elf,
+};
+use serde::Serialize;
+
+use super::{ElfError, Result};
+
+/// Size in bytes of the `DT_RELR` relocation table.
+///
+/// Not yet exported by the `object` crate.
+pub(crate) const DT_RELRSZ: u32 = 35;
+/// Address of the `DT_RELR` relocation table.
+pub(crate) const DT_RELR: u32 = 36;
+/// Size in bytes of a `DT_RELR` entry.
+pub(crate) const DT_RELRENT: u32 = 37;
+
+/// The known bits of the `DT_FLAGS` entry.
+pub(crate) const DT_FLAGS_NAMES: &[(u32, &str)] = &[
+    (elf::DF_ORIGIN, "ORIGIN"),
+    (elf::DF_SYMBOLIC, "SYMBOLIC"),
+    (elf::DF_TEXTREL, "TEXTREL"),
+    (elf::DF_BIND_NOW, "BIND_NOW"),
+    (elf::DF_STATIC_TLS, "STATIC_TLS"),
+];
+
+/// The known bits of the `DT_FLAGS_1` entry.
+pub(crate) const DT_FLAGS_1_NAMES: &[(u32, &str)] = &[
+    (elf::DF_1_NOW, "NOW"),
+    (elf::DF_1_GLOBAL, "GLOBAL"),
+    (elf::DF_1_GROUP, "GROUP"),
+    (elf::DF_1_NODELETE, "NODELETE"),
+    (elf::DF_1_LOADFLTR, "LOADFLTR"),
+    (elf::DF_1_INITFIRST, "INITFIRST"),
+    (elf::DF_1_NOOPEN, "NOOPEN"),
+    (elf::DF_1_ORIGIN, "ORIGIN"),
+    (elf::DF_1_DIRECT, "DIRECT"),
+    (elf::DF_1_TRANS, "TRANS"),
+    (elf::DF_1_INTERPOSE, "INTERPOSE"),
+    (elf::DF_1_NODEFLIB, "NODEFLIB"),
+    (elf::DF_1_NODUMP, "NODUMP"),
+    (elf::DF_1_CONFALT, "CONFALT"),
+    (elf::DF_1_ENDFILTEE, "ENDFILTEE"),
+    (elf::DF_1_DISPRELDNE, "DISPRELDNE"),
+    (elf::DF_1_DISPRELPND, "DISPRELPND"),
+    (elf::DF_1_NODIRECT, "NODIRECT"),
+    (elf::DF_1_IGNMULDEF, "IGNMULDEF"),
+    (elf::DF_1_NOKSYMS, "NOKSYMS"),
+    (elf::DF_1_NOHDR, "NOHDR"),
+    (elf::DF_1_EDITED, "EDITED"),
+    (elf::DF_1_NORELOC, "NORELOC"),
+    (elf::DF_1_SYMINTPOSE, "SYMINTPOSE"),
+    (elf::DF_1_GLOBAUDIT, "GLOBAUDIT"),
+    (elf::DF_1_SINGLETON, "SINGLETON"),
+    (elf::DF_1_STUB, "STUB"),
+    (elf::DF_1_PIE, "PIE"),
+];
+
+/// A single entry of the dynamic section.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DynamicEntry {
+    /// The raw `d_tag` value.
+    pub tag: u32,
+    /// The name of the tag, e.g. `DT_NEEDED`.
+    ///
+    /// Unknown tags are named by their hexadecimal value.
+    pub name: String,
+    /// The value of the entry.
+    pub value: DynamicValue,
+    /// The names of the bits set in a `DT_FLAGS` or `DT_FLAGS_1` entry.
+    ///
+    /// Unknown bits are listed by their hexadecimal value.
+    /// Empty for all other tags.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub flags: Vec<String>,
+}
+
+/// The value of a dynamic section entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+pub enum DynamicValue {
+    /// A value that is an offset into the dynamic string table.
+    String(String),
+    /// A value that is an address, size or plain integer.
+    Integer(u64),
+}
+
+impl DynamicEntry {
+    pub(crate) fn new(builder: &build::elf::Builder, entry: &build::elf::Dynamic) -> Self {
+        let tag = entry.tag();
+        let value = match entry {
+            build::elf::Dynamic::String { val, .. } => {
+                DynamicValue::String(String::from_utf8_lossy(val).into_owned())
+            }
+            build::elf::Dynamic::Integer { val, .. } => DynamicValue::Integer(*val),
+            build::elf::Dynamic::Auto { tag } => DynamicValue::Integer(auto_value(builder, *tag)),
+        };
+        let flags = match (&value, tag) {
+            (DynamicValue::Integer(val), elf::DT_FLAGS) => flag_names(*val, DT_FLAGS_NAMES),
+            (DynamicValue::Integer(val), elf::DT_FLAGS_1) => flag_names(*val, DT_FLAGS_1_NAMES),
+            _ => Vec::new(),
+        };
+        Self {
+            tag,
+            name: tag_name(tag).map_or_else(|| format!("{tag:#x}"), str::to_string),
+            value,
+            flags,
+        }
+    }
+}
+
+impl fmt::Display for DynamicEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:<20} ", self.name)?;
+        match &self.value {
+            DynamicValue::String(val) => write!(f, "{val}")?,
+            DynamicValue::Integer(val) => write!(f, "{val:#x}")?,
+        }
+        for flag in &self.flags {
+            write!(f, " {flag}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The dynamic tag that a list of library search paths was read from.
+///
+/// The dynamic linker searches `DT_RPATH` before `LD_LIBRARY_PATH`, and `DT_RUNPATH`
+/// after it. `DT_RPATH` is ignored entirely when `DT_RUNPATH` is present.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum RunPathKind {
+    /// The path list comes from a `DT_RPATH` entry.
+    Rpath,
+    /// The path list comes from a `DT_RUNPATH` entry.
+    Runpath,
+}
+
+impl RunPathKind {
+    /// Return the dynamic tag for this kind.
+    pub fn tag(self) -> u32 {
+        match self {
+            RunPathKind::Rpath => elf::DT_RPATH,
+            RunPathKind::Runpath => elf::DT_RUNPATH,
+        }
+    }
+}
+
+impl fmt::Display for RunPathKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunPathKind::Rpath => write!(f, "RPATH"),
+            RunPathKind::Runpath => write!(f, "RUNPATH"),
+        }
+    }
+}
+
+/// The library search paths of a `DT_RPATH` or `DT_RUNPATH` entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RunPath {
+    /// The tag the paths were read from.
+    pub kind: RunPathKind,
+    /// The colon separated paths of the entry.
+    pub entries: Vec<String>,
+}
+
+impl RunPath {
+    pub(crate) fn new(kind: RunPathKind, value: &[u8]) -> Self {
+        let value = String::from_utf8_lossy(value);
+        let entries = if value.is_empty() {
+            Vec::new()
+        } else {
+            value.split(':').map(str::to_string).collect()
+        };
+        Self { kind, entries }
+    }
+}
+
+impl fmt::Display for RunPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.entries.join(":"))
+    }
+}
+
+/// Why shrinking kept or removed a search path entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "reason", content = "library")]
+pub enum ShrinkReason {
+    /// The directory holds a compatible library named by a `DT_NEEDED` entry.
+    ProvidesLibrary(String),
+    /// The directory holds no compatible library named by a `DT_NEEDED` entry.
+    NoNeededLibrary,
+    /// The directory doesn't start with one of the allowed prefixes.
+    PrefixNotAllowed,
+    /// The entry uses `$ORIGIN`, but the location of the file is unknown.
+    UnknownOrigin,
+    /// The entry is relative or uses a token other than `$ORIGIN`, so it can't be checked.
+    Unresolved,
+}
+
+impl fmt::Display for ShrinkReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShrinkReason::ProvidesLibrary(library) => write!(f, "provides {library}"),
+            ShrinkReason::NoNeededLibrary => write!(f, "no needed library"),
+            ShrinkReason::PrefixNotAllowed => write!(f, "prefix not allowed"),
+            ShrinkReason::UnknownOrigin => write!(f, "$ORIGIN is unknown"),
+            ShrinkReason::Unresolved => write!(f, "can't be checked"),
+        }
+    }
+}
+
+/// The decision shrinking made for one search path entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ShrinkEntry {
+    /// The tag the entry was read from.
+    pub kind: RunPathKind,
+    /// The entry as written in the dynamic section.
+    pub path: String,
+    /// Whether the entry was kept.
+    pub kept: bool,
+    /// Why the entry was kept or removed.
+    #[serde(flatten)]
+    pub reason: ShrinkReason,
+}
+
+/// The result of shrinking the `DT_RPATH` and `DT_RUNPATH` entries of a file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ShrinkReport {
+    /// Every entry that was checked, in order.
+    pub entries: Vec<ShrinkEntry>,
+}
+
+impl ShrinkReport {
+    /// Return the entries that were kept.
+    pub fn kept(&self) -> impl Iterator<Item = &ShrinkEntry> {
+        self.entries.iter().filter(|entry| entry.kept)
+    }
+
+    /// Return the entries that were removed.
+    pub fn removed(&self) -> impl Iterator<Item = &ShrinkEntry> {
+        self.entries.iter().filter(|entry| !entry.kept)
+    }
+}
+
+impl fmt::Display for ShrinkReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.entries {
+            let action = if entry.kept { "kept" } else { "removed" };
+            writeln!(
+                f,
+                "{action} {} {} ({})",
+                entry.kind, entry.path, entry.reason
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Where to insert new `DT_NEEDED` entries among the existing ones.
+///
+/// The order of the `DT_NEEDED` entries is the order in which the dynamic loader
+/// searches the libraries for symbols.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NeededPosition {
+    /// Before the `DT_NEEDED` entry at this index, or after the last one if the index
+    /// is the number of entries.
+    Index(usize),
+    /// Right before the entry of this library.
+    Before(Vec<u8>),
+    /// Right after the entry of this library.
+    After(Vec<u8>),
+}
+
+impl Default for NeededPosition {
+    fn default() -> Self {
+        NeededPosition::Index(0)
+    }
+}
+
+/// A set of bits for the `DT_FLAGS` and `DT_FLAGS_1` entries.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct DynamicFlags {
+    /// The `DF_*` bits of the `DT_FLAGS` entry.
+    pub flags: u64,
+    /// The `DF_1_*` bits of the `DT_FLAGS_1` entry.
+    pub flags_1: u64,
+}
+
+impl DynamicFlags {
+    /// Build a set of flags from symbolic names such as `BIND_NOW`, `NOW` or `PIE`.
+    ///
+    /// Names are matched case-insensitively and may carry a `DF_` or `DF_1_` prefix.
+    /// A bare name is looked up in the `DT_FLAGS` bits first, so `ORIGIN` selects
+    /// `DF_ORIGIN`; use `DF_1_ORIGIN` for the `DT_FLAGS_1` bit.
+    pub fn from_names<S: AsRef<str>>(names: &[S]) -> Result<Self> {
+        let mut result = Self::default();
+        for name in names {
+            let name = name.as_ref().to_uppercase();
+            let flags = if let Some(name) = name.strip_prefix("DF_1_") {
+                find_flag(name, DT_FLAGS_1_NAMES).map(|bit| (0, bit))
+            } else if let Some(name) = name.strip_prefix("DF_") {
+                find_flag(name, DT_FLAGS_NAMES).map(|bit| (bit, 0))
+            } else {
+                find_flag(&name, DT_FLAGS_NAMES)
+                    .map(|bit| (bit, 0))
+                    .or_else(|| find_flag(&name, DT_FLAGS_1_NAMES).map(|bit| (0, bit)))
+            };
+            let Some((flags, flags_1)) = flags else {
+                return Err(ElfError::Modify(format!("Unknown dynamic flag {name}")));
+            };
+            result.flags |= u64::from(flags);
+            result.flags_1 |= u64::from(flags_1);
+        }
+        Ok(result)
+    }
+
+    /// Return the names of the bits set in the `DT_FLAGS` entry.
+    pub fn flags_names(&self) -> Vec<String> {
+        flag_names(self.flags, DT_FLAGS_NAMES)
+    }
+
+    /// Return the names of the bits set in the `DT_FLAGS_1` entry.
+    pub fn flags_1_names(&self) -> Vec<String> {
+        flag_names(self.flags_1, DT_FLAGS_1_NAMES)
+    }
+}
+
+fn find_flag(name: &str, names: &[(u32, &str)]) -> Option<u32> {
+    names
+        .iter()
+        .find(|(_, known)| *known == name)
+        .map(|(bit, _)| *bit)
+}
+
+/// Return the names of the bits set in `value`, using the given table of known bits.
+pub(crate) fn flag_names(value: u64, names: &[(u32, &str)]) -> Vec<String> {
+    let mut remaining = value;
+    let mut flags = Vec::new();
+    for (bit, name) in names {
+        let bit = u64::from(*bit);
+        if value & bit != 0 {
+            flags.push((*name).to_string());
+            remaining &= !bit;
+        }
+    }
+    if remaining != 0 {
+        flags.push(format!("{remaining:#x}"));
+    }
+    flags
+}
+
+/// Compute the value of an entry whose value is only known when the file is written.
+///
+/// The value is derived from the current state of the builder, so it matches the
+/// input file until sections are moved.
+fn auto_value(builder: &build::elf::Builder, tag: u32) -> u64 {
+    let section_address = |matches: fn(&SectionData) -> bool| {
+        builder
+            .sections
+            .iter()
+            .find(|section| matches(&section.data))
+            .map_or(0, |section| section.sh_addr)
+    };
+    match tag {
+        elf::DT_SYMTAB => section_address(|data| matches!(data, SectionData::DynamicSymbol)),
+        elf::DT_STRTAB => section_address(|data| matches!(data, SectionData::DynamicString)),
+        elf::DT_HASH => section_address(|data| matches!(data, SectionData::Hash)),
+        elf::DT_GNU_HASH => section_address(|data| matches!(data, SectionData::GnuHash)),
+        elf::DT_VERSYM => section_address(|data| matches!(data, SectionData::GnuVersym)),
+        elf::DT_VERDEF => section_address(|data| matches!(data, SectionData::GnuVerdef)),
+        elf::DT_VERNEED => section_address(|data| matches!(data, SectionData::GnuVerneed)),
+        elf::DT_STRSZ => builder
+            .sections
+            .iter()
+            .find(|section| matches!(section.data, SectionData::DynamicString))
+            .map_or(0, |section| section.sh_size),
+        elf::DT_VERDEFNUM => {
+            let defs = builder
+                .versions
+                .iter()
+                .filter(|version| matches!(version.data, build::elf::VersionData::Def(_)))
+                .count();
+            (defs + usize::from(builder.version_base.is_some())) as u64
+        }
+        elf::DT_VERNEEDNUM => builder.version_files.count() as u64,
+        _ => 0,
+    }
+}
+
+/// Return true if the value of the dynamic tag is a virtual address.
+pub(crate) fn is_address_tag(tag: u32) -> bool {
+    matches!(
+        tag,
+        elf::DT_PLTGOT
+            | elf::DT_HASH
+            | elf::DT_STRTAB
+            | elf::DT_SYMTAB
+            | elf::DT_RELA
+            | elf::DT_INIT
+            | elf::DT_FINI
+            | elf::DT_REL
+            | elf::DT_JMPREL
+            | elf::DT_INIT_ARRAY
+            | elf::DT_FINI_ARRAY
+            | elf::DT_PREINIT_ARRAY
+            | elf::DT_SYMTAB_SHNDX
+            | DT_RELR
+            | elf::DT_GNU_HASH
+            | elf::DT_TLSDESC_PLT
+            | elf::DT_TLSDESC_GOT
+            | elf::DT_GNU_CONFLICT
+            | elf::DT_GNU_LIBLIST
+            | elf::DT_CONFIG
+            | elf::DT_DEPAUDIT
+            | elf::DT_AUDIT
+            | elf::DT_PLTPAD
+            | elf::DT_MOVETAB
+            | elf::DT_SYMINFO
+            | elf::DT_VERSYM
+            | elf::DT_VERDEF
+            | elf::DT_VERNEED
+    )
+}
+
+/// Return the name of a dynamic tag, e.g. `DT_NEEDED`.
+pub fn tag_name(tag: u32) -> Option<&'static str> {
+    let name = match tag {
+        elf::DT_NULL => "DT_NULL",
+        elf::DT_NEEDED => "DT_NEEDED",
+        elf::DT_PLTRELSZ => "DT_PLTRELSZ",
+        elf::DT_PLTGOT => "DT_PLTGOT",
+        elf::DT_HASH => "DT_HASH",
+        elf::DT_STRTAB => "DT_STRTAB",
+        elf::DT_SYMTAB => "DT_SYMTAB",
+        elf::DT_RELA => "DT_RELA",
+        elf::DT_RELASZ => "DT_RELASZ",
+        elf::DT_RELAENT => "DT_RELAENT",
+        elf::DT_STRSZ => "DT_STRSZ",
+        elf::DT_SYMENT => "DT_SYMENT",
+        elf::DT_INIT => "DT_INIT",
+        elf::DT_FINI => "DT_FINI",
+        elf::DT_SONAME => "DT_SONAME",
+        elf::DT_RPATH => "DT_RPATH",
+        elf::DT_SYMBOLIC => "DT_SYMBOLIC",
+        elf::DT_REL => "DT_REL",
+        elf::DT_RELSZ => "DT_RELSZ",
+        elf::DT_RELENT => "DT_RELENT",
+        elf::DT_PLTREL => "DT_PLTREL",
+        elf::DT_DEBUG => "DT_DEBUG",
+        elf::DT_TEXTREL => "DT_TEXTREL",
+        elf::DT_JMPREL => "DT_JMPREL",
+        elf::DT_BIND_NOW => "DT_BIND_NOW",
+        elf::DT_INIT_ARRAY => "DT_INIT_ARRAY",
+        elf::DT_FINI_ARRAY => "DT_FINI_ARRAY",
+        elf::DT_INIT_ARRAYSZ => "DT_INIT_ARRAYSZ",
+        elf::DT_FINI_ARRAYSZ => "DT_FINI_ARRAYSZ",
+        elf::DT_RUNPATH => "DT_RUNPATH",
+        elf::DT_FLAGS => "DT_FLAGS",
+        elf::DT_PREINIT_ARRAY => "DT_PREINIT_ARRAY",
+        elf::DT_PREINIT_ARRAYSZ => "DT_PREINIT_ARRAYSZ",
+        elf::DT_SYMTAB_SHNDX => "DT_SYMTAB_SHNDX",
+        DT_RELRSZ => "DT_RELRSZ",
+        DT_RELR => "DT_RELR",
+        DT_RELRENT => "DT_RELRENT",
+        elf::DT_GNU_PRELINKED => "DT_GNU_PRELINKED",
+        elf::DT_GNU_CONFLICTSZ => "DT_GNU_CONFLICTSZ",
+        elf::DT_GNU_LIBLISTSZ => "DT_GNU_LIBLISTSZ",
+        elf::DT_CHECKSUM => "DT_CHECKSUM",
+        elf::DT_PLTPADSZ => "DT_PLTPADSZ",
+        elf::DT_MOVEENT => "DT_MOVEENT",
+        elf::DT_MOVESZ => "DT_MOVESZ",
+        elf::DT_FEATURE_1 => "DT_FEATURE_1",
+        elf::DT_POSFLAG_1 => "DT_POSFLAG_1",
+        elf::DT_SYMINSZ => "DT_SYMINSZ",
+        elf::DT_SYMINENT => "DT_SYMINENT",
+        elf::DT_GNU_HASH => "DT_GNU_HASH",
+        elf::DT_TLSDESC_PLT => "DT_TLSDESC_PLT",
+        elf::DT_TLSDESC_GOT => "DT_TLSDESC_GOT",
+        elf::DT_GNU_CONFLICT => "DT_GNU_CONFLICT",
+        elf::DT_GNU_LIBLIST => "DT_GNU_LIBLIST",
+        elf::DT_CONFIG => "DT_CONFIG",
+        elf::DT_DEPAUDIT => "DT_DEPAUDIT",
+        elf::DT_AUDIT => "DT_AUDIT",
+        elf::DT_PLTPAD => "DT_PLTPAD",
+        elf::DT_MOVETAB => "DT_MOVETAB",
+        elf::DT_SYMINFO => "DT_SYMINFO",
+        elf::DT_VERSYM => "DT_VERSYM",
+        elf::DT_RELACOUNT => "DT_RELACOUNT",
+        elf::DT_RELCOUNT => "DT_RELCOUNT",
+        elf::DT_FLAGS_1 => "DT_FLAGS_1",
+        elf::DT_VERDEF => "DT_VERDEF",
+        elf::DT_VERDEFNUM => "DT_VERDEFNUM",
+        elf::DT_VERNEED => "DT_VERNEED",
+        elf::DT_VERNEEDNUM => "DT_VERNEEDNUM",
+        elf::DT_AUXILIARY => "DT_AUXILIARY",
+        elf::DT_FILTER => "DT_FILTER",
+        _ => return None,
+    };
+    Some(name)
+}
@@ -7,7 +7,18 @@ use object::{
     elf,
 };
 
-use super::{ElfError, Result};
+use super::{
+    dynamic::{DT_RELR, DT_RELRSZ},
+    ElfError, Result,
+};
+
+/// Pairs of dynamic entries that give the address and size of a table of dynamic relocations.
+const RELOCATION_TAGS: [(u32, u32); 4] = [
+    (elf::DT_RELA, elf::DT_RELASZ),
+    (elf::DT_REL, elf::DT_RELSZ),
+    (elf::DT_JMPREL, elf::DT_PLTRELSZ),
+    (DT_RELR, DT_RELRSZ),
+];
 
 enum BlockKind {
     FileHeader,
@@ -28,21 +39,44 @@ struct Block<'a> {
 
 /// Move sections between segments if needed, and assign file offsets to segments and sections.
 ///
-/// Does not change the size of existing `PT_LOAD` segments, but may add new segments.
-// TODO: allow changing size of existing `PT_LOAD` segments
+/// Sections that need to move are appended to an existing `PT_LOAD` segment with the same
+/// permissions when there is room after its end. Segments that only contain movable sections,
+/// such as the ones added by a previous edit, are emptied and laid out again. A new segment is
+/// only added when the sections don't fit anywhere else, so that the number of segments stays
+/// stable across repeated edits.
 pub(crate) fn move_sections(builder: &mut build::elf::Builder) -> Result<()> {
     builder.delete_orphans();
     builder.delete_unused_versions();
+    // This must use the section sizes from the input file.
+    let relocation_tables = find_relocation_tables(builder);
+    let movable_relocations: Vec<_> = relocation_tables
+        .iter()
+        .map(|table| table.section)
+        .collect();
     builder.set_section_sizes();
 
     let mut added_p_flags = Vec::new();
     let mut added_segments = 0;
+    let mut emptied_segments = Vec::new();
 
     // Loop until we reach a fixed point for the number of additional segments needed.
     loop {
-        let mut move_sections = find_move_sections(builder, added_segments)?;
+        let mut move_sections = find_move_sections(builder, added_segments, &movable_relocations)?;
         if move_sections.is_empty() {
-            return Ok(());
+            break;
+        }
+
+        // Empty the segments that only contain movable sections, so that they can be reused.
+        for segment_id in find_reusable_segments(builder, &move_sections, &movable_relocations) {
+            let segment = builder.segments.get_mut(segment_id);
+            for id in segment.sections.drain(..) {
+                if !move_sections.contains(&id) {
+                    move_sections.push(id);
+                }
+            }
+            segment.p_filesz = 0;
+            segment.p_memsz = 0;
+            emptied_segments.push(segment_id);
         }
 
         // Calculate the number of additional PT_LOAD segments needed.
@@ -80,23 +114,42 @@ pub(crate) fn move_sections(builder: &mut build::elf::Builder) -> Result<()> {
             split_segments += 1;
         }
 
-        // Check if we have reached a fixed point for the number of additional segments needed.
-        if added_segments < split_segments + added_p_flags.len() {
-            added_segments = split_segments + added_p_flags.len();
-            continue;
-        }
-
-        // Add the PT_LOAD segments and append sections to them.
-        // Try to keep the same order of sections in the new segments.
+        // Try to keep the same order of sections in the new locations.
         move_sections.sort_by_key(|id| {
             let section = builder.sections.get(*id);
             (section.sh_addr, section.sh_size)
         });
-        for p_flags in added_p_flags {
-            // TODO: reuse segments that only contain movable sections
-            let segment = builder
-                .segments
-                .add_load_segment(p_flags, builder.load_align);
+
+        // Find an existing PT_LOAD segment with room for the sections of each p_flags.
+        let mut targets = Vec::new();
+        let mut new_segments = 0;
+        for p_flags in &added_p_flags {
+            let sections: Vec<_> = move_sections
+                .iter()
+                .copied()
+                .filter(|id| builder.sections.get(*id).p_flags() == *p_flags)
+                .collect();
+            let target = find_segment_with_room(builder, *p_flags, &sections, &move_sections);
+            if target.is_none() {
+                new_segments += 1;
+            }
+            targets.push((*p_flags, target));
+        }
+
+        // Check if we have reached a fixed point for the number of additional segments needed.
+        if added_segments < split_segments + new_segments {
+            added_segments = split_segments + new_segments;
+            continue;
+        }
+
+        // Append sections to the chosen PT_LOAD segments, adding new segments where needed.
+        for (p_flags, target) in targets {
+            let segment = match target {
+                Some(segment_id) => builder.segments.get_mut(segment_id),
+                None => builder
+                    .segments
+                    .add_load_segment(p_flags, builder.load_align),
+            };
             for id in &move_sections {
                 let section = builder.sections.get_mut(*id);
                 if p_flags == section.p_flags() {
@@ -105,6 +158,14 @@ pub(crate) fn move_sections(builder: &mut build::elf::Builder) -> Result<()> {
             }
         }
 
+        // Emptied segments that weren't reused would map nothing.
+        for segment_id in emptied_segments {
+            let segment = builder.segments.get_mut(segment_id);
+            if segment.sections.is_empty() {
+                segment.delete = true;
+            }
+        }
+
         // Split or move non-PT_LOAD segments that contain sections that have been moved.
         let sections = &builder.sections;
         let mut split_segments = Vec::new();
@@ -166,16 +227,26 @@ pub(crate) fn move_sections(builder: &mut build::elf::Builder) -> Result<()> {
             segment.p_filesz = size;
             segment.p_memsz = size;
         }
-        return Ok(());
+        break;
     }
+
+    // The interpreter can grow in place when the space after it was freed.
+    let sections = &builder.sections;
+    for segment in &mut builder.segments {
+        if segment.p_type == elf::PT_INTERP && !segment.sections.is_empty() {
+            segment.recalculate_ranges(sections);
+        }
+    }
+
+    update_relocation_tables(builder, &relocation_tables);
+    Ok(())
 }
 
 pub(crate) fn find_move_sections(
     builder: &build::elf::Builder,
     added_segments: usize,
+    movable_relocations: &[build::elf::SectionId],
 ) -> Result<Vec<build::elf::SectionId>> {
-    use build::elf::SectionData;
-
     let mut move_sections = Vec::new();
     let mut blocks = Vec::new();
     let file_header_size = builder.file_header_size() as u64;
@@ -244,35 +315,7 @@ pub(crate) fn find_move_sections(
             // Uninitialized TLS sections are not part of the address space.
             continue;
         }
-        let move_priority = match &section.data {
-            // Can't move sections whose address may referenced from
-            // a section that we can't rewrite.
-            SectionData::Data(_) => {
-                if Some(section.id()) == interp {
-                    1
-                } else {
-                    0
-                }
-            }
-            SectionData::UninitializedData(_) | SectionData::Dynamic(_) => 0,
-            // TODO: Can be referenced by dynamic entries, but we don't support that yet.
-            SectionData::DynamicRelocation(_) => 0,
-            // None of these can be referenced by address that I am aware of.
-            SectionData::Relocation(_)
-            | SectionData::Note(_)
-            | SectionData::Attributes(_)
-            | SectionData::SectionString
-            | SectionData::Symbol
-            | SectionData::SymbolSectionIndex
-            | SectionData::String
-            | SectionData::DynamicSymbol
-            | SectionData::DynamicString
-            | SectionData::Hash
-            | SectionData::GnuHash
-            | SectionData::GnuVersym
-            | SectionData::GnuVerdef
-            | SectionData::GnuVerneed => 2,
-        };
+        let move_priority = move_priority(section, interp, movable_relocations);
         blocks.push(Block {
             name: (*section.name).into(),
             kind: BlockKind::Section(section.id()),
@@ -315,3 +358,437 @@ pub(crate) fn find_move_sections(
     }
     Ok(move_sections)
 }
+
+/// Return how willing we are to move a section to a different address.
+///
+/// Higher means better to move. 0 means never move.
+fn move_priority(
+    section: &build::elf::Section,
+    interp: Option<build::elf::SectionId>,
+    movable_relocations: &[build::elf::SectionId],
+) -> u8 {
+    use build::elf::SectionData;
+
+    if movable_relocations.contains(&section.id()) {
+        // Referenced by dynamic entries, which are updated after moving.
+        return 2;
+    }
+    match &section.data {
+        // Can't move sections whose address may referenced from
+        // a section that we can't rewrite.
+        SectionData::Data(_) => {
+            if Some(section.id()) == interp {
+                1
+            } else {
+                0
+            }
+        }
+        SectionData::UninitializedData(_) | SectionData::Dynamic(_) => 0,
+        // Only movable if the dynamic entries refer to exactly this section.
+        SectionData::DynamicRelocation(_) => 0,
+        // None of these can be referenced by address that I am aware of.
+        SectionData::Relocation(_)
+        | SectionData::Note(_)
+        | SectionData::Attributes(_)
+        | SectionData::SectionString
+        | SectionData::Symbol
+        | SectionData::SymbolSectionIndex
+        | SectionData::String
+        | SectionData::DynamicSymbol
+        | SectionData::DynamicString
+        | SectionData::Hash
+        | SectionData::GnuHash
+        | SectionData::GnuVersym
+        | SectionData::GnuVerdef
+        | SectionData::GnuVerneed => 2,
+    }
+}
+
+/// Find the `PT_LOAD` segments whose sections are all movable and some of them need to move.
+///
+/// These are usually segments added by a previous edit. Laying them out again avoids
+/// leaving holes behind the moved sections.
+fn find_reusable_segments(
+    builder: &build::elf::Builder,
+    move_sections: &[build::elf::SectionId],
+    movable_relocations: &[build::elf::SectionId],
+) -> Vec<build::elf::SegmentId> {
+    let interp = builder.interp_section();
+    let program_headers_end = builder.header.e_phoff + builder.program_headers_size() as u64;
+    builder
+        .segments
+        .iter()
+        .filter(|segment| {
+            segment.p_type == elf::PT_LOAD
+                && segment.p_offset >= program_headers_end
+                && segment.sections.iter().any(|id| move_sections.contains(id))
+                && segment.sections.iter().all(|id| {
+                    let section = builder.sections.get(*id);
+                    section.sh_type != elf::SHT_NOBITS
+                        && move_priority(section, interp, movable_relocations) > 0
+                })
+        })
+        .map(|segment| segment.id())
+        .collect()
+}
+
+/// Find a `PT_LOAD` segment with the given flags that can be extended to hold `sections`.
+///
+/// The segment can only grow into the space up to the next segment in memory,
+/// rounded down to its alignment so that no page is mapped twice, and up to the
+/// next data in the file. Later segments are preferred.
+fn find_segment_with_room(
+    builder: &build::elf::Builder,
+    p_flags: u32,
+    sections: &[build::elf::SectionId],
+    move_sections: &[build::elf::SectionId],
+) -> Option<build::elf::SegmentId> {
+    let mut candidates: Vec<_> = builder
+        .segments
+        .iter()
+        .filter(|segment| {
+            segment.p_type == elf::PT_LOAD
+                && segment.p_flags == p_flags
+                && segment.p_filesz == segment.p_memsz
+                && segment.p_paddr == segment.p_vaddr
+        })
+        .collect();
+    candidates.sort_by_key(|segment| std::cmp::Reverse(segment.p_vaddr));
+
+    candidates.into_iter().find_map(|segment| {
+        let end_offset = segment.p_offset + segment.p_filesz;
+        let end_address = segment.p_vaddr + segment.p_memsz;
+
+        let mut max_offset = u64::MAX;
+        let mut max_address = u64::MAX;
+        if builder.header.e_phoff >= end_offset {
+            max_offset = builder.header.e_phoff;
+        }
+        for other in &builder.segments {
+            if other.id() == segment.id() || other.p_type != elf::PT_LOAD {
+                continue;
+            }
+            if other.p_offset >= end_offset {
+                max_offset = max_offset.min(other.p_offset);
+            }
+            if other.p_vaddr >= end_address {
+                let align = other.p_align.max(1);
+                max_address = max_address.min(other.p_vaddr & !(align - 1));
+            }
+        }
+        for section in &builder.sections {
+            if !section.is_alloc()
+                || section.sh_type == elf::SHT_NOBITS
+                || move_sections.contains(&section.id())
+            {
+                continue;
+            }
+            if section.sh_offset >= end_offset {
+                max_offset = max_offset.min(section.sh_offset);
+            }
+        }
+
+        // Simulate `Segment::append_section` for each section.
+        let mut offset = end_offset;
+        let mut address = end_address;
+        for id in sections {
+            let section = builder.sections.get(*id);
+            let align = section.sh_addralign.max(1);
+            if align > segment.p_align.max(1) {
+                return None;
+            }
+            offset = ((offset + (align - 1)) & !(align - 1)) + section.sh_size;
+            address = ((address + (align - 1)) & !(align - 1)) + section.sh_size;
+        }
+        (offset <= max_offset && address <= max_address).then(|| segment.id())
+    })
+}
+
+/// Move the `PT_LOAD` segments that share a page of `page_size` bytes with another
+/// segment to a new page, when all their sections can move.
+///
+/// The sections are flagged for [`move_sections`], which lays them out again in the
+/// moved segment. Segments that can't move are reported by [`realign_segments`].
+pub(crate) fn release_shared_pages(builder: &mut build::elf::Builder, page_size: u64) {
+    let movable_relocations: Vec<_> = find_relocation_tables(builder)
+        .iter()
+        .map(|table| table.section)
+        .collect();
+    let interp = builder.interp_section();
+    let program_headers_end = builder.header.e_phoff + builder.program_headers_size() as u64;
+    let is_movable = |segment: &build::elf::Segment| {
+        segment.p_offset >= program_headers_end
+            && !segment.sections.is_empty()
+            && segment.sections.iter().all(|id| {
+                let section = builder.sections.get(*id);
+                section.sh_type != elf::SHT_NOBITS
+                    && move_priority(section, interp, &movable_relocations) > 0
+            })
+    };
+
+    // Move the later segment of each pair when possible, since it is usually the one
+    // added by a previous edit.
+    let mut release = Vec::new();
+    for (first, second) in find_shared_pages(builder, page_size) {
+        let id = [second, first]
+            .into_iter()
+            .find(|id| is_movable(builder.segments.get(*id)));
+        if let Some(id) = id {
+            if !release.contains(&id) {
+                release.push(id);
+            }
+        }
+    }
+    if release.is_empty() {
+        return;
+    }
+
+    let mut end_offset = 0;
+    let mut end_address = 0;
+    for segment in &builder.segments {
+        if segment.p_type == elf::PT_LOAD && !release.contains(&segment.id()) {
+            end_offset = end_offset.max(segment.p_offset + segment.p_filesz);
+            end_address = end_address.max(segment.p_vaddr + segment.p_memsz);
+        }
+    }
+    for id in release {
+        let segment = builder.segments.get_mut(id);
+        // Start on a new page, at an address that matches the file offset.
+        let address = align_up(end_address, page_size) + end_offset % page_size;
+        end_address = address + segment.p_memsz;
+        segment.p_offset = end_offset;
+        segment.p_vaddr = address;
+        segment.p_paddr = address;
+        for section_id in &segment.sections {
+            // A zero offset marks the section for placement by `move_sections`.
+            builder.sections.get_mut(*section_id).sh_offset = 0;
+        }
+    }
+}
+
+/// Shift the file offsets of the `PT_LOAD` segments so that they are congruent with
+/// their addresses modulo `page_size`, as the loader requires.
+///
+/// The addresses of the sections don't change, so the code doesn't need relocating.
+/// The segment holding the file header can't move in the file; it is extended down
+/// to the start of its page instead, which moves the program headers in memory.
+///
+/// Fails if two segments still share a page, since that needs the file to be linked
+/// again.
+pub(crate) fn realign_segments(builder: &mut build::elf::Builder, page_size: u64) -> Result<()> {
+    let headers_end = (builder.file_header_size() as u64)
+        .max(builder.header.e_phoff + builder.program_headers_size() as u64);
+
+    let mut loads: Vec<_> = builder
+        .segments
+        .iter()
+        .filter(|segment| segment.p_type == elf::PT_LOAD && !segment.delete)
+        .map(|segment| segment.id())
+        .collect();
+    loads.sort_by_key(|id| builder.segments.get(*id).p_offset);
+
+    // The shift of the file offsets from each original offset onwards.
+    let mut shifts: Vec<(u64, u64)> = Vec::new();
+    let mut headers_shift = 0;
+    let mut end = 0;
+    for id in loads {
+        let segment = builder.segments.get_mut(id);
+        let misalignment =
+            (segment.p_vaddr % page_size + page_size - segment.p_offset % page_size) % page_size;
+        if segment.p_offset == 0 {
+            shifts.push((0, 0));
+            if misalignment != 0 {
+                segment.p_vaddr -= misalignment;
+                segment.p_paddr -= misalignment;
+                segment.p_filesz += misalignment;
+                segment.p_memsz += misalignment;
+                shifts.push((headers_end, misalignment));
+                headers_shift = misalignment;
+            }
+            end = end.max(segment.p_filesz);
+            continue;
+        }
+        let offset = segment.p_offset.max(end);
+        let offset =
+            offset + (segment.p_vaddr % page_size + page_size - offset % page_size) % page_size;
+        shifts.push((segment.p_offset, offset - segment.p_offset));
+        segment.p_offset = offset;
+        end = end.max(offset + segment.p_filesz);
+    }
+    let shift_at = |offset: u64| {
+        shifts
+            .iter()
+            .rev()
+            .find(|&&(start, _)| start <= offset)
+            .map_or(0, |&(_, shift)| shift)
+    };
+
+    for section in &mut builder.sections {
+        if section.is_alloc() {
+            section.sh_offset += shift_at(section.sh_offset);
+        }
+    }
+    for segment in &mut builder.segments {
+        match segment.p_type {
+            elf::PT_LOAD => {}
+            elf::PT_PHDR => {
+                segment.p_vaddr -= headers_shift;
+                segment.p_paddr -= headers_shift;
+            }
+            _ if segment.p_filesz == 0 && segment.p_memsz == 0 => {}
+            _ => segment.p_offset += shift_at(segment.p_offset),
+        }
+    }
+
+    if let Some((first, second)) = find_shared_pages(builder, page_size).first() {
+        let first = builder.segments.get(*first).p_vaddr;
+        let second = builder.segments.get(*second).p_vaddr;
+        return Err(ElfError::Modify(format!(
+            "LOAD segments at {first:#x} and {second:#x} share a page of {page_size:#x} bytes \
+             and can't be moved apart; the file needs to be linked again with \
+             -z max-page-size={page_size}",
+        )));
+    }
+    Ok(())
+}
+
+/// Find the pairs of `PT_LOAD` segments that map different contents or permissions
+/// to the same page, in order of address.
+fn find_shared_pages(
+    builder: &build::elf::Builder,
+    page_size: u64,
+) -> Vec<(build::elf::SegmentId, build::elf::SegmentId)> {
+    let mut loads: Vec<_> = builder
+        .segments
+        .iter()
+        .filter(|segment| segment.p_type == elf::PT_LOAD && !segment.delete && segment.p_memsz > 0)
+        .collect();
+    loads.sort_by_key(|segment| segment.p_vaddr);
+    loads
+        .windows(2)
+        .filter(|pair| {
+            let (first, second) = (pair[0], pair[1]);
+            let last_page = (first.p_vaddr + first.p_memsz - 1) / page_size;
+            let same_mapping = first.p_flags == second.p_flags
+                && first.p_vaddr.wrapping_sub(first.p_offset)
+                    == second.p_vaddr.wrapping_sub(second.p_offset);
+            last_page >= second.p_vaddr / page_size && !same_mapping
+        })
+        .map(|pair| (pair[0].id(), pair[1].id()))
+        .collect()
+}
+
+fn align_up(value: u64, align: u64) -> u64 {
+    (value + (align - 1)) & !(align - 1)
+}
+
+/// A table of dynamic relocations whose address and size are given by dynamic entries.
+struct RelocationTable {
+    section: build::elf::SectionId,
+    address_tag: u32,
+    size_tag: u32,
+}
+
+/// Find the sections that hold exactly a table of dynamic relocations given by dynamic entries.
+///
+/// A section that is only partly covered by a table can't be moved, since the dynamic
+/// entries can't describe its new location.
+fn find_relocation_tables(builder: &build::elf::Builder) -> Vec<RelocationTable> {
+    let Some(dynamic) = builder.dynamic_data() else {
+        return Vec::new();
+    };
+    let value = |tag| {
+        dynamic.iter().find_map(|entry| match entry {
+            build::elf::Dynamic::Integer {
+                tag: entry_tag,
+                val,
+            } if *entry_tag == tag => Some(*val),
+            _ => None,
+        })
+    };
+
+    let mut tables = Vec::new();
+    let mut partial = Vec::new();
+    for (address_tag, size_tag) in RELOCATION_TAGS {
+        let (Some(address), Some(size)) = (value(address_tag), value(size_tag)) else {
+            continue;
+        };
+        for section in &builder.sections {
+            let is_relocation = match section.data {
+                build::elf::SectionData::DynamicRelocation(_) => true,
+                build::elf::SectionData::Data(_) => section.sh_type == elf::SHT_RELR,
+                _ => false,
+            };
+            if !section.is_alloc() || !is_relocation {
+                continue;
+            }
+            if address == section.sh_addr && size == section.sh_size {
+                tables.push(RelocationTable {
+                    section: section.id(),
+                    address_tag,
+                    size_tag,
+                });
+            } else if address < section.sh_addr + section.sh_size
+                && section.sh_addr < address + size
+            {
+                partial.push(section.id());
+            }
+        }
+    }
+    tables.retain(|table| !partial.contains(&table.section));
+    tables
+}
+
+/// Point the dynamic entries for each relocation table at the current location of its section.
+fn update_relocation_tables(builder: &mut build::elf::Builder, tables: &[RelocationTable]) {
+    let mut values = Vec::new();
+    for table in tables {
+        let section = builder.sections.get(table.section);
+        if section.delete {
+            continue;
+        }
+        values.push((table.address_tag, section.sh_addr));
+        values.push((table.size_tag, section.sh_size));
+    }
+
+    let Some(dynamic) = builder.dynamic_data_mut() else {
+        return;
+    };
+    for entry in dynamic.iter_mut() {
+        let build::elf::Dynamic::Integer { tag, val } = entry else {
+            continue;
+        };
+        if let Some((_, value)) = values.iter().find(|(value_tag, _)| value_tag == tag) {
+            *val = *value;
+        }
+    }
+}
+
+/// The name of the section holding the `NT_GNU_PROPERTY_TYPE_0` notes.
+pub(crate) const GNU_PROPERTY_SECTION: &[u8] = b".note.gnu.property";
+
+/// Make the `PT_GNU_PROPERTY` segment cover the `.note.gnu.property` section.
+///
+/// The segment is deleted along with the section.
+pub(crate) fn sync_gnu_property_segment(builder: &mut build::elf::Builder) {
+    let section = builder
+        .sections
+        .iter()
+        .find(|section| section.name.as_slice() == GNU_PROPERTY_SECTION && !section.delete);
+    let range = section.map(|section| (section.sh_offset, section.sh_addr, section.sh_size));
+    for segment in &mut builder.segments {
+        if segment.p_type != elf::PT_GNU_PROPERTY {
+            continue;
+        }
+        match range {
+            Some((offset, address, size)) => {
+                segment.p_offset = offset;
+                segment.p_vaddr = address;
+                segment.p_paddr = address;
+                segment.p_filesz = size;
+                segment.p_memsz = size;
+            }
+            None => segment.delete = true,
+        }
+    }
+}
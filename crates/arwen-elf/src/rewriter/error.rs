@@ -1,5 +1,7 @@
+use crate::check::Problem;
 use object::build;
 use std::io;
+use std::path::PathBuf;
 use std::string::FromUtf8Error;
 use thiserror::Error;
 
@@ -21,6 +23,18 @@ pub enum ElfError {
     #[error("Non-UTF-8 DT_NEEDED entry")]
     /// The `DT_NEEDED` entry is not UTF-8 encoded
     InvalidDtNeededEncoding(#[source] FromUtf8Error),
+    #[error("The written file failed the check: {}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("; "))]
+    /// The written file failed the check enabled by
+    /// [`set_check_on_write`](crate::Writer::set_check_on_write).
+    Check(Vec<Problem>),
+    #[error("Error in {}: {source}", .path.display())]
+    /// An error occurred in one of several files changed together.
+    File {
+        /// The file the error occurred in.
+        path: PathBuf,
+        /// The error.
+        source: Box<ElfError>,
+    },
 }
 
 /// The  `Result` type for this library.
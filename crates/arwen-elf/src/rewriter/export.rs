@@ -0,0 +1,108 @@
+use std::fmt;
+
+use object::{
+    build::elf::{DynamicSymbol, DynamicSymbolId},
+    elf,
+};
+use serde::Serialize;
+
+/// A change to how a defined dynamic symbol is exported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportChange {
+    /// Make the symbol `STB_LOCAL`, so that it only binds inside the object.
+    Localize,
+    /// Make the symbol `STV_HIDDEN`, so that other objects can't bind to it.
+    Hide,
+    /// Turn a `STB_GLOBAL` symbol into `STB_WEAK`, so that other definitions win.
+    Weaken,
+    /// Remove the symbol from `.dynsym`.
+    ///
+    /// Symbols still referenced by dynamic relocations are localized instead.
+    Unexport,
+}
+
+impl fmt::Display for ExportChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ExportChange::Localize => "localize",
+            ExportChange::Hide => "hide",
+            ExportChange::Weaken => "weaken",
+            ExportChange::Unexport => "unexport",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Apply a change to a dynamic symbol.
+///
+/// Returns whether the symbol changed.
+pub(crate) fn change_export(
+    symbol: &mut DynamicSymbol<'_>,
+    change: ExportChange,
+    relocated: &[DynamicSymbolId],
+) -> bool {
+    let st_bind = symbol.st_bind();
+    match change {
+        ExportChange::Unexport if !relocated.contains(&symbol.id()) => symbol.delete = true,
+        ExportChange::Localize | ExportChange::Unexport => {
+            if st_bind == elf::STB_LOCAL {
+                return false;
+            }
+            // The version is kept, so that the version definitions that consumers
+            // require stay in place.
+            symbol.set_st_info(elf::STB_LOCAL, symbol.st_type());
+        }
+        ExportChange::Hide => {
+            if symbol.st_other & 0x3 == elf::STV_HIDDEN {
+                return false;
+            }
+            symbol.st_other = (symbol.st_other & !0x3) | elf::STV_HIDDEN;
+        }
+        ExportChange::Weaken => {
+            if st_bind != elf::STB_GLOBAL {
+                return false;
+            }
+            symbol.set_st_info(elf::STB_WEAK, symbol.st_type());
+        }
+    }
+    true
+}
+
+/// A dynamic symbol renamed by [`prefix_exports`](crate::ElfContainer::prefix_exports).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SymbolRename {
+    /// The name of the symbol before the rename.
+    pub old: String,
+    /// The name of the symbol after the rename.
+    pub new: String,
+    /// The version of the symbol, e.g. `OPENSSL_3.0.0`, if it has one.
+    pub version: Option<String>,
+}
+
+impl fmt::Display for SymbolRename {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.version {
+            Some(version) => write!(f, "{}@{version} => {}@{version}", self.old, self.new),
+            None => write!(f, "{} => {}", self.old, self.new),
+        }
+    }
+}
+
+/// The relocation type that copies a symbol's data into an executable.
+///
+/// The executable defines a copy of the symbol, which has to be renamed with the
+/// import that it replaces.
+pub(crate) fn copy_relocation_type(e_machine: u16) -> Option<u32> {
+    match e_machine {
+        elf::EM_X86_64 => Some(elf::R_X86_64_COPY),
+        elf::EM_386 => Some(elf::R_386_COPY),
+        elf::EM_AARCH64 => Some(elf::R_AARCH64_COPY),
+        elf::EM_ARM => Some(elf::R_ARM_COPY),
+        elf::EM_PPC | elf::EM_PPC64 => Some(elf::R_PPC_COPY),
+        elf::EM_S390 => Some(elf::R_390_COPY),
+        elf::EM_RISCV => Some(elf::R_RISCV_COPY),
+        elf::EM_LOONGARCH => Some(elf::R_LARCH_COPY),
+        _ => None,
+    }
+}
@@ -1,5 +1,5 @@
 use object::{
-    build::elf::{Builder, Segment},
+    build::elf::{Builder, Section, Segment},
     elf,
 };
 
@@ -18,6 +18,20 @@ pub trait BuilderExt<'data> {
     /// Add a new `PT_GNU_STACK` segment
     /// with all segment permission
     fn add_gnu_stack(&mut self) -> &mut Segment<'data>;
+
+    /// Return the segment at `index` in the program header table.
+    ///
+    /// Deleted segments are skipped, so the index matches the written file.
+    fn segment_at(&self, index: usize) -> Option<&Segment<'data>>;
+
+    /// Return the mutable segment at `index` in the program header table.
+    fn segment_at_mut(&mut self, index: usize) -> Option<&mut Segment<'data>>;
+
+    /// Return the allocated sections whose address lies in the memory range of the segment.
+    fn segment_sections(&self, segment: &Segment<'data>) -> Vec<&Section<'data>>;
+
+    /// Return the union of the `p_flags` of all `PT_LOAD` segments that map the section.
+    fn section_load_flags(&self, section: &Section<'data>) -> u32;
 }
 
 impl<'data> BuilderExt<'data> for Builder<'data> {
@@ -49,4 +63,38 @@ impl<'data> BuilderExt<'data> for Builder<'data> {
 
         Some(segment)
     }
+
+    fn segment_at(&self, index: usize) -> Option<&Segment<'data>> {
+        self.segments
+            .iter()
+            .filter(|segment| !segment.delete)
+            .nth(index)
+    }
+
+    fn segment_at_mut(&mut self, index: usize) -> Option<&mut Segment<'data>> {
+        self.segments
+            .iter_mut()
+            .filter(|segment| !segment.delete)
+            .nth(index)
+    }
+
+    fn segment_sections(&self, segment: &Segment<'data>) -> Vec<&Section<'data>> {
+        self.sections
+            .iter()
+            .filter(|section| {
+                !section.delete
+                    && section.sh_flags & u64::from(elf::SHF_ALLOC) != 0
+                    && segment.contains_address(section.sh_addr)
+            })
+            .collect()
+    }
+
+    fn section_load_flags(&self, section: &Section<'data>) -> u32 {
+        self.segments
+            .iter()
+            .filter(|segment| {
+                !segment.delete && segment.is_load() && segment.contains_address(section.sh_addr)
+            })
+            .fold(0, |flags, segment| flags | segment.p_flags)
+    }
 }
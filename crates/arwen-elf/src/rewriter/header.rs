@@ -0,0 +1,279 @@
+use std::fmt;
+
+use object::{build::elf::Builder, elf, Endianness};
+use serde::Serialize;
+
+/// The decoded fields of the ELF file header.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ElfHeader {
+    /// Whether the file is a 64-bit ELF file.
+    pub is_64: bool,
+    /// Whether the file is big endian.
+    pub big_endian: bool,
+    /// The `EI_OSABI` byte.
+    pub os_abi: u8,
+    /// The name of the OS ABI, if it is known.
+    pub os_abi_name: Option<String>,
+    /// The `EI_ABIVERSION` byte.
+    pub abi_version: u8,
+    /// The object file type, one of the `ET_*` constants.
+    pub e_type: u16,
+    /// The name of the object file type, if it is known.
+    pub type_name: Option<String>,
+    /// The architecture, one of the `EM_*` constants.
+    pub e_machine: u16,
+    /// The name of the architecture, if it is known.
+    pub machine_name: Option<String>,
+    /// The entry point address.
+    pub e_entry: u64,
+    /// The processor-specific flags.
+    pub e_flags: u32,
+    /// The names of the bits and fields set in `e_flags`, see [`decode_e_flags`].
+    pub flags: Vec<String>,
+    /// The file offset of the program header table.
+    pub e_phoff: u64,
+}
+
+impl ElfHeader {
+    pub(crate) fn new(builder: &Builder<'_>) -> Self {
+        let header = &builder.header;
+        Self {
+            is_64: builder.is_64,
+            big_endian: builder.endian == Endianness::Big,
+            os_abi: header.os_abi,
+            os_abi_name: os_abi_name(header.os_abi).map(str::to_string),
+            abi_version: header.abi_version,
+            e_type: header.e_type,
+            type_name: type_name(header.e_type).map(str::to_string),
+            e_machine: header.e_machine,
+            machine_name: machine_name(header.e_machine).map(str::to_string),
+            e_entry: header.e_entry,
+            e_flags: header.e_flags,
+            flags: decode_e_flags(header.e_machine, header.e_flags),
+            e_phoff: header.e_phoff,
+        }
+    }
+}
+
+impl fmt::Display for ElfHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = |name: &Option<String>| name.clone().unwrap_or_else(|| "unknown".to_string());
+        writeln!(f, "class: {}", if self.is_64 { "ELF64" } else { "ELF32" })?;
+        writeln!(
+            f,
+            "data: {}",
+            if self.big_endian {
+                "big endian"
+            } else {
+                "little endian"
+            }
+        )?;
+        writeln!(f, "os abi: {} ({})", name(&self.os_abi_name), self.os_abi)?;
+        writeln!(f, "abi version: {}", self.abi_version)?;
+        writeln!(f, "type: {} ({})", name(&self.type_name), self.e_type)?;
+        writeln!(
+            f,
+            "machine: {} ({})",
+            name(&self.machine_name),
+            self.e_machine
+        )?;
+        writeln!(f, "entry: {:#x}", self.e_entry)?;
+        if self.flags.is_empty() {
+            writeln!(f, "flags: {:#x}", self.e_flags)?;
+        } else {
+            writeln!(f, "flags: {:#x} ({})", self.e_flags, self.flags.join(", "))?;
+        }
+        writeln!(f, "program headers offset: {}", self.e_phoff)
+    }
+}
+
+/// Decode the processor-specific `e_flags` for an architecture.
+///
+/// RISC-V, ARM and MIPS flags are decoded; bits that are not known are listed as
+/// hexadecimal values. Flags of other architectures are not decoded.
+pub fn decode_e_flags(e_machine: u16, e_flags: u32) -> Vec<String> {
+    let mut names = Vec::new();
+    let rest = match e_machine {
+        elf::EM_RISCV => decode_riscv_flags(e_flags, &mut names),
+        elf::EM_ARM => decode_arm_flags(e_flags, &mut names),
+        elf::EM_MIPS | elf::EM_MIPS_RS3_LE => decode_mips_flags(e_flags, &mut names),
+        _ => return names,
+    };
+    if rest != 0 {
+        names.push(format!("{rest:#x}"));
+    }
+    names
+}
+
+/// Push the names of the `bits` that are set and return the flags without them.
+fn decode_bits(flags: u32, bits: &[(u32, &str)], names: &mut Vec<String>) -> u32 {
+    let mut rest = flags;
+    for &(bit, name) in bits {
+        if flags & bit != 0 {
+            names.push(name.to_string());
+            rest &= !bit;
+        }
+    }
+    rest
+}
+
+fn decode_riscv_flags(flags: u32, names: &mut Vec<String>) -> u32 {
+    let float_abi = match flags & elf::EF_RISCV_FLOAT_ABI {
+        elf::EF_RISCV_FLOAT_ABI_SOFT => "soft-float",
+        elf::EF_RISCV_FLOAT_ABI_SINGLE => "single-float",
+        elf::EF_RISCV_FLOAT_ABI_DOUBLE => "double-float",
+        _ => "quad-float",
+    };
+    names.push(float_abi.to_string());
+    decode_bits(
+        flags & !elf::EF_RISCV_FLOAT_ABI,
+        &[
+            (elf::EF_RISCV_RVC, "rvc"),
+            (elf::EF_RISCV_RVE, "rve"),
+            (elf::EF_RISCV_TSO, "tso"),
+            (elf::EF_RISCV_RV64ILP32, "rv64ilp32"),
+        ],
+        names,
+    )
+}
+
+fn decode_arm_flags(flags: u32, names: &mut Vec<String>) -> u32 {
+    let eabi = flags & elf::EF_ARM_EABIMASK;
+    if eabi == elf::EF_ARM_EABI_UNKNOWN {
+        // The flags of the old GNU ABI.
+        return decode_bits(
+            flags,
+            &[
+                (elf::EF_ARM_RELEXEC, "relexec"),
+                (elf::EF_ARM_HASENTRY, "has-entry"),
+                (elf::EF_ARM_INTERWORK, "interwork"),
+                (elf::EF_ARM_APCS_26, "apcs-26"),
+                (elf::EF_ARM_APCS_FLOAT, "apcs-float"),
+                (elf::EF_ARM_PIC, "pic"),
+                (elf::EF_ARM_ALIGN8, "align8"),
+                (elf::EF_ARM_NEW_ABI, "new-abi"),
+                (elf::EF_ARM_OLD_ABI, "old-abi"),
+                (elf::EF_ARM_SOFT_FLOAT, "soft-float"),
+                (elf::EF_ARM_VFP_FLOAT, "vfp-float"),
+                (elf::EF_ARM_MAVERICK_FLOAT, "maverick-float"),
+            ],
+            names,
+        );
+    }
+    names.push(format!("eabi{}", eabi >> 24));
+    decode_bits(
+        flags & !elf::EF_ARM_EABIMASK,
+        &[
+            (elf::EF_ARM_ABI_FLOAT_SOFT, "soft-float"),
+            (elf::EF_ARM_ABI_FLOAT_HARD, "hard-float"),
+            (elf::EF_ARM_BE8, "be8"),
+            (elf::EF_ARM_LE8, "le8"),
+        ],
+        names,
+    )
+}
+
+fn decode_mips_flags(flags: u32, names: &mut Vec<String>) -> u32 {
+    let arch = match flags & elf::EF_MIPS_ARCH {
+        elf::EF_MIPS_ARCH_1 => Some("mips1"),
+        elf::EF_MIPS_ARCH_2 => Some("mips2"),
+        elf::EF_MIPS_ARCH_3 => Some("mips3"),
+        elf::EF_MIPS_ARCH_4 => Some("mips4"),
+        elf::EF_MIPS_ARCH_5 => Some("mips5"),
+        elf::EF_MIPS_ARCH_32 => Some("mips32"),
+        elf::EF_MIPS_ARCH_64 => Some("mips64"),
+        elf::EF_MIPS_ARCH_32R2 => Some("mips32r2"),
+        elf::EF_MIPS_ARCH_64R2 => Some("mips64r2"),
+        elf::EF_MIPS_ARCH_32R6 => Some("mips32r6"),
+        elf::EF_MIPS_ARCH_64R6 => Some("mips64r6"),
+        _ => None,
+    };
+    let mut rest = flags;
+    if let Some(arch) = arch {
+        names.push(arch.to_string());
+        rest &= !elf::EF_MIPS_ARCH;
+    }
+    let abi = match flags & elf::EF_MIPS_ABI {
+        elf::EF_MIPS_ABI_O32 => Some("o32"),
+        elf::EF_MIPS_ABI_O64 => Some("o64"),
+        elf::EF_MIPS_ABI_EABI32 => Some("eabi32"),
+        elf::EF_MIPS_ABI_EABI64 => Some("eabi64"),
+        _ => None,
+    };
+    if let Some(abi) = abi {
+        names.push(abi.to_string());
+        rest &= !elf::EF_MIPS_ABI;
+    }
+    decode_bits(
+        rest,
+        &[
+            (elf::EF_MIPS_NOREORDER, "noreorder"),
+            (elf::EF_MIPS_PIC, "pic"),
+            (elf::EF_MIPS_CPIC, "cpic"),
+            (elf::EF_MIPS_XGOT, "xgot"),
+            (elf::EF_MIPS_64BIT_WHIRL, "64bit-whirl"),
+            (elf::EF_MIPS_ABI2, "abi2"),
+            (elf::EF_MIPS_ABI_ON32, "abi-on32"),
+            (elf::EF_MIPS_FP64, "fp64"),
+            (elf::EF_MIPS_NAN2008, "nan2008"),
+        ],
+        names,
+    )
+}
+
+/// Return the name of an OS ABI, as accepted when setting it.
+fn os_abi_name(os_abi: u8) -> Option<&'static str> {
+    let name = match os_abi {
+        elf::ELFOSABI_SYSV => "sysv",
+        elf::ELFOSABI_HPUX => "hpux",
+        elf::ELFOSABI_NETBSD => "netbsd",
+        elf::ELFOSABI_LINUX => "linux",
+        elf::ELFOSABI_HURD => "hurd",
+        elf::ELFOSABI_SOLARIS => "solaris",
+        elf::ELFOSABI_AIX => "aix",
+        elf::ELFOSABI_IRIX => "irix",
+        elf::ELFOSABI_FREEBSD => "freebsd",
+        elf::ELFOSABI_TRU64 => "tru64",
+        elf::ELFOSABI_MODESTO => "modesto",
+        elf::ELFOSABI_OPENBSD => "openbsd",
+        elf::ELFOSABI_OPENVMS => "openvms",
+        elf::ELFOSABI_NSK => "nsk",
+        elf::ELFOSABI_AROS => "aros",
+        elf::ELFOSABI_FENIXOS => "fenixos",
+        elf::ELFOSABI_CLOUDABI => "cloudabi",
+        _ => return None,
+    };
+    Some(name)
+}
+
+fn type_name(e_type: u16) -> Option<&'static str> {
+    let name = match e_type {
+        elf::ET_NONE => "NONE",
+        elf::ET_REL => "REL",
+        elf::ET_EXEC => "EXEC",
+        elf::ET_DYN => "DYN",
+        elf::ET_CORE => "CORE",
+        _ => return None,
+    };
+    Some(name)
+}
+
+fn machine_name(e_machine: u16) -> Option<&'static str> {
+    let name = match e_machine {
+        elf::EM_386 => "386",
+        elf::EM_X86_64 => "X86_64",
+        elf::EM_ARM => "ARM",
+        elf::EM_AARCH64 => "AARCH64",
+        elf::EM_RISCV => "RISCV",
+        elf::EM_MIPS => "MIPS",
+        elf::EM_MIPS_RS3_LE => "MIPS_RS3_LE",
+        elf::EM_PPC => "PPC",
+        elf::EM_PPC64 => "PPC64",
+        elf::EM_S390 => "S390",
+        elf::EM_SPARC => "SPARC",
+        elf::EM_SPARCV9 => "SPARCV9",
+        elf::EM_LOONGARCH => "LOONGARCH",
+        _ => return None,
+    };
+    Some(name)
+}
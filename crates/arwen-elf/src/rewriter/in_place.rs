@@ -0,0 +1,277 @@
+//! Patch strings directly in the file bytes, without rewriting the file.
+//!
+//! A string can be replaced in place when the new one fits in the space of the old
+//! one, plus any unused NUL padding after it. In `.dynstr` the space must not be
+//! shared with other strings, since the linker merges strings that are suffixes of
+//! each other.
+
+use object::{
+    elf,
+    read::elf::{Dyn, FileHeader, ProgramHeader, SectionHeader, Sym},
+    Endian, Endianness, FileKind,
+};
+
+/// Replace the interpreter path in `PT_INTERP`, padding it with NUL bytes.
+///
+/// `interpreter` must include the NUL terminator. The sizes of the segment and of the
+/// `.interp` section are set to its length. Returns false, leaving `data` unchanged,
+/// if it doesn't fit.
+pub(crate) fn patch_interpreter(data: &mut [u8], interpreter: &[u8]) -> bool {
+    let (interp, is_64) = match FileKind::parse(&*data) {
+        Ok(FileKind::Elf32) => (
+            find_interpreter::<elf::FileHeader32<Endianness>>(data),
+            false,
+        ),
+        Ok(FileKind::Elf64) => (
+            find_interpreter::<elf::FileHeader64<Endianness>>(data),
+            true,
+        ),
+        _ => return false,
+    };
+    let Some(interp) = interp else {
+        return false;
+    };
+    if interpreter.is_empty() || interpreter.len() > interp.end - interp.start {
+        return false;
+    }
+    patch(&mut data[interp.start..interp.end], interpreter);
+
+    let size = interpreter.len() as u64;
+    let write_size = |data: &mut [u8], offset: usize| {
+        if is_64 {
+            let bytes = interp.endian.write_u64_bytes(size);
+            data[offset..offset + 8].copy_from_slice(&bytes);
+        } else {
+            let bytes = interp.endian.write_u32_bytes(size as u32);
+            data[offset..offset + 4].copy_from_slice(&bytes);
+        }
+    };
+    // The offsets of p_filesz and p_memsz in a program header, and of sh_size in a
+    // section header.
+    let (p_filesz, p_memsz, sh_size) = if is_64 { (32, 40, 32) } else { (16, 20, 20) };
+    write_size(data, interp.program_header + p_filesz);
+    write_size(data, interp.program_header + p_memsz);
+    if let Some(section_header) = interp.section_header {
+        write_size(data, section_header + sh_size);
+    }
+    true
+}
+
+/// The location of the interpreter in a file.
+struct Interpreter {
+    endian: Endianness,
+    /// The file range of the `PT_INTERP` segment.
+    start: usize,
+    end: usize,
+    /// The file offset of the `PT_INTERP` program header.
+    program_header: usize,
+    /// The file offset of the header of the section with the same range.
+    section_header: Option<usize>,
+}
+
+fn find_interpreter<Elf: FileHeader<Endian = Endianness>>(data: &[u8]) -> Option<Interpreter> {
+    let header = Elf::parse(data).ok()?;
+    let endian = header.endian().ok()?;
+    let segments = header.program_headers(endian, data).ok()?;
+    let index = segments
+        .iter()
+        .position(|segment| segment.p_type(endian) == elf::PT_INTERP)?;
+    let segment = &segments[index];
+    let (offset, size) = segment.file_range(endian);
+    let start = usize::try_from(offset).ok()?;
+    let end = start.checked_add(usize::try_from(size).ok()?)?;
+    if end > data.len() {
+        return None;
+    }
+    let e_phoff = usize::try_from(header.e_phoff(endian).into()).ok()?;
+    let e_shoff = usize::try_from(header.e_shoff(endian).into()).ok()?;
+    let section_header = header
+        .sections(endian, data)
+        .ok()?
+        .iter()
+        .position(|section| section.file_range(endian) == Some((offset, size)))
+        .map(|index| e_shoff + index * usize::from(header.e_shentsize(endian)));
+    Some(Interpreter {
+        endian,
+        start,
+        end,
+        program_header: e_phoff + index * usize::from(header.e_phentsize(endian)),
+        section_header,
+    })
+}
+
+/// Replace the strings of the dynamic entries with one of `tags`, in order.
+///
+/// There must be one value per entry, without NUL terminator. Returns false, leaving
+/// `data` unchanged, if an entry is missing or a value doesn't fit.
+pub(crate) fn patch_dynamic_strings(data: &mut [u8], tags: &[u32], values: &[&[u8]]) -> bool {
+    let ranges = match FileKind::parse(&*data) {
+        Ok(FileKind::Elf32) => {
+            dynamic_string_ranges::<elf::FileHeader32<Endianness>>(data, tags, values)
+        }
+        Ok(FileKind::Elf64) => {
+            dynamic_string_ranges::<elf::FileHeader64<Endianness>>(data, tags, values)
+        }
+        _ => None,
+    };
+    let Some(ranges) = ranges else {
+        return false;
+    };
+    for ((start, end), value) in ranges.into_iter().zip(values) {
+        patch(&mut data[start..end], value);
+    }
+    true
+}
+
+/// Overwrite the start of `space` with `value`, and the rest with NUL bytes.
+fn patch(space: &mut [u8], value: &[u8]) {
+    let (head, tail) = space.split_at_mut(value.len());
+    head.copy_from_slice(value);
+    tail.fill(0);
+}
+
+/// Return the file range that each value can be written to, or `None` if one of
+/// them doesn't fit.
+///
+/// The range of a string covers the string, its NUL terminator and the NUL padding
+/// after it, up to the next string used by the file.
+fn dynamic_string_ranges<Elf: FileHeader<Endian = Endianness>>(
+    data: &[u8],
+    tags: &[u32],
+    values: &[&[u8]],
+) -> Option<Vec<(usize, usize)>> {
+    let header = Elf::parse(data).ok()?;
+    let endian = header.endian().ok()?;
+    let dynamic = header
+        .program_headers(endian, data)
+        .ok()?
+        .iter()
+        .find_map(|segment| segment.dynamic(endian, data).ok().flatten())?;
+    let entries: Vec<(u32, u64)> = dynamic
+        .iter()
+        .map(|entry| (entry.d_tag(endian).into(), entry.d_val(endian).into()))
+        .take_while(|&(tag, _)| tag != u64::from(elf::DT_NULL))
+        .filter_map(|(tag, value)| Some((u32::try_from(tag).ok()?, value)))
+        .collect();
+    let strtab = entries.iter().find(|&&(tag, _)| tag == elf::DT_STRTAB)?.1;
+
+    // The section headers tell which sections use the string table; without them the
+    // users can't all be found.
+    let sections = header.sections(endian, data).ok()?;
+    let (strings_index, strings) = sections.enumerate().find(|(_, section)| {
+        let sh_addr: u64 = section.sh_addr(endian).into();
+        section.sh_type(endian) == elf::SHT_STRTAB && sh_addr == strtab
+    })?;
+    let strings_offset = usize::try_from(strings.sh_offset(endian).into()).ok()?;
+    let strings = strings.data(endian, data).ok()?;
+
+    // Collect the string offsets used by the file, apart from the ones being replaced.
+    let mut targets = Vec::new();
+    let mut used = Vec::new();
+    for &(tag, value) in &entries {
+        let is_string = matches!(
+            tag,
+            elf::DT_NEEDED
+                | elf::DT_SONAME
+                | elf::DT_RPATH
+                | elf::DT_RUNPATH
+                | elf::DT_AUXILIARY
+                | elf::DT_FILTER
+                | elf::DT_CONFIG
+                | elf::DT_DEPAUDIT
+                | elf::DT_AUDIT
+        );
+        if tags.contains(&tag) {
+            targets.push(usize::try_from(value).ok()?);
+        } else if is_string {
+            used.push(usize::try_from(value).ok()?);
+        }
+    }
+    if targets.len() != values.len() {
+        return None;
+    }
+    for section in sections.iter() {
+        if section.sh_link(endian) as usize != strings_index.0 {
+            continue;
+        }
+        match section.sh_type(endian) {
+            elf::SHT_DYNAMIC => {}
+            elf::SHT_DYNSYM => {
+                let symbols = sections
+                    .symbols(endian, data, elf::SHT_DYNSYM)
+                    .ok()
+                    .filter(|symbols| symbols.string_section() == strings_index)?;
+                for symbol in symbols.symbols() {
+                    used.push(symbol.st_name(endian) as usize);
+                }
+            }
+            elf::SHT_GNU_VERDEF => {
+                let (mut verdefs, _) = section.gnu_verdef(endian, data).ok()??;
+                while let Some((_, mut verdauxs)) = verdefs.next().ok()? {
+                    while let Some(verdaux) = verdauxs.next().ok()? {
+                        used.push(verdaux.vda_name.get(endian) as usize);
+                    }
+                }
+            }
+            elf::SHT_GNU_VERNEED => {
+                let (mut verneeds, _) = section.gnu_verneed(endian, data).ok()??;
+                while let Some((verneed, mut vernauxs)) = verneeds.next().ok()? {
+                    used.push(verneed.vn_file.get(endian) as usize);
+                    while let Some(vernaux) = vernauxs.next().ok()? {
+                        used.push(vernaux.vna_name.get(endian) as usize);
+                    }
+                }
+            }
+            // Some other table uses the strings, and its users aren't known.
+            _ => return None,
+        }
+    }
+    let used: Vec<(usize, usize)> = used
+        .into_iter()
+        .filter_map(|offset| Some((offset, string_end(strings, offset)?)))
+        .collect();
+
+    let mut ranges = Vec::new();
+    for (&start, value) in targets.iter().zip(values) {
+        // Entries that share a string must get the same value.
+        if targets
+            .iter()
+            .zip(values)
+            .any(|(&other, other_value)| other == start && other_value != value)
+        {
+            return None;
+        }
+        // A string that ends inside this one shares its bytes.
+        if used
+            .iter()
+            .any(|&(used_start, used_end)| used_start < start && used_end > start)
+        {
+            return None;
+        }
+        let mut end = string_end(strings, start)?;
+        while strings.get(end) == Some(&0) {
+            end += 1;
+        }
+        // Stop before the next string in use, which may be a suffix of this one.
+        let next = used
+            .iter()
+            .map(|&(used_start, _)| used_start)
+            .chain(targets.iter().copied().filter(|&other| other != start))
+            .filter(|&other| other >= start)
+            .min();
+        if let Some(next) = next {
+            end = end.min(next);
+        }
+        if value.len() >= end - start {
+            return None;
+        }
+        ranges.push((strings_offset + start, strings_offset + end));
+    }
+    Some(ranges)
+}
+
+/// Return the offset just after the NUL terminator of the string at `offset`.
+fn string_end(strings: &[u8], offset: usize) -> Option<usize> {
+    let length = strings.get(offset..)?.iter().position(|&byte| byte == 0)?;
+    Some(offset + length + 1)
+}
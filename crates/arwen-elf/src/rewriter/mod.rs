@@ -15,5 +15,29 @@ pub use writer::Writer;
 
 mod elf;
 
+mod in_place;
+
 mod ext;
 pub use ext::BuilderExt;
+
+mod dynamic;
+pub(crate) use dynamic::is_address_tag;
+pub use dynamic::{
+    tag_name, DynamicEntry, DynamicFlags, DynamicValue, NeededPosition, RunPath, RunPathKind,
+    ShrinkEntry, ShrinkReason, ShrinkReport,
+};
+
+mod version;
+pub use version::VersionRequirement;
+
+mod header;
+pub use header::{decode_e_flags, ElfHeader};
+
+mod segment;
+pub use segment::{parse_segment_flags, SegmentInfo};
+
+mod property;
+pub use property::GnuProperty;
+
+mod export;
+pub use export::{ExportChange, SymbolRename};
@@ -0,0 +1,189 @@
+use std::fmt;
+
+use object::{elf, Endian, Endianness};
+use serde::Serialize;
+
+/// A property from a `NT_GNU_PROPERTY_TYPE_0` note, as found in `.note.gnu.property`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GnuProperty {
+    /// The property type, one of the `GNU_PROPERTY_*` constants.
+    pub pr_type: u32,
+    /// The name of the property type, e.g. `X86_FEATURE_1_AND`.
+    pub name: String,
+    /// The value of the property, if it is a 4 or 8 byte integer.
+    pub value: Option<u64>,
+    /// The names of the bits set in the value, e.g. `IBT` and `SHSTK`.
+    pub flags: Vec<String>,
+    /// The offset of the property data in the section.
+    #[serde(skip)]
+    pub(crate) data_offset: usize,
+}
+
+impl fmt::Display for GnuProperty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        match self.value {
+            Some(value) if self.flags.is_empty() => write!(f, ": {value:#x}"),
+            Some(value) => write!(f, ": {:#x} ({})", value, self.flags.join(", ")),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Parse the properties of the `NT_GNU_PROPERTY_TYPE_0` notes in the contents of a
+/// note section.
+///
+/// Malformed notes end the parsing, keeping the properties found before them.
+pub(crate) fn parse_gnu_properties(
+    data: &[u8],
+    endian: Endianness,
+    is_64: bool,
+    e_machine: u16,
+) -> Vec<GnuProperty> {
+    // Notes and properties are aligned to 8 bytes in 64-bit files, 4 bytes otherwise.
+    let align = if is_64 { 8 } else { 4 };
+    let read_u32 = |offset: usize| -> Option<u32> {
+        let bytes = data.get(offset..offset + 4)?;
+        Some(endian.read_u32_bytes(bytes.try_into().unwrap()))
+    };
+
+    let mut properties = Vec::new();
+    let mut offset = 0;
+    while let (Some(namesz), Some(descsz), Some(n_type)) =
+        (read_u32(offset), read_u32(offset + 4), read_u32(offset + 8))
+    {
+        let name_offset = offset + 12;
+        let desc_offset = align_up(name_offset + namesz as usize, align);
+        let desc_end = desc_offset + descsz as usize;
+        if desc_end > data.len() {
+            break;
+        }
+        let name = &data[name_offset..name_offset + namesz as usize];
+        let name = name.strip_suffix(&[0]).unwrap_or(name);
+        if name == elf::ELF_NOTE_GNU && n_type == elf::NT_GNU_PROPERTY_TYPE_0 {
+            let mut property_offset = desc_offset;
+            while let (Some(pr_type), Some(pr_datasz)) =
+                (read_u32(property_offset), read_u32(property_offset + 4))
+            {
+                let data_offset = property_offset + 8;
+                let data_end = data_offset + pr_datasz as usize;
+                if data_end > desc_end {
+                    break;
+                }
+                let value = match pr_datasz {
+                    4 => read_u32(data_offset).map(u64::from),
+                    8 => {
+                        let bytes = data[data_offset..data_end].try_into().unwrap();
+                        Some(endian.read_u64_bytes(bytes))
+                    }
+                    _ => None,
+                };
+                properties.push(GnuProperty {
+                    pr_type,
+                    name: property_name(pr_type, e_machine),
+                    value,
+                    flags: value
+                        .map(|value| property_flags(pr_type, e_machine, value))
+                        .unwrap_or_default(),
+                    data_offset,
+                });
+                property_offset = align_up(data_end, align);
+            }
+        }
+        offset = align_up(desc_end, align);
+    }
+    properties
+}
+
+/// Return the property type and bit for a feature name such as `IBT` or `BTI`.
+pub(crate) fn feature_bit(name: &str, e_machine: u16) -> Option<(u32, u32)> {
+    FEATURE_BITS
+        .iter()
+        .find(|(machines, _, _, bit_name)| {
+            machines.contains(&e_machine) && bit_name.eq_ignore_ascii_case(name)
+        })
+        .map(|&(_, pr_type, bit, _)| (pr_type, bit))
+}
+
+const X86: &[u16] = &[elf::EM_X86_64, elf::EM_386];
+const AARCH64: &[u16] = &[elf::EM_AARCH64];
+
+/// The bits of the `FEATURE_1_AND` properties, per machine.
+const FEATURE_BITS: &[(&[u16], u32, u32, &str)] = &[
+    (
+        X86,
+        elf::GNU_PROPERTY_X86_FEATURE_1_AND,
+        elf::GNU_PROPERTY_X86_FEATURE_1_IBT,
+        "IBT",
+    ),
+    (
+        X86,
+        elf::GNU_PROPERTY_X86_FEATURE_1_AND,
+        elf::GNU_PROPERTY_X86_FEATURE_1_SHSTK,
+        "SHSTK",
+    ),
+    (
+        AARCH64,
+        elf::GNU_PROPERTY_AARCH64_FEATURE_1_AND,
+        elf::GNU_PROPERTY_AARCH64_FEATURE_1_BTI,
+        "BTI",
+    ),
+    (
+        AARCH64,
+        elf::GNU_PROPERTY_AARCH64_FEATURE_1_AND,
+        elf::GNU_PROPERTY_AARCH64_FEATURE_1_PAC,
+        "PAC",
+    ),
+];
+
+fn property_name(pr_type: u32, e_machine: u16) -> String {
+    let name = match pr_type {
+        elf::GNU_PROPERTY_STACK_SIZE => "STACK_SIZE",
+        elf::GNU_PROPERTY_NO_COPY_ON_PROTECTED => "NO_COPY_ON_PROTECTED",
+        elf::GNU_PROPERTY_1_NEEDED => "1_NEEDED",
+        _ if X86.contains(&e_machine) => match pr_type {
+            elf::GNU_PROPERTY_X86_FEATURE_1_AND => "X86_FEATURE_1_AND",
+            elf::GNU_PROPERTY_X86_ISA_1_NEEDED => "X86_ISA_1_NEEDED",
+            elf::GNU_PROPERTY_X86_ISA_1_USED => "X86_ISA_1_USED",
+            _ => return format!("{pr_type:#x}"),
+        },
+        _ if AARCH64.contains(&e_machine) => match pr_type {
+            elf::GNU_PROPERTY_AARCH64_FEATURE_1_AND => "AARCH64_FEATURE_1_AND",
+            elf::GNU_PROPERTY_AARCH64_FEATURE_PAUTH => "AARCH64_FEATURE_PAUTH",
+            _ => return format!("{pr_type:#x}"),
+        },
+        _ => return format!("{pr_type:#x}"),
+    };
+    name.to_string()
+}
+
+fn property_flags(pr_type: u32, e_machine: u16, value: u64) -> Vec<String> {
+    let isa_levels: &[(u32, &str)] = &[
+        (elf::GNU_PROPERTY_X86_ISA_1_BASELINE, "x86-64-baseline"),
+        (elf::GNU_PROPERTY_X86_ISA_1_V2, "x86-64-v2"),
+        (elf::GNU_PROPERTY_X86_ISA_1_V3, "x86-64-v3"),
+        (elf::GNU_PROPERTY_X86_ISA_1_V4, "x86-64-v4"),
+    ];
+    let bits: Vec<(u32, &str)> = match pr_type {
+        elf::GNU_PROPERTY_X86_ISA_1_NEEDED | elf::GNU_PROPERTY_X86_ISA_1_USED
+            if X86.contains(&e_machine) =>
+        {
+            isa_levels.to_vec()
+        }
+        _ => FEATURE_BITS
+            .iter()
+            .filter(|(machines, feature_type, _, _)| {
+                machines.contains(&e_machine) && *feature_type == pr_type
+            })
+            .map(|&(_, _, bit, name)| (bit, name))
+            .collect(),
+    };
+    bits.into_iter()
+        .filter(|&(bit, _)| value & u64::from(bit) != 0)
+        .map(|(_, name)| name.to_string())
+        .collect()
+}
+
+fn align_up(offset: usize, align: usize) -> usize {
+    (offset + align - 1) & !(align - 1)
+}
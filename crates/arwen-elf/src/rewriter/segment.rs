@@ -0,0 +1,125 @@
+use std::fmt;
+
+use object::{
+    build::elf::{Builder, Segment},
+    elf,
+};
+use serde::Serialize;
+
+use super::BuilderExt;
+
+/// A program header and the sections mapped into it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SegmentInfo {
+    /// The index of the segment in the program header table.
+    pub index: usize,
+    /// The segment type, one of the `PT_*` constants.
+    pub p_type: u32,
+    /// The name of the segment type, e.g. `LOAD` or `GNU_RELRO`.
+    pub type_name: String,
+    /// The file offset of the segment.
+    pub p_offset: u64,
+    /// The virtual address of the segment.
+    pub p_vaddr: u64,
+    /// The physical address of the segment.
+    pub p_paddr: u64,
+    /// The size of the segment in the file.
+    pub p_filesz: u64,
+    /// The size of the segment in memory.
+    pub p_memsz: u64,
+    /// The `PF_*` bits of the segment.
+    pub p_flags: u32,
+    /// The permissions of the segment, e.g. `r-x`.
+    pub flags: String,
+    /// The alignment of the segment.
+    pub p_align: u64,
+    /// The names of the allocated sections in the memory range of the segment.
+    pub sections: Vec<String>,
+}
+
+impl SegmentInfo {
+    pub(crate) fn new(builder: &Builder<'_>, index: usize, segment: &Segment<'_>) -> Self {
+        Self {
+            index,
+            p_type: segment.p_type,
+            type_name: segment_type_name(segment.p_type),
+            p_offset: segment.p_offset,
+            p_vaddr: segment.p_vaddr,
+            p_paddr: segment.p_paddr,
+            p_filesz: segment.p_filesz,
+            p_memsz: segment.p_memsz,
+            p_flags: segment.p_flags,
+            flags: segment_flags(segment.p_flags),
+            p_align: segment.p_align,
+            sections: builder
+                .segment_sections(segment)
+                .iter()
+                .map(|section| section.name.to_string())
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Display for SegmentInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{:>2}] {:<14} offset {:#010x} vaddr {:#010x} filesz {:#08x} memsz {:#08x} {} align {:#x}",
+            self.index,
+            self.type_name,
+            self.p_offset,
+            self.p_vaddr,
+            self.p_filesz,
+            self.p_memsz,
+            self.flags,
+            self.p_align,
+        )?;
+        if !self.sections.is_empty() {
+            write!(f, " {}", self.sections.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse segment permissions such as `r-x` or `RW` into `PF_*` bits.
+///
+/// Letters are case-insensitive and `-` is ignored.
+pub fn parse_segment_flags(flags: &str) -> Option<u32> {
+    flags.chars().try_fold(0, |bits, flag| {
+        let bit = match flag.to_ascii_lowercase() {
+            'r' => elf::PF_R,
+            'w' => elf::PF_W,
+            'x' => elf::PF_X,
+            '-' => 0,
+            _ => return None,
+        };
+        Some(bits | bit)
+    })
+}
+
+fn segment_flags(p_flags: u32) -> String {
+    [(elf::PF_R, 'r'), (elf::PF_W, 'w'), (elf::PF_X, 'x')]
+        .iter()
+        .map(|&(bit, name)| if p_flags & bit != 0 { name } else { '-' })
+        .collect()
+}
+
+fn segment_type_name(p_type: u32) -> String {
+    let name = match p_type {
+        elf::PT_NULL => "NULL",
+        elf::PT_LOAD => "LOAD",
+        elf::PT_DYNAMIC => "DYNAMIC",
+        elf::PT_INTERP => "INTERP",
+        elf::PT_NOTE => "NOTE",
+        elf::PT_SHLIB => "SHLIB",
+        elf::PT_PHDR => "PHDR",
+        elf::PT_TLS => "TLS",
+        elf::PT_GNU_EH_FRAME => "GNU_EH_FRAME",
+        elf::PT_GNU_STACK => "GNU_STACK",
+        elf::PT_GNU_RELRO => "GNU_RELRO",
+        elf::PT_GNU_PROPERTY => "GNU_PROPERTY",
+        elf::PT_GNU_SFRAME => "GNU_SFRAME",
+        _ => return format!("{p_type:#x}"),
+    };
+    name.to_string()
+}
@@ -0,0 +1,18 @@
+use std::fmt;
+
+use serde::Serialize;
+
+/// The symbol versions required from a single library, as listed in `.gnu.version_r`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VersionRequirement {
+    /// The file name of the library providing the versions, e.g. `libc.so.6`.
+    pub library: String,
+    /// The names of the required versions, e.g. `GLIBC_2.34`.
+    pub versions: Vec<String>,
+}
+
+impl fmt::Display for VersionRequirement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.library, self.versions.join(" "))
+    }
+}
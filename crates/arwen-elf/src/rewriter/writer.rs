@@ -11,14 +11,26 @@ use std::{
 use object::{
     build::{
         self,
-        elf::{Header, VersionId},
+        elf::{DynamicSymbolId, Header, SectionData, VersionData, VersionFileId, VersionId},
         ByteString,
     },
-    elf,
-    read::elf::FileHeader,
+    elf, Endian, Endianness,
 };
 
-use super::{elf::move_sections, BuilderExt, ElfError, Result};
+use super::{
+    dynamic::{is_address_tag, tag_name},
+    elf::{
+        move_sections, realign_segments, release_shared_pages, sync_gnu_property_segment,
+        GNU_PROPERTY_SECTION,
+    },
+    export::{change_export, copy_relocation_type},
+    in_place::{patch_dynamic_strings, patch_interpreter},
+    property::{feature_bit, parse_gnu_properties},
+    BuilderExt, DynamicEntry, DynamicFlags, ElfError, ElfHeader, ExportChange, GnuProperty,
+    NeededPosition, Result, RunPath, RunPathKind, SegmentInfo, ShrinkEntry, ShrinkReason,
+    ShrinkReport, SymbolRename, VersionRequirement,
+};
+use crate::util::{expand_tokens, find_token, wildcard_match, ElfIdentity};
 
 /// A rewriter for object and executable files.
 ///
@@ -28,6 +40,12 @@ pub struct Writer<'data> {
     pub(crate) builder: build::elf::Builder<'data>,
     pub(crate) modified: bool,
     pub(crate) page_size: Option<u32>,
+    pub(crate) check: bool,
+    /// The original file.
+    pub(crate) data: &'data [u8],
+    /// The original file with the edits patched in place, while those are the only
+    /// edits.
+    pub(crate) patched: Option<Vec<u8>>,
 }
 
 impl<'data> Writer<'data> {
@@ -38,6 +56,9 @@ impl<'data> Writer<'data> {
             builder,
             modified: false,
             page_size: None,
+            check: false,
+            data,
+            patched: None,
         })
     }
 
@@ -51,7 +72,7 @@ impl<'data> Writer<'data> {
 
     /// Delete symbols from the symbol table.
     pub fn elf_delete_symbols(&mut self, names: &HashSet<Vec<u8>>) {
-        for symbol in &mut self.builder.dynamic_symbols {
+        for symbol in &mut self.builder.symbols {
             if names.contains(&*symbol.name) {
                 symbol.delete = true;
                 self.modified = true;
@@ -60,20 +81,51 @@ impl<'data> Writer<'data> {
     }
 
     /// Delete symbols from the dynamic symbol table.
-    pub fn elf_delete_dynamic_symbols(&mut self, names: &HashSet<Vec<u8>>) {
-        for symbol in &mut self.builder.symbols {
+    ///
+    /// Symbols that dynamic relocations still refer to can't be deleted, since the
+    /// relocations would be dropped with them and leave their slots unrelocated.
+    /// Nothing is deleted if any of the symbols is refused.
+    pub fn elf_delete_dynamic_symbols(&mut self, names: &HashSet<Vec<u8>>) -> Result<()> {
+        let relocated = self.relocated_dynamic_symbols();
+        if let Some(symbol) = self
+            .builder
+            .dynamic_symbols
+            .iter()
+            .find(|symbol| names.contains(&*symbol.name) && relocated.contains(&symbol.id()))
+        {
+            return Err(ElfError::Modify(format!(
+                "Can't delete the dynamic symbol {}: dynamic relocations refer to it",
+                symbol.name
+            )));
+        }
+        for symbol in &mut self.builder.dynamic_symbols {
             if names.contains(&*symbol.name) {
                 symbol.delete = true;
                 self.modified = true;
             }
         }
+        Ok(())
+    }
+
+    /// The dynamic symbols that dynamic relocations refer to.
+    fn relocated_dynamic_symbols(&self) -> Vec<DynamicSymbolId> {
+        self.builder
+            .sections
+            .iter()
+            .filter_map(|section| match &section.data {
+                SectionData::DynamicRelocation(relocations) => Some(relocations),
+                _ => None,
+            })
+            .flatten()
+            .filter_map(|relocation| relocation.symbol)
+            .collect()
     }
 
     /// Rename symbols in the symbol table.
     ///
     /// The `names` map is from old names to new names.
     pub fn elf_rename_symbols(&mut self, names: &HashMap<Vec<u8>, Vec<u8>>) {
-        for symbol in &mut self.builder.dynamic_symbols {
+        for symbol in &mut self.builder.symbols {
             if let Some(name) = names.get(&*symbol.name) {
                 let name = name.clone().into();
                 symbol.name = name;
@@ -95,17 +147,294 @@ impl<'data> Writer<'data> {
         }
     }
 
-    pub(crate) fn elf_delete_sections(&mut self, names: &HashSet<Vec<u8>>) {
-        for section in &mut self.builder.sections {
-            if names.contains(&*section.name) {
-                // Associated program header will be deleted by delete_orphan_segments.
-                section.delete = true;
+    /// Add `prefix` to the name of every symbol exported by the file.
+    ///
+    /// Local and hidden symbols aren't exported, and the symbols naming the version
+    /// definitions are left alone. Names that already start with `prefix` are kept, so
+    /// that prefixing twice changes nothing. The versions of the symbols are kept.
+    pub fn elf_prefix_exports(&mut self, prefix: &str) -> Vec<SymbolRename> {
+        let version_names: Vec<&[u8]> = self
+            .builder
+            .versions
+            .iter()
+            .filter_map(|version| match &version.data {
+                VersionData::Def(def) => def.names.first().map(|name| name.as_slice()),
+                VersionData::Need(_) => None,
+            })
+            .collect();
+
+        let mut renames = Vec::new();
+        for symbol in &self.builder.dynamic_symbols {
+            let defined = symbol.section.is_some() || symbol.st_shndx != elf::SHN_UNDEF;
+            let visibility = symbol.st_other & 0x3;
+            if symbol.delete
+                || symbol.name.is_empty()
+                || !defined
+                || symbol.st_bind() == elf::STB_LOCAL
+                || visibility == elf::STV_HIDDEN
+                || visibility == elf::STV_INTERNAL
+                || symbol.name.starts_with(prefix.as_bytes())
+            {
+                continue;
+            }
+            if symbol.st_shndx == elf::SHN_ABS && version_names.contains(&symbol.name.as_slice()) {
+                continue;
+            }
+            let old = symbol.name.to_string();
+            let rename = SymbolRename {
+                new: format!("{prefix}{old}"),
+                old,
+                version: self.version_name(symbol.version).map(|(name, _)| name),
+            };
+            renames.push((symbol.id(), rename));
+        }
+
+        for (id, rename) in &renames {
+            self.builder.dynamic_symbols.get_mut(*id).name = rename.new.clone().into_bytes().into();
+            self.modified = true;
+        }
+        renames.into_iter().map(|(_, rename)| rename).collect()
+    }
+
+    /// Rename the symbols that the file imports from `library`, following the renames
+    /// done by [`Writer::elf_prefix_exports`] on that library.
+    ///
+    /// Imports with a version required from another library are left alone, and so are
+    /// imports whose version doesn't match the renamed symbol. Data that is copied into
+    /// an executable by a copy relocation is renamed too. Returns the renames done.
+    pub fn elf_rename_imports(
+        &mut self,
+        library: &[u8],
+        renames: &[SymbolRename],
+    ) -> Result<Vec<SymbolRename>> {
+        if !self.elf_needed().any(|needed| needed == library) {
+            return Err(ElfError::Modify(format!(
+                "The file doesn't need {}; can't rename its imports",
+                String::from_utf8_lossy(library)
+            )));
+        }
+        let copied: Vec<_> = match copy_relocation_type(self.builder.header.e_machine) {
+            Some(copy_type) => self
+                .builder
+                .sections
+                .iter()
+                .filter_map(|section| match &section.data {
+                    SectionData::DynamicRelocation(relocations) => Some(relocations),
+                    _ => None,
+                })
+                .flatten()
+                .filter(|relocation| relocation.r_type == copy_type)
+                .filter_map(|relocation| relocation.symbol)
+                .collect(),
+            None => Vec::new(),
+        };
+
+        let mut done = Vec::new();
+        for symbol in &self.builder.dynamic_symbols {
+            let defined = symbol.section.is_some() || symbol.st_shndx != elf::SHN_UNDEF;
+            if symbol.delete
+                || symbol.name.is_empty()
+                || (defined && !copied.contains(&symbol.id()))
+            {
+                continue;
+            }
+            let version = self.version_name(symbol.version);
+            if let Some((_, Some(file))) = &version {
+                if file.as_bytes() != library {
+                    continue;
+                }
+            }
+            let version = version.map(|(name, _)| name);
+            let Some(rename) = renames.iter().find(|rename| {
+                rename.old.as_bytes() == symbol.name.as_slice()
+                    && (version.is_none() || rename.version.is_none() || rename.version == version)
+            }) else {
+                continue;
+            };
+            done.push((
+                symbol.id(),
+                SymbolRename {
+                    old: rename.old.clone(),
+                    new: rename.new.clone(),
+                    version,
+                },
+            ));
+        }
+
+        for (id, rename) in &done {
+            self.builder.dynamic_symbols.get_mut(*id).name = rename.new.clone().into_bytes().into();
+            self.modified = true;
+        }
+        Ok(done.into_iter().map(|(_, rename)| rename).collect())
+    }
+
+    /// Return the name of a symbol version, and the library it is required from if it
+    /// is a version requirement.
+    fn version_name(&self, version: VersionId) -> Option<(String, Option<String>)> {
+        if version.is_special() {
+            return None;
+        }
+        match &self.builder.versions.get(version).data {
+            VersionData::Def(def) => Some((def.names.first()?.to_string(), None)),
+            VersionData::Need(need) => Some((
+                need.name.to_string(),
+                Some(self.builder.version_files.get(need.file).name.to_string()),
+            )),
+        }
+    }
+
+    /// Change how the defined dynamic symbols matching any of `patterns` are exported.
+    ///
+    /// Patterns are symbol names where `*` and `?` are wildcards. Undefined symbols are
+    /// imports and are never changed. Returns the names of the symbols that changed.
+    pub fn elf_change_symbol_export(
+        &mut self,
+        patterns: &[String],
+        change: ExportChange,
+    ) -> Vec<String> {
+        let relocated = self.relocated_dynamic_symbols();
+
+        let mut changed = Vec::new();
+        for symbol in &mut self.builder.dynamic_symbols {
+            let defined = symbol.section.is_some() || symbol.st_shndx != elf::SHN_UNDEF;
+            if symbol.delete || symbol.name.is_empty() || !defined {
+                continue;
+            }
+            let name = symbol.name.to_string();
+            if !patterns
+                .iter()
+                .any(|pattern| wildcard_match(pattern, &name))
+            {
+                continue;
+            }
+            if change_export(symbol, change, &relocated) {
+                changed.push(name);
                 self.modified = true;
             }
         }
+        changed
     }
 
-    pub(crate) fn elf_rename_sections(&mut self, names: &HashMap<Vec<u8>, Vec<u8>>) {
+    /// Delete sections from the file.
+    ///
+    /// Every section with one of the names is deleted, also when several sections
+    /// share a name. Sections that are referenced by the dynamic section or by a
+    /// program header can't be deleted, since the loader would still try to use them;
+    /// nothing is deleted if any of the sections is refused.
+    /// Deleting the symbol table also deletes all of its symbols.
+    pub fn elf_delete_sections(&mut self, names: &HashSet<Vec<u8>>) -> Result<()> {
+        let mut ids = Vec::new();
+        for name in names {
+            let sections: Vec<_> = self
+                .builder
+                .sections
+                .iter()
+                .filter(|section| section.name.as_slice() == name.as_slice())
+                .collect();
+            if sections.is_empty() {
+                return Err(ElfError::Modify(format!(
+                    "Section {} not found",
+                    String::from_utf8_lossy(name)
+                )));
+            }
+            for section in sections {
+                if let Some(reason) = self.section_reference(section) {
+                    return Err(ElfError::Modify(format!(
+                        "Section {} is referenced by {reason}; can't delete it",
+                        String::from_utf8_lossy(name)
+                    )));
+                }
+                ids.push(section.id());
+            }
+        }
+
+        let deletes = |matches: fn(&SectionData) -> bool| {
+            ids.iter()
+                .any(|id| matches(&self.builder.sections.get(*id).data))
+        };
+        let deletes_symtab = deletes(|data| matches!(data, SectionData::Symbol));
+        let deletes_strtab = deletes(|data| matches!(data, SectionData::String));
+        let has_symtab = self
+            .builder
+            .sections
+            .iter()
+            .any(|section| matches!(section.data, SectionData::Symbol));
+        if deletes_strtab && has_symtab && !deletes_symtab {
+            return Err(ElfError::Modify(
+                "The string table is used by the symbol table; can't delete it".to_string(),
+            ));
+        }
+
+        for id in ids {
+            // Associated program header will be deleted by delete_orphan_segments.
+            self.builder.sections.get_mut(id).delete = true;
+        }
+        if deletes_symtab {
+            for section in &mut self.builder.sections {
+                if matches!(section.data, SectionData::SymbolSectionIndex) {
+                    section.delete = true;
+                }
+            }
+            for symbol in &mut self.builder.symbols {
+                symbol.delete = true;
+            }
+        }
+        self.modified = true;
+        Ok(())
+    }
+
+    /// Describe what still references a section, if anything.
+    fn section_reference(&self, section: &build::elf::Section) -> Option<String> {
+        let id = section.id();
+        if let Some(segment) = self
+            .builder
+            .segments
+            .iter()
+            .find(|segment| segment.sections.contains(&id))
+        {
+            return Some(format!("a program header of type {:#x}", segment.p_type));
+        }
+
+        match section.data {
+            SectionData::SectionString => return Some("the section headers".to_string()),
+            SectionData::Dynamic(_)
+            | SectionData::DynamicSymbol
+            | SectionData::DynamicString
+            | SectionData::Hash
+            | SectionData::GnuHash
+            | SectionData::GnuVersym
+            | SectionData::GnuVerdef
+            | SectionData::GnuVerneed
+                if self.builder.dynamic_section().is_some() =>
+            {
+                return Some("the dynamic section".to_string());
+            }
+            _ => {}
+        }
+
+        if !section.is_alloc() {
+            return None;
+        }
+        let dynamic = self.builder.dynamic_data().unwrap_or(&[]);
+        for entry in dynamic.iter() {
+            let build::elf::Dynamic::Integer { tag, val } = entry else {
+                continue;
+            };
+            if !is_address_tag(*tag) {
+                continue;
+            }
+            if *val >= section.sh_addr && *val < section.sh_addr + section.sh_size.max(1) {
+                let name = tag_name(*tag).map_or_else(|| format!("{tag:#x}"), str::to_string);
+                return Some(format!("the {name} dynamic entry"));
+            }
+        }
+        None
+    }
+
+    /// Rename sections in the file.
+    ///
+    /// The `names` map is from old names to new names.
+    pub fn elf_rename_sections(&mut self, names: &HashMap<Vec<u8>, Vec<u8>>) {
         for section in &mut self.builder.sections {
             if let Some(name) = names.get(&*section.name) {
                 let name = name.clone().into();
@@ -115,6 +444,156 @@ impl<'data> Writer<'data> {
         }
     }
 
+    /// Add a new `SHT_PROGBITS` section with the given contents.
+    ///
+    /// `flags` are the `SHF_*` flags of the section. Allocated sections are placed
+    /// into a new `PT_LOAD` segment when the file is written; other sections are
+    /// appended to the end of the file.
+    pub fn elf_add_section(
+        &mut self,
+        name: Vec<u8>,
+        flags: u64,
+        data: Vec<u8>,
+        alloc: bool,
+    ) -> Result<()> {
+        if self.find_section(&name).is_some() {
+            return Err(ElfError::Modify(format!(
+                "Section {} already exists",
+                String::from_utf8_lossy(&name)
+            )));
+        }
+
+        let mut sh_flags = flags & !u64::from(elf::SHF_ALLOC);
+        if alloc {
+            sh_flags |= u64::from(elf::SHF_ALLOC);
+        }
+        let section = self.builder.sections.add();
+        section.name = name.into();
+        section.sh_type = elf::SHT_PROGBITS;
+        section.sh_flags = sh_flags;
+        section.sh_addralign = if alloc { 8 } else { 1 };
+        section.sh_size = data.len() as u64;
+        // A zero offset marks the section for placement by `move_sections`.
+        section.sh_offset = 0;
+        section.data = SectionData::Data(data.into());
+        self.modified = true;
+        Ok(())
+    }
+
+    /// Replace the contents of an existing section.
+    ///
+    /// Only sections holding raw data or notes can be updated. The contents of an
+    /// allocated section can't grow past its current size, since code may refer to
+    /// the addresses that follow it.
+    pub fn elf_update_section(&mut self, name: &[u8], data: Vec<u8>) -> Result<()> {
+        let id = self
+            .find_section(name)
+            .ok_or_else(|| {
+                ElfError::Modify(format!(
+                    "Section {} not found",
+                    String::from_utf8_lossy(name)
+                ))
+            })?
+            .id();
+        let section = self.builder.sections.get_mut(id);
+        if section.is_alloc() && section.sh_offset != 0 && data.len() as u64 > section.sh_size {
+            return Err(ElfError::Modify(format!(
+                "Section {} is allocated; new contents must not be larger than {} bytes",
+                String::from_utf8_lossy(name),
+                section.sh_size
+            )));
+        }
+        match &mut section.data {
+            SectionData::Data(bytes) | SectionData::Note(bytes) => *bytes = data.into(),
+            _ => {
+                return Err(ElfError::Modify(format!(
+                    "Section {} doesn't hold raw data; can't update it",
+                    String::from_utf8_lossy(name)
+                )))
+            }
+        }
+        self.modified = true;
+        Ok(())
+    }
+
+    /// Return the contents of a section holding raw data or notes.
+    pub fn elf_section_data(&self, name: &[u8]) -> Result<&[u8]> {
+        let section = self.find_section(name).ok_or_else(|| {
+            ElfError::Modify(format!(
+                "Section {} not found",
+                String::from_utf8_lossy(name)
+            ))
+        })?;
+        match &section.data {
+            SectionData::Data(bytes) | SectionData::Note(bytes) => Ok(bytes),
+            _ => Err(ElfError::Modify(format!(
+                "Section {} doesn't hold raw data; can't dump it",
+                String::from_utf8_lossy(name)
+            ))),
+        }
+    }
+
+    /// Return the properties of the `NT_GNU_PROPERTY_TYPE_0` notes in `.note.gnu.property`.
+    pub fn elf_gnu_properties(&self) -> Vec<GnuProperty> {
+        let Ok(data) = self.elf_section_data(GNU_PROPERTY_SECTION) else {
+            return Vec::new();
+        };
+        parse_gnu_properties(
+            data,
+            self.builder.endian,
+            self.builder.is_64,
+            self.builder.header.e_machine,
+        )
+    }
+
+    /// Clear a feature bit of the GNU properties, such as `IBT` or `SHSTK` on x86
+    /// and `BTI` or `PAC` on AArch64.
+    ///
+    /// The note keeps its size, so `PT_GNU_PROPERTY` still covers it.
+    pub fn elf_clear_gnu_property(&mut self, feature: &str) -> Result<()> {
+        let e_machine = self.builder.header.e_machine;
+        let (pr_type, bit) = feature_bit(feature, e_machine).ok_or_else(|| {
+            ElfError::Modify(format!(
+                "Unknown property {feature} for machine {e_machine}; expected IBT or SHSTK on x86, BTI or PAC on AArch64"
+            ))
+        })?;
+        let property = self
+            .elf_gnu_properties()
+            .into_iter()
+            .find(|property| property.pr_type == pr_type);
+        let Some(GnuProperty {
+            value: Some(value),
+            data_offset,
+            ..
+        }) = property
+        else {
+            // Without the property, the feature is already disabled.
+            return Ok(());
+        };
+        if value & u64::from(bit) == 0 {
+            return Ok(());
+        }
+
+        let endian = self.builder.endian;
+        let id = self.find_section(GNU_PROPERTY_SECTION).unwrap().id();
+        let (SectionData::Data(bytes) | SectionData::Note(bytes)) =
+            &mut self.builder.sections.get_mut(id).data
+        else {
+            unreachable!("the properties were read from the section data");
+        };
+        let value = endian.write_u32_bytes(value as u32 & !bit);
+        bytes.to_mut()[data_offset..data_offset + 4].copy_from_slice(&value);
+        self.modified = true;
+        Ok(())
+    }
+
+    fn find_section(&self, name: &[u8]) -> Option<&build::elf::Section<'data>> {
+        self.builder
+            .sections
+            .iter()
+            .find(|section| section.name.as_slice() == name)
+    }
+
     /// Add a `DT_DEBUG` entry to the dynamic section.
     pub fn elf_add_dynamic_debug(&mut self) -> Result<()> {
         let dynamic = self.builder.dynamic_data_mut().ok_or_else(|| {
@@ -131,6 +610,85 @@ impl<'data> Writer<'data> {
         Ok(())
     }
 
+    /// Add a `.gnu.hash` section and a `DT_GNU_HASH` entry to a file that only has a
+    /// `DT_HASH` table.
+    ///
+    /// Both tables, and the order of the dynamic symbols that `.gnu.hash` requires, are
+    /// regenerated from the dynamic symbols when the file is written. Nothing is done if
+    /// the file already has a `.gnu.hash` section.
+    pub fn elf_add_gnu_hash(&mut self) -> Result<()> {
+        let has_section = |matches: fn(&SectionData) -> bool| {
+            self.builder
+                .sections
+                .iter()
+                .any(|section| !section.delete && matches(&section.data))
+        };
+        if has_section(|data| matches!(data, SectionData::GnuHash)) {
+            return Ok(());
+        }
+        if !has_section(|data| matches!(data, SectionData::DynamicSymbol)) {
+            return Err(ElfError::Modify(
+                "No dynamic symbol table found; can't add a GNU hash table".to_string(),
+            ));
+        }
+
+        // Only defined symbols are hashed.
+        let symbol_count = self
+            .builder
+            .dynamic_symbols
+            .iter()
+            .filter(|symbol| {
+                !symbol.delete
+                    && !symbol.name.is_empty()
+                    && (symbol.section.is_some() || symbol.st_shndx != elf::SHN_UNDEF)
+            })
+            .count() as u32;
+        // The same sizes as lld: 12 bloom bits per symbol and 2 symbols per bucket.
+        let word_bits = if self.builder.is_64 { 64 } else { 32 };
+        let bloom_count = (symbol_count * 12 / word_bits).next_power_of_two();
+        let bucket_count = symbol_count.div_ceil(2).max(1);
+
+        let dynamic = self.builder.dynamic_data_mut().ok_or_else(|| {
+            ElfError::Modify("No dynamic section found; can't add a GNU hash table".to_string())
+        })?;
+        // Keep the hash tables together at the start of the dynamic section.
+        let position = dynamic
+            .iter()
+            .position(|entry| entry.tag() == elf::DT_HASH)
+            .unwrap_or(0);
+        dynamic.insert(
+            position,
+            build::elf::Dynamic::Auto {
+                tag: elf::DT_GNU_HASH,
+            },
+        );
+
+        self.builder.gnu_hash_bloom_shift = 26;
+        self.builder.gnu_hash_bloom_count = bloom_count;
+        self.builder.gnu_hash_bucket_count = bucket_count;
+        let section = self.builder.sections.add();
+        section.name = b".gnu.hash".to_vec().into();
+        section.sh_type = elf::SHT_GNU_HASH;
+        section.sh_flags = u64::from(elf::SHF_ALLOC);
+        section.sh_addralign = u64::from(word_bits / 8);
+        // A zero offset marks the section for placement by `move_sections`.
+        section.sh_offset = 0;
+        section.data = SectionData::GnuHash;
+        self.modified = true;
+        Ok(())
+    }
+
+    /// Return all entries of the dynamic section, in file order.
+    ///
+    /// Returns an empty list if there is no dynamic section.
+    pub fn elf_dynamic_entries(&self) -> Vec<DynamicEntry> {
+        let dynamic = self.builder.dynamic_data().unwrap_or(&[]);
+        dynamic
+            .iter()
+            .map(|entry| DynamicEntry::new(&self.builder, entry))
+            .collect()
+    }
+
     /// Find the first `DT_RUNPATH` or `DT_RPATH` entry in the dynamic section.
     pub fn elf_runpath(&self) -> Option<&[u8]> {
         let dynamic = self.builder.dynamic_data()?;
@@ -146,6 +704,25 @@ impl<'data> Writer<'data> {
         None
     }
 
+    /// Find all `DT_RPATH` and `DT_RUNPATH` entries in the dynamic section, in file order.
+    pub fn elf_runpaths(&self) -> Vec<RunPath> {
+        let dynamic = self.builder.dynamic_data().unwrap_or(&[]);
+        dynamic
+            .iter()
+            .filter_map(|entry| {
+                let build::elf::Dynamic::String { tag, val } = entry else {
+                    return None;
+                };
+                let kind = match *tag {
+                    elf::DT_RPATH => RunPathKind::Rpath,
+                    elf::DT_RUNPATH => RunPathKind::Runpath,
+                    _ => return None,
+                };
+                Some(RunPath::new(kind, val))
+            })
+            .collect()
+    }
+
     /// Delete any `DT_RUNPATH` or `DT_RPATH` entries in the dynamic section.
     pub fn elf_delete_runpath(&mut self) -> Result<()> {
         let dynamic = self.builder.dynamic_data_mut().ok_or_else(|| {
@@ -191,7 +768,7 @@ impl<'data> Writer<'data> {
                 val,
             });
         }
-        self.modified = true;
+        self.patch_runpath_in_place();
         Ok(())
     }
 
@@ -224,10 +801,30 @@ impl<'data> Writer<'data> {
                 val,
             });
         }
-        self.modified = true;
+        self.patch_runpath_in_place();
         Ok(())
     }
 
+    /// Patch the runpaths set in the builder into the file bytes, if they fit in the
+    /// space of the old ones.
+    fn patch_runpath_in_place(&mut self) {
+        let tags = [elf::DT_RPATH, elf::DT_RUNPATH];
+        let values: Vec<Vec<u8>> = self
+            .builder
+            .dynamic_data()
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| match entry {
+                build::elf::Dynamic::String { tag, val } if tags.contains(tag) => {
+                    Some(val.to_vec())
+                }
+                _ => None,
+            })
+            .collect();
+        let values: Vec<&[u8]> = values.iter().map(Vec::as_slice).collect();
+        self.patch_in_place(|data| patch_dynamic_strings(data, &tags, &values));
+    }
+
     /// Change any `DT_RPATH` entry in the dynamic section to `DT_RUNPATH`.
     pub fn elf_use_runpath(&mut self) -> Result<()> {
         let dynamic = self.builder.dynamic_data_mut().ok_or_else(|| {
@@ -300,6 +897,9 @@ impl<'data> Writer<'data> {
     }
 
     /// Replace `DT_NEEDED` entries in the dynamic section.
+    ///
+    /// The symbol version requirements on a replaced library are moved to the new
+    /// name too, since the loader matches them against the needed libraries.
     pub fn elf_replace_needed(&mut self, names: &HashMap<Vec<u8>, Vec<u8>>) -> Result<()> {
         let dynamic = self.builder.dynamic_data_mut().ok_or_else(|| {
             ElfError::Modify("No dynamic section found; can't replace needed library".to_string())
@@ -319,44 +919,133 @@ impl<'data> Writer<'data> {
             *val = name;
             self.modified = true;
         }
+        for (old, new) in names {
+            self.elf_rename_version_file(old, new);
+        }
         Ok(())
     }
 
-    /// Add `DT_NEEDED` entries to the start of the dynamic section.
+    /// Add `DT_NEEDED` entries before the existing ones.
     ///
     /// This does not add a `DT_NEEDED` entry if the library is already listed.
     pub fn elf_add_needed(&mut self, names: &[Vec<u8>]) -> Result<()> {
+        self.elf_add_needed_at(names, &NeededPosition::default())
+    }
+
+    /// Add `DT_NEEDED` entries at a position among the existing ones, keeping their
+    /// order.
+    ///
+    /// This does not add a `DT_NEEDED` entry if the library is already listed.
+    pub fn elf_add_needed_at(
+        &mut self,
+        names: &[Vec<u8>],
+        position: &NeededPosition,
+    ) -> Result<()> {
         let dynamic = self.builder.dynamic_data_mut().ok_or_else(|| {
             ElfError::Modify("No dynamic section found; can't add needed library".to_string())
         })?;
-        let mut found = HashSet::new();
-        for entry in dynamic.iter() {
-            let build::elf::Dynamic::String { tag, val } = entry else {
-                continue;
-            };
-            if *tag != elf::DT_NEEDED {
-                continue;
-            }
-            found.insert(val.clone());
-        }
-        for name in names
+        let needed: Vec<(usize, &[u8])> = dynamic
             .iter()
-            .rev()
-            .filter(|name| !found.contains(name.as_slice()))
-        {
-            let val = name.clone().into();
-            dynamic.insert(
-                0,
-                build::elf::Dynamic::String {
-                    tag: elf::DT_NEEDED,
-                    val,
-                },
-            );
+            .enumerate()
+            .filter_map(|(index, entry)| match entry {
+                build::elf::Dynamic::String { tag, val } if *tag == elf::DT_NEEDED => {
+                    Some((index, val.as_slice()))
+                }
+                _ => None,
+            })
+            .collect();
+        let find = |library: &[u8]| {
+            needed
+                .iter()
+                .find(|(_, name)| *name == library)
+                .map(|(index, _)| *index)
+                .ok_or_else(|| {
+                    ElfError::Modify(format!(
+                        "{} is not needed; can't add libraries next to it",
+                        String::from_utf8_lossy(library)
+                    ))
+                })
+        };
+        let index = match position {
+            NeededPosition::Index(position) => match needed.get(*position) {
+                Some((index, _)) => *index,
+                None if *position == needed.len() => {
+                    needed.last().map_or(0, |(index, _)| index + 1)
+                }
+                None => {
+                    return Err(ElfError::Modify(format!(
+                        "Position {position} is past the end of the {} needed libraries",
+                        needed.len()
+                    )))
+                }
+            },
+            NeededPosition::Before(library) => find(library)?,
+            NeededPosition::After(library) => find(library)? + 1,
+        };
+
+        let found: HashSet<Vec<u8>> = needed.iter().map(|(_, name)| name.to_vec()).collect();
+        let entries: Vec<_> = names
+            .iter()
+            .filter(|name| !found.contains(*name))
+            .map(|name| build::elf::Dynamic::String {
+                tag: elf::DT_NEEDED,
+                val: name.clone().into(),
+            })
+            .collect();
+        if !entries.is_empty() {
+            dynamic.splice(index..index, entries);
             self.modified = true;
         }
         Ok(())
     }
 
+    /// Reorder the `DT_NEEDED` entries.
+    ///
+    /// The listed libraries come first, in the given order, followed by the other
+    /// libraries in their current order. The entries keep their slots in the dynamic
+    /// section.
+    pub fn elf_reorder_needed(&mut self, order: &[Vec<u8>]) -> Result<()> {
+        let dynamic = self.builder.dynamic_data_mut().ok_or_else(|| {
+            ElfError::Modify("No dynamic section found; can't reorder needed libraries".to_string())
+        })?;
+        let slots: Vec<usize> = dynamic
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.tag() == elf::DT_NEEDED)
+            .map(|(index, _)| index)
+            .collect();
+        let mut entries: Vec<_> = slots.iter().map(|index| dynamic[*index].clone()).collect();
+        let name = |entry: &build::elf::Dynamic| match entry {
+            build::elf::Dynamic::String { val, .. } => val.to_vec(),
+            _ => Vec::new(),
+        };
+
+        let mut reordered = Vec::with_capacity(entries.len());
+        for library in order {
+            let Some(position) = entries.iter().position(|entry| name(entry) == *library) else {
+                let reason = if reordered.iter().any(|entry| name(entry) == *library) {
+                    "is listed twice"
+                } else {
+                    "is not needed"
+                };
+                return Err(ElfError::Modify(format!(
+                    "{} {reason}; can't reorder needed libraries",
+                    String::from_utf8_lossy(library)
+                )));
+            };
+            reordered.push(entries.remove(position));
+        }
+        reordered.extend(entries);
+
+        for (index, entry) in slots.into_iter().zip(reordered) {
+            if dynamic[index] != entry {
+                dynamic[index] = entry;
+                self.modified = true;
+            }
+        }
+        Ok(())
+    }
+
     /// Find the `DT_SONAME` entry in the dynamic section.
     pub fn elf_soname(&self) -> Option<&[u8]> {
         let id = self.builder.dynamic_section()?;
@@ -378,6 +1067,9 @@ impl<'data> Writer<'data> {
     }
 
     /// Set the `DT_SONAME` entry in the dynamic section.
+    ///
+    /// The base version definition, which names the file for the version
+    /// requirements of its consumers, is renamed too.
     pub fn elf_set_soname(&mut self, soname: Vec<u8>) -> Result<()> {
         let dynamic = self.builder.dynamic_data_mut().ok_or_else(|| {
             ElfError::Modify("No dynamic section found; can't set soname".to_string())
@@ -395,12 +1087,13 @@ impl<'data> Writer<'data> {
             found = true;
         }
         if !found {
-            let val = soname.into();
+            let val = soname.clone().into();
             dynamic.push(build::elf::Dynamic::String {
                 tag: elf::DT_SONAME,
                 val,
             });
         }
+        self.elf_set_version_base(soname);
         self.modified = true;
         Ok(())
     }
@@ -421,11 +1114,28 @@ impl<'data> Writer<'data> {
         if !interpreter.is_empty() && interpreter.last() != Some(&0) {
             interpreter.push(0);
         }
-        *data = interpreter.into();
-        self.modified = true;
+        *data = interpreter.clone().into();
+        self.patch_in_place(|data| patch_interpreter(data, &interpreter));
         Ok(())
     }
 
+    /// Apply an edit that was made to the builder to the file bytes too, so that the
+    /// file can be written without being rebuilt.
+    ///
+    /// This is only done while all the earlier edits were patched in place as well.
+    /// If `patch` fails, the file is rebuilt from the builder when written.
+    fn patch_in_place(&mut self, patch: impl FnOnce(&mut [u8]) -> bool) {
+        if self.modified {
+            return;
+        }
+        let mut data = self.patched.take().unwrap_or_else(|| self.data.to_vec());
+        if patch(&mut data) {
+            self.patched = Some(data);
+        } else {
+            self.modified = true;
+        }
+    }
+
     /// Set the EI_OSABI to some ABI
     pub fn elf_set_osabi(&mut self, abi_name: &str) -> Result<()> {
         let header = &mut self.builder.header;
@@ -460,114 +1170,222 @@ impl<'data> Writer<'data> {
         Ok(())
     }
 
+    /// Set the EI_ABIVERSION byte of the file header.
+    pub fn elf_set_abi_version(&mut self, abi_version: u8) {
+        self.builder.header.abi_version = abi_version;
+        self.modified = true;
+    }
+
+    /// Set the processor-specific `e_flags` of the file header.
+    pub fn elf_set_e_flags(&mut self, e_flags: u32) {
+        self.builder.header.e_flags = e_flags;
+        self.modified = true;
+    }
+
+    /// Return the decoded fields of the file header.
+    pub fn elf_header(&self) -> ElfHeader {
+        ElfHeader::new(&self.builder)
+    }
+
+    /// Return the program headers and the sections mapped into them.
+    pub fn elf_segments(&self) -> Vec<SegmentInfo> {
+        self.builder
+            .segments
+            .iter()
+            .filter(|segment| !segment.delete)
+            .enumerate()
+            .map(|(index, segment)| SegmentInfo::new(&self.builder, index, segment))
+            .collect()
+    }
+
+    /// Set the `p_flags` of the segment at `index` in the program header table.
+    ///
+    /// Unless `force` is set, this fails if a section would be mapped both
+    /// writable and executable.
+    pub fn elf_set_segment_flags(&mut self, index: usize, p_flags: u32, force: bool) -> Result<()> {
+        let segment = self.builder.segment_at_mut(index).ok_or_else(|| {
+            ElfError::Modify(format!("No segment at index {index}; can't set flags"))
+        })?;
+        let old_flags = mem::replace(&mut segment.p_flags, p_flags);
+
+        if !force {
+            let segment = self.builder.segment_at(index).unwrap();
+            let writable_and_executable = self
+                .builder
+                .segment_sections(segment)
+                .into_iter()
+                .find(|section| {
+                    let flags = self.builder.section_load_flags(section);
+                    flags & elf::PF_W != 0 && flags & elf::PF_X != 0
+                })
+                .map(|section| section.name.to_string());
+            if let Some(name) = writable_and_executable {
+                self.builder.segment_at_mut(index).unwrap().p_flags = old_flags;
+                return Err(ElfError::Modify(format!(
+                    "Section {name} would be mapped writable and executable; force to allow it"
+                )));
+            }
+        }
+
+        self.modified = true;
+        Ok(())
+    }
+
     /// Remove from the DT_RUNPATH or DT_RPATH all directories that do not contain a library referenced by DT_NEEDED.
-    pub fn elf_shrink_rpath(&mut self, allowed_rpath_prefixes: Vec<String>) -> Result<()> {
-        let endian = self.builder.endian;
-        let e_machine = self.builder.header.e_machine;
+    ///
+    /// A library only counts if it has the same class, byte order and machine as this file.
+    /// `origin` is the directory that contains this file, used to expand `$ORIGIN`;
+    /// entries using `$ORIGIN` are kept unchecked without it.
+    pub fn elf_shrink_rpath(
+        &mut self,
+        allowed_rpath_prefixes: Vec<String>,
+        origin: Option<&Path>,
+    ) -> Result<ShrinkReport> {
+        let identity = ElfIdentity {
+            machine: self.builder.header.e_machine,
+            is_64: self.builder.is_64,
+            big_endian: self.builder.endian == Endianness::Big,
+        };
         let needed_libraries = self
             .elf_needed()
-            .map(|e| e.to_vec())
-            .collect::<HashSet<_>>();
+            .map(|e| String::from_utf8_lossy(e).into_owned())
+            .collect::<Vec<_>>();
         let dynamic = self.builder.dynamic_data_mut().ok_or_else(|| {
             ElfError::Modify("No dynamic section found; can't shrink rpath".to_string())
         })?;
         let mut found = false;
+        let mut report = ShrinkReport::default();
         for entry in dynamic.iter_mut() {
             let build::elf::Dynamic::String { tag, val } = entry else {
                 continue;
             };
-            if *tag != elf::DT_RPATH && *tag != elf::DT_RUNPATH {
-                continue;
-            }
+            let kind = match *tag {
+                elf::DT_RPATH => RunPathKind::Rpath,
+                elf::DT_RUNPATH => RunPathKind::Runpath,
+                _ => continue,
+            };
 
             found = true;
 
-            let rpath = val.clone();
-            let rpath_str = rpath.to_string();
-            let mut rpath_vec: Vec<&str> = rpath_str.split(":").collect();
-            // rpath_vec.retain(|&x| x != "");
-
-            // Check if each directory contains a library referenced by DT_NEEDED
-            rpath_vec.retain(|&dir| {
-                let dir_path = Path::new(dir);
-
-                // Keep non-absolute paths (e.g., "$ORIGIN")
-                if !dir.starts_with('/') {
-                    return true;
-                };
-
-                // Check allowed prefixes
-                if !allowed_rpath_prefixes.is_empty()
-                    && !allowed_rpath_prefixes
-                        .iter()
-                        .any(|prefix| dir.starts_with(prefix))
-                {
-                    eprintln!("Removing {dir} from RPATH due to non-allowed prefix");
-                    return false;
-                }
-
-                for lib_needed in needed_libraries.iter() {
-                    let Ok(possible_library) = String::from_utf8(lib_needed.to_vec()) else {
-                        continue;
-                    };
-
-                    let library_location = dir_path.join(possible_library);
-                    // read the elf file
-                    let file_content = fs::read(library_location);
-                    let Ok(file_content) = file_content else {
-                        continue;
-                    };
-                    let Ok(elf_file) =
-                        elf::FileHeader64::<object::Endianness>::parse(file_content.as_slice())
-                    else {
-                        continue;
-                    };
-                    let lib_e_machine = elf_file.e_machine(endian);
-
-                    if lib_e_machine == e_machine {
-                        return true;
-                    }
+            let rpath = val.to_string();
+            let mut kept_paths = Vec::new();
+            for dir in rpath.split(':') {
+                let (kept, reason) = check_search_path(
+                    dir,
+                    origin,
+                    &allowed_rpath_prefixes,
+                    &needed_libraries,
+                    identity,
+                );
+                if kept {
+                    kept_paths.push(dir);
                 }
-                eprintln!("Removing directory {dir}");
-                false
-            });
-
-            eprintln!("New rpath: {rpath_vec:?}");
+                report.entries.push(ShrinkEntry {
+                    kind,
+                    path: dir.to_string(),
+                    kept,
+                    reason,
+                });
+            }
 
-            let new_rpath = rpath_vec.join(":");
+            let new_rpath = kept_paths.join(":");
             *val.to_mut() = ByteString::from(new_rpath.as_bytes()).to_vec();
         }
         if !found {
             return Err(ElfError::Modify("No DT_RPATH entry found".to_string()));
         }
         self.modified = true;
-        Ok(())
+        Ok(report)
     }
 
     /// Disable the default library search paths
     pub fn elf_no_default_lib(&mut self) -> Result<()> {
-        let dynamic = self.builder.dynamic_data_mut().ok_or_else(|| {
-            ElfError::Modify("No dynamic section found; can't set soname".to_string())
-        })?;
-        let mut found = false;
-        for entry in dynamic.iter_mut() {
+        self.elf_set_dynamic_flags(DynamicFlags {
+            flags: 0,
+            flags_1: elf::DF_1_NODEFLIB as u64,
+        })
+    }
+
+    /// Return the bits of the `DT_FLAGS` and `DT_FLAGS_1` entries in the dynamic section.
+    ///
+    /// Missing entries are reported as having no bits set.
+    pub fn elf_dynamic_flags(&self) -> DynamicFlags {
+        let mut flags = DynamicFlags::default();
+        let dynamic = self.builder.dynamic_data().unwrap_or(&[]);
+        for entry in dynamic.iter() {
             let build::elf::Dynamic::Integer { tag, val } = entry else {
                 continue;
             };
-            if *tag != elf::DT_FLAGS_1 {
+            match *tag {
+                elf::DT_FLAGS => flags.flags |= *val,
+                elf::DT_FLAGS_1 => flags.flags_1 |= *val,
+                _ => {}
+            }
+        }
+        flags
+    }
+
+    /// Set bits in the `DT_FLAGS` and `DT_FLAGS_1` entries of the dynamic section.
+    ///
+    /// The entries are created if they are missing and any of their bits are requested.
+    pub fn elf_set_dynamic_flags(&mut self, flags: DynamicFlags) -> Result<()> {
+        let dynamic = self.builder.dynamic_data_mut().ok_or_else(|| {
+            ElfError::Modify("No dynamic section found; can't set flags".to_string())
+        })?;
+        for (flag_tag, bits) in [
+            (elf::DT_FLAGS, flags.flags),
+            (elf::DT_FLAGS_1, flags.flags_1),
+        ] {
+            if bits == 0 {
                 continue;
             }
+            let mut found = false;
+            for entry in dynamic.iter_mut() {
+                let build::elf::Dynamic::Integer { tag, val } = entry else {
+                    continue;
+                };
+                if *tag != flag_tag {
+                    continue;
+                }
 
-            *val |= elf::DF_1_NODEFLIB as u64;
-            found = true;
+                if *val & bits != bits {
+                    *val |= bits;
+                    self.modified = true;
+                }
+                found = true;
+            }
+            if !found {
+                dynamic.push(build::elf::Dynamic::Integer {
+                    tag: flag_tag,
+                    val: bits,
+                });
+                self.modified = true;
+            }
         }
-        if !found {
-            dynamic.push(build::elf::Dynamic::Integer {
-                tag: elf::DT_FLAGS_1,
-                val: elf::DF_1_NODEFLIB as u64,
-            });
+        Ok(())
+    }
+
+    /// Clear bits in the `DT_FLAGS` and `DT_FLAGS_1` entries of the dynamic section.
+    ///
+    /// Entries are kept even if no bits remain set.
+    pub fn elf_clear_dynamic_flags(&mut self, flags: DynamicFlags) -> Result<()> {
+        let dynamic = self.builder.dynamic_data_mut().ok_or_else(|| {
+            ElfError::Modify("No dynamic section found; can't clear flags".to_string())
+        })?;
+        for entry in dynamic.iter_mut() {
+            let build::elf::Dynamic::Integer { tag, val } = entry else {
+                continue;
+            };
+            let bits = match *tag {
+                elf::DT_FLAGS => flags.flags,
+                elf::DT_FLAGS_1 => flags.flags_1,
+                _ => continue,
+            };
+            if *val & bits != 0 {
+                *val &= !bits;
+                self.modified = true;
+            }
         }
-        self.modified = true;
         Ok(())
     }
 
@@ -590,6 +1408,185 @@ impl<'data> Writer<'data> {
         Ok(())
     }
 
+    /// List the symbol versions required from each library, in file order.
+    pub fn elf_version_requirements(&self) -> Vec<VersionRequirement> {
+        self.builder
+            .version_files
+            .iter()
+            .map(|file| {
+                let versions = self
+                    .builder
+                    .versions
+                    .iter()
+                    .filter_map(|version| match &version.data {
+                        VersionData::Need(need) if need.file == file.id() => {
+                            Some(need.name.to_string())
+                        }
+                        _ => None,
+                    })
+                    .collect();
+                VersionRequirement {
+                    library: file.name.to_string(),
+                    versions,
+                }
+            })
+            .collect()
+    }
+
+    /// Remove a symbol version requirement.
+    ///
+    /// Symbols that were bound to the version become unversioned.
+    /// If `library` is given, only the requirement on that library is removed.
+    pub fn elf_remove_version_requirement(
+        &mut self,
+        library: Option<&[u8]>,
+        version: &[u8],
+    ) -> Result<()> {
+        let ids = self.find_version_requirements(library, version);
+        if ids.is_empty() {
+            return Err(ElfError::Modify(format!(
+                "Version requirement {} not found",
+                String::from_utf8_lossy(version)
+            )));
+        }
+        for symbol in &mut self.builder.dynamic_symbols {
+            if ids.contains(&symbol.version) {
+                symbol.version = VersionId::global();
+                symbol.version_hidden = false;
+            }
+        }
+        for id in ids {
+            self.builder.versions.get_mut(id).delete = true;
+        }
+        self.modified = true;
+        Ok(())
+    }
+
+    /// Replace a symbol version requirement with another version from the same library.
+    ///
+    /// If the library already has a requirement for the new version, the symbols bound to
+    /// the old version are moved to it. Otherwise the old requirement is renamed.
+    /// If `library` is given, only the requirement on that library is replaced.
+    pub fn elf_replace_version_requirement(
+        &mut self,
+        library: Option<&[u8]>,
+        old_version: &[u8],
+        new_version: &[u8],
+    ) -> Result<()> {
+        let ids = self.find_version_requirements(library, old_version);
+        if ids.is_empty() {
+            return Err(ElfError::Modify(format!(
+                "Version requirement {} not found",
+                String::from_utf8_lossy(old_version)
+            )));
+        }
+        for id in ids {
+            let VersionData::Need(need) = &self.builder.versions.get(id).data else {
+                continue;
+            };
+            let file = need.file;
+            let existing = self.find_version_requirements_in(file, new_version);
+            if let Some(&new_id) = existing.first() {
+                for symbol in &mut self.builder.dynamic_symbols {
+                    if symbol.version == id {
+                        symbol.version = new_id;
+                    }
+                }
+                self.builder.versions.get_mut(id).delete = true;
+            } else if let VersionData::Need(need) = &mut self.builder.versions.get_mut(id).data {
+                need.name = new_version.to_vec().into();
+            }
+        }
+        self.modified = true;
+        Ok(())
+    }
+
+    /// Rename the library that symbol versions are required from.
+    ///
+    /// Returns whether the file requires versions from `old`.
+    pub fn elf_rename_version_file(&mut self, old: &[u8], new: &[u8]) -> bool {
+        let mut found = false;
+        for file in &mut self.builder.version_files {
+            if file.name.as_slice() == old {
+                file.name = new.to_vec().into();
+                found = true;
+            }
+        }
+        if found {
+            self.modified = true;
+        }
+        found
+    }
+
+    /// Rename the base version definition, which names the file for the version
+    /// requirements of its consumers.
+    ///
+    /// Files without version definitions are left unchanged.
+    pub fn elf_set_version_base(&mut self, name: Vec<u8>) {
+        if let Some(base) = &mut self.builder.version_base {
+            *base = name.into();
+            self.modified = true;
+        }
+    }
+
+    /// Remove all symbol versioning information.
+    ///
+    /// This deletes the `.gnu.version`, `.gnu.version_d` and `.gnu.version_r` sections
+    /// and makes all dynamic symbols unversioned.
+    pub fn elf_strip_symbol_versions(&mut self) -> Result<()> {
+        let mut found = false;
+        for section in &mut self.builder.sections {
+            if matches!(
+                section.data,
+                SectionData::GnuVersym | SectionData::GnuVerdef | SectionData::GnuVerneed
+            ) {
+                section.delete = true;
+                found = true;
+            }
+        }
+        if !found {
+            return Ok(());
+        }
+        for symbol in &mut self.builder.dynamic_symbols {
+            symbol.version = if symbol.st_bind() == elf::STB_LOCAL {
+                VersionId::local()
+            } else {
+                VersionId::global()
+            };
+            symbol.version_hidden = false;
+        }
+        for version in &mut self.builder.versions {
+            version.delete = true;
+        }
+        for file in &mut self.builder.version_files {
+            file.delete = true;
+        }
+        self.builder.version_base = None;
+        self.modified = true;
+        Ok(())
+    }
+
+    fn find_version_requirements(&self, library: Option<&[u8]>, version: &[u8]) -> Vec<VersionId> {
+        self.builder
+            .version_files
+            .iter()
+            .filter(|file| library.is_none_or(|library| file.name.as_slice() == library))
+            .flat_map(|file| self.find_version_requirements_in(file.id(), version))
+            .collect()
+    }
+
+    fn find_version_requirements_in(&self, file: VersionFileId, version: &[u8]) -> Vec<VersionId> {
+        self.builder
+            .versions
+            .iter()
+            .filter(|item| match &item.data {
+                VersionData::Need(need) => need.file == file && need.name.as_slice() == version,
+                VersionData::Def(_) => false,
+            })
+            .map(|item| item.id())
+            .collect()
+    }
+
     /// Clear the symbol version information for a symbol
     pub fn elf_clear_exec_stack(&mut self) -> Result<()> {
         let gnu_stack = self.builder.gnu_stack_mut();
@@ -642,40 +1639,110 @@ impl<'data> Writer<'data> {
 
     pub(crate) fn elf_finalize(&mut self) -> Result<()> {
         if self.modified {
+            if let Some(page_size) = self.page_size {
+                release_shared_pages(&mut self.builder, u64::from(page_size));
+            }
             move_sections(&mut self.builder)?;
+            if let Some(page_size) = self.page_size {
+                realign_segments(&mut self.builder, u64::from(page_size))?;
+            }
+            sync_gnu_property_segment(&mut self.builder);
         }
         Ok(())
     }
 
     /// Write the file to an output stream.
-    pub fn write<W: std::io::Write>(mut self, w: W) -> Result<()> {
+    ///
+    /// When all the edits were patched in place, the file is written without being
+    /// rebuilt, so that the bytes outside the patched strings stay the same.
+    pub fn write<W: std::io::Write>(mut self, mut w: W) -> Result<()> {
+        if let Some(data) = self.take_patched()? {
+            return w.write_all(&data).map_err(ElfError::Io);
+        }
         self.elf_finalize()?;
+        if self.check {
+            let data = Self::write_checked_data(self.builder, self.page_size)?;
+            return w.write_all(&data).map_err(ElfError::Io);
+        }
         let mut buffer = object::write::StreamingBuffer::new(w);
         self.builder.write(&mut buffer).map_err(ElfError::Write)?;
         buffer.result().map_err(ElfError::Io)
     }
 
     /// Write the builded/patched ELF file to a path.
+    ///
+    /// The file is written to a temporary file that replaces `path` once it is
+    /// complete, so `path` is left untouched if writing fails, and can be the file
+    /// that was read.
     pub fn write_to_path(&mut self, path: &Path) -> Result<()> {
-        let file_writer = fs::File::create(path).map_err(ElfError::Io)?;
-
+        if let Some(data) = self.take_patched()? {
+            return arwen_core::write_atomically(path, |w| {
+                w.write_all(&data).map_err(ElfError::Io)
+            });
+        }
         self.elf_finalize()?;
-        let mut buffer = object::write::StreamingBuffer::new(file_writer);
         let new_builder = build::elf::Builder::new(self.builder.endian, self.builder.is_64);
-
         let builder = mem::replace(&mut self.builder, new_builder);
 
-        builder.write(&mut buffer).map_err(ElfError::Write)?;
-        buffer.result().map_err(ElfError::Io)
+        if self.check {
+            let data = Self::write_checked_data(builder, self.page_size)?;
+            return arwen_core::write_atomically(path, |w| {
+                w.write_all(&data).map_err(ElfError::Io)
+            });
+        }
+        arwen_core::write_atomically(path, |w| {
+            let mut buffer = object::write::StreamingBuffer::new(w);
+            builder.write(&mut buffer).map_err(ElfError::Write)?;
+            buffer.result().map_err(ElfError::Io)
+        })
+    }
+
+    /// Return the file with the edits patched in place, if they were all patched in
+    /// place.
+    fn take_patched(&mut self) -> Result<Option<Vec<u8>>> {
+        if self.modified {
+            return Ok(None);
+        }
+        let Some(data) = self.patched.take() else {
+            return Ok(None);
+        };
+        if self.check {
+            check_written(&data, self.page_size)?;
+        }
+        Ok(Some(data))
+    }
+
+    /// Check the written file before [`write`](Self::write) or
+    /// [`write_to_path`](Self::write_to_path) output it.
+    ///
+    /// When the check finds problems, the write fails with [`ElfError::Check`] and
+    /// nothing is written.
+    pub fn set_check_on_write(&mut self, check: bool) {
+        self.check = check;
+    }
+
+    /// Write a builder to memory and check the result.
+    fn write_checked_data(
+        builder: build::elf::Builder<'_>,
+        page_size: Option<u32>,
+    ) -> Result<Vec<u8>> {
+        let mut data = Vec::new();
+        builder.write(&mut data).map_err(ElfError::Write)?;
+        check_written(&data, page_size)?;
+        Ok(data)
     }
 
     /// Delete symbols from the symbol table.
     ///
     /// For ELF files, this deletes symbols from both the symbol table and the
     /// dynamic symbol table.
-    pub fn delete_symbols(&mut self, names: &HashSet<Vec<u8>>) {
+    ///
+    /// Nothing is deleted if dynamic relocations still refer to one of the dynamic
+    /// symbols.
+    pub fn delete_symbols(&mut self, names: &HashSet<Vec<u8>>) -> Result<()> {
+        self.elf_delete_dynamic_symbols(names)?;
         self.elf_delete_symbols(names);
-        self.elf_delete_dynamic_symbols(names);
+        Ok(())
     }
 
     /// Rename symbols in the symbol table.
@@ -690,8 +1757,8 @@ impl<'data> Writer<'data> {
     }
 
     /// Delete sections from the file.
-    pub fn delete_sections(&mut self, names: &HashSet<Vec<u8>>) {
-        self.elf_delete_sections(names);
+    pub fn delete_sections(&mut self, names: &HashSet<Vec<u8>>) -> Result<()> {
+        self.elf_delete_sections(names)
     }
 
     /// Rename sections in the file.
@@ -702,6 +1769,11 @@ impl<'data> Writer<'data> {
     }
 
     /// Set the page size for ELF file segment alignment.
+    ///
+    /// When the file is written, the file offsets of the `PT_LOAD` segments are
+    /// shifted to be congruent with their addresses modulo the page size, and
+    /// segments that only hold movable sections are moved off pages shared with
+    /// other segments. Writing fails if segments that can't move share a page.
     pub fn elf_set_page_size(&mut self, page_size: u32) -> Result<()> {
         // Validate page size (must be power of 2 and >= 1024)
         if !page_size.is_power_of_two() || page_size < 1024 {
@@ -712,6 +1784,8 @@ impl<'data> Writer<'data> {
 
         self.page_size = Some(page_size);
         self.modified = true;
+        // Segments added by later edits start on a page of their own.
+        self.builder.load_align = u64::from(page_size);
 
         // Update program header alignments
         for segment in &mut self.builder.segments {
@@ -748,3 +1822,61 @@ impl<'data> Writer<'data> {
         }
     }
 }
+
+/// Decide whether shrinking keeps a search path entry.
+fn check_search_path(
+    dir: &str,
+    origin: Option<&Path>,
+    allowed_rpath_prefixes: &[String],
+    needed_libraries: &[String],
+    identity: ElfIdentity,
+) -> (bool, ShrinkReason) {
+    let uses_origin = find_token(dir, "ORIGIN").is_some();
+    let origin = match origin {
+        Some(origin) => origin.to_string_lossy(),
+        None if uses_origin => return (true, ShrinkReason::UnknownOrigin),
+        None => Default::default(),
+    };
+    if !uses_origin && !dir.starts_with('/') {
+        return (true, ShrinkReason::Unresolved);
+    }
+    // $LIB and $PLATFORM depend on the loader, so those entries can't be checked.
+    let Some(expanded) = expand_tokens(
+        dir,
+        &[("ORIGIN", Some(&origin)), ("LIB", None), ("PLATFORM", None)],
+    ) else {
+        return (true, ShrinkReason::Unresolved);
+    };
+
+    // $ORIGIN entries move with the file, so the prefixes don't apply to them.
+    if !uses_origin
+        && !allowed_rpath_prefixes.is_empty()
+        && !allowed_rpath_prefixes
+            .iter()
+            .any(|prefix| dir.starts_with(prefix))
+    {
+        return (false, ShrinkReason::PrefixNotAllowed);
+    }
+
+    for library in needed_libraries {
+        let Ok(file_content) = fs::read(Path::new(&expanded).join(library)) else {
+            continue;
+        };
+        if ElfIdentity::read(&file_content) == Some(identity) {
+            return (true, ShrinkReason::ProvidesLibrary(library.clone()));
+        }
+    }
+    (false, ShrinkReason::NoNeededLibrary)
+}
+
+/// Fail with [`ElfError::Check`] if the written file has problems.
+fn check_written(data: &[u8], page_size: Option<u32>) -> Result<()> {
+    let problems = match page_size {
+        Some(page_size) => crate::check::check_page_size(data, u64::from(page_size)),
+        None => crate::check::check(data),
+    };
+    if !problems.is_empty() {
+        return Err(ElfError::Check(problems));
+    }
+    Ok(())
+}
@@ -0,0 +1,123 @@
+//! Helpers shared by the rewriter and the dependency resolver.
+
+use object::{elf, read::elf::FileHeader, Endianness, FileKind};
+
+/// The fields that decide whether two ELF files can be loaded into the same process.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct ElfIdentity {
+    pub(crate) machine: u16,
+    pub(crate) is_64: bool,
+    pub(crate) big_endian: bool,
+}
+
+impl ElfIdentity {
+    /// Read the identity of an ELF file, or `None` if `data` isn't one.
+    pub(crate) fn read(data: &[u8]) -> Option<Self> {
+        match FileKind::parse(data).ok()? {
+            FileKind::Elf32 => Self::read_elf::<elf::FileHeader32<Endianness>>(data),
+            FileKind::Elf64 => Self::read_elf::<elf::FileHeader64<Endianness>>(data),
+            _ => None,
+        }
+    }
+
+    fn read_elf<Elf: FileHeader<Endian = Endianness>>(data: &[u8]) -> Option<Self> {
+        let header = Elf::parse(data).ok()?;
+        Some(Self::from_header(header, header.endian().ok()?))
+    }
+
+    pub(crate) fn from_header<Elf: FileHeader<Endian = Endianness>>(
+        header: &Elf,
+        endian: Endianness,
+    ) -> Self {
+        Self {
+            machine: header.e_machine(endian),
+            is_64: header.is_type_64(),
+            big_endian: header.is_big_endian(),
+        }
+    }
+}
+
+/// Find a `$NAME` or `${NAME}` token, returning its byte range.
+pub(crate) fn find_token(entry: &str, name: &str) -> Option<(usize, usize)> {
+    let mut offset = 0;
+    while let Some(position) = entry[offset..].find('$') {
+        let start = offset + position;
+        let rest = &entry[start + 1..];
+        if let Some(rest) = rest.strip_prefix('{') {
+            if rest
+                .strip_prefix(name)
+                .is_some_and(|rest| rest.starts_with('}'))
+            {
+                return Some((start, start + name.len() + 3));
+            }
+        } else if let Some(after) = rest.strip_prefix(name) {
+            if !after.starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_') {
+                return Some((start, start + name.len() + 1));
+            }
+        }
+        offset = start + 1;
+    }
+    None
+}
+
+/// Replace the `$NAME` and `${NAME}` tokens in `entry` with their values.
+///
+/// The entry is expanded in a single pass, so tokens that appear inside the
+/// values are kept as they are. Returns `None` if a token in `entry` has no value.
+pub(crate) fn expand_tokens(entry: &str, values: &[(&str, Option<&str>)]) -> Option<String> {
+    let mut expanded = String::with_capacity(entry.len());
+    let mut rest = entry;
+    while let Some(position) = rest.find('$') {
+        expanded.push_str(&rest[..position]);
+        let token = &rest[position..];
+        let found = values.iter().find_map(|(name, value)| {
+            find_token(token, name)
+                .filter(|(start, _)| *start == 0)
+                .map(|(_, end)| (end, *value))
+        });
+        match found {
+            Some((end, value)) => {
+                expanded.push_str(value?);
+                rest = &token[end..];
+            }
+            None => {
+                expanded.push('$');
+                rest = &token[1..];
+            }
+        }
+    }
+    expanded.push_str(rest);
+    Some(expanded)
+}
+
+/// Match a name against a pattern where `*` matches any run of characters and `?`
+/// matches a single character.
+pub(crate) fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // The position of the last `*` in the pattern, and of the name when it was reached.
+    let mut star = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                // Let the last `*` match one more character.
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
@@ -1,7 +1,17 @@
-use arwen_elf::ElfContainer;
-use goblin::elf::Elf;
+use arwen_elf::{
+    decode_e_flags, DynamicFlags, DynamicValue, ElfContainer, ElfError, ExportChange,
+    NeededPosition, Resolver, RunPath, RunPathKind, SearchSource, ShrinkReason, VersionRequirement,
+};
+use goblin::elf::{
+    dynamic::{DT_RELA, DT_RELASZ},
+    sym::{STB_GLOBAL, STB_LOCAL, STB_WEAK, STV_DEFAULT, STV_HIDDEN},
+    Elf,
+};
 use rstest::rstest;
-use std::path::PathBuf;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
 /// This test checks if the rpath of a Elf file can be set.
 #[rstest]
@@ -111,3 +121,1637 @@ fn test_set_page_size(#[files("../../tests/data/elf/x64/exec/*")] bin_path: Path
     insta::assert_debug_snapshot!(changed_elf.program_headers);
     insta::assert_snapshot!(changed_elf_data.len());
 }
+
+/// This test verifies that setting a larger page size shifts the segments in the file
+/// until they load with that page size, without moving any section in memory.
+#[rstest]
+fn test_set_page_size_realign(#[files("../../tests/data/elf/x64/exec/*")] bin_path: PathBuf) {
+    use arwen_elf::Problem;
+    use goblin::elf::section_header::{SHF_ALLOC, SHT_NOBITS, SHT_PROGBITS};
+
+    let data_bytes = std::fs::read(&bin_path).unwrap();
+    assert!(arwen_elf::check_page_size(&data_bytes, 0x4000)
+        .iter()
+        .any(|problem| matches!(problem, Problem::MisalignedForPageSize { .. })));
+
+    let mut elf_container = ElfContainer::parse(&data_bytes).unwrap();
+    elf_container.set_check_on_write(true);
+    elf_container.set_page_size(0x4000).unwrap();
+    let mut changed_elf_data = Vec::new();
+    elf_container.write(&mut changed_elf_data).unwrap();
+
+    assert_eq!(arwen_elf::check_page_size(&changed_elf_data, 0x4000), []);
+
+    let elf = Elf::parse(&data_bytes).unwrap();
+    let changed_elf = Elf::parse(&changed_elf_data).unwrap();
+    for section in &elf.section_headers {
+        if section.sh_flags & u64::from(SHF_ALLOC) == 0 || section.sh_type == SHT_NOBITS {
+            continue;
+        }
+        let name = elf.shdr_strtab.get_at(section.sh_name).unwrap();
+        let changed_section = changed_elf
+            .section_headers
+            .iter()
+            .find(|changed| changed_elf.shdr_strtab.get_at(changed.sh_name) == Some(name))
+            .unwrap();
+        assert_eq!(changed_section.sh_addr, section.sh_addr, "{name}");
+        // Tables such as the hash tables are generated again by the rewriter.
+        if section.sh_type != SHT_PROGBITS {
+            continue;
+        }
+        let range = |section: &goblin::elf::SectionHeader| {
+            section.sh_offset as usize..(section.sh_offset + section.sh_size) as usize
+        };
+        assert_eq!(
+            changed_elf_data[range(changed_section)],
+            data_bytes[range(section)],
+            "{name}"
+        );
+    }
+    assert_eq!(changed_elf.interpreter, elf.interpreter);
+    assert_eq!(changed_elf.runpaths, elf.runpaths);
+
+    // The segment holding the file header starts on a 64 KiB page that the code
+    // also uses, which only linking again can fix.
+    let mut elf_container = ElfContainer::parse(&data_bytes).unwrap();
+    elf_container.set_page_size(0x10000).unwrap();
+    let error = elf_container.write(&mut Vec::new()).unwrap_err();
+    assert!(matches!(error, ElfError::Modify(_)));
+}
+
+/// This test verifies that segments sharing a page are reported for larger page
+/// sizes, and that a library whose code shares pages with other segments is not
+/// realigned.
+#[rstest]
+fn test_page_size_shared_pages(
+    #[files("../../tests/data/elf/x64/libs/libversioned.so.1")] bin_path: PathBuf,
+) {
+    use arwen_elf::Problem;
+
+    let data_bytes = std::fs::read(&bin_path).unwrap();
+    assert_eq!(arwen_elf::check_page_size(&data_bytes, 0x1000), []);
+
+    let problems = arwen_elf::check_page_size(&data_bytes, 0x4000);
+    assert!(problems.contains(&Problem::SharedPage {
+        first: 0,
+        second: 1,
+        page_size: 0x4000,
+    }));
+    assert!(problems.contains(&Problem::AlignmentBelowPageSize {
+        index: 0,
+        p_align: 0x1000,
+        page_size: 0x4000,
+    }));
+
+    let mut elf_container = ElfContainer::parse(&data_bytes).unwrap();
+    elf_container.set_page_size(0x4000).unwrap();
+    let error = elf_container.write(&mut Vec::new()).unwrap_err();
+    assert!(matches!(&error, ElfError::Modify(message) if message.contains("max-page-size=16384")));
+}
+
+/// This test verifies that the dynamic section can be dumped as typed entries.
+#[rstest]
+fn test_dynamic_entries(#[files("../../tests/data/elf/x64/exec/*")] bin_path: PathBuf) {
+    let data_bytes = std::fs::read(&bin_path).unwrap();
+
+    let elf_container = ElfContainer::parse(&data_bytes).unwrap();
+
+    let entries = elf_container.dynamic_entries();
+
+    let needed: Vec<_> = entries
+        .iter()
+        .filter(|entry| entry.name == "DT_NEEDED")
+        .map(|entry| entry.value.clone())
+        .collect();
+    assert_eq!(
+        needed,
+        vec![
+            DynamicValue::String("libncurses.so.5".to_string()),
+            DynamicValue::String("libdl.so.2".to_string()),
+            DynamicValue::String("libc.so.6".to_string()),
+        ]
+    );
+
+    insta::assert_debug_snapshot!(entries);
+}
+
+/// This test verifies that DT_RPATH and DT_RUNPATH entries are reported with their kind.
+#[rstest]
+fn test_runpath_kind(#[files("../../tests/data/elf/x64/exec/*")] bin_path: PathBuf) {
+    let data_bytes = std::fs::read(&bin_path).unwrap();
+
+    let mut elf_container = ElfContainer::parse(&data_bytes).unwrap();
+
+    assert_eq!(
+        elf_container.runpaths(),
+        vec![RunPath {
+            kind: RunPathKind::Runpath,
+            entries: vec!["path-graf".to_string()],
+        }]
+    );
+
+    elf_container.force_rpath().unwrap();
+
+    let mut changed_elf_data = Vec::new();
+    elf_container.write(&mut changed_elf_data).unwrap();
+
+    let changed_elf = ElfContainer::parse(&changed_elf_data).unwrap();
+
+    let runpaths = changed_elf.runpaths();
+    assert_eq!(runpaths.len(), 1);
+    assert_eq!(runpaths[0].kind, RunPathKind::Rpath);
+    assert_eq!(changed_elf.get_rpath(), vec!["path-graf".to_string()]);
+}
+
+/// This test verifies that DT_FLAGS and DT_FLAGS_1 entries are created, set and cleared.
+#[rstest]
+fn test_dynamic_flags(#[files("../../tests/data/elf/x64/exec/*")] bin_path: PathBuf) {
+    let data_bytes = std::fs::read(&bin_path).unwrap();
+
+    let mut elf_container = ElfContainer::parse(&data_bytes).unwrap();
+
+    assert_eq!(elf_container.dynamic_flags(), DynamicFlags::default());
+
+    elf_container
+        .set_dynamic_flags(&["BIND_NOW", "now", "NODELETE", "DF_1_ORIGIN"])
+        .unwrap();
+    elf_container.clear_dynamic_flags(&["NODELETE"]).unwrap();
+
+    assert!(elf_container.set_dynamic_flags(&["NOT_A_FLAG"]).is_err());
+
+    let mut changed_elf_data = Vec::new();
+    elf_container.write(&mut changed_elf_data).unwrap();
+
+    let changed_elf = ElfContainer::parse(&changed_elf_data).unwrap();
+    let flags = changed_elf.dynamic_flags();
+
+    assert_eq!(flags.flags_names(), vec!["BIND_NOW"]);
+    assert_eq!(flags.flags_1_names(), vec!["NOW", "ORIGIN"]);
+}
+
+/// This test verifies that symbol version requirements can be listed, remapped and removed.
+#[rstest]
+fn test_version_requirements(#[files("../../tests/data/elf/x64/exec/*")] bin_path: PathBuf) {
+    let data_bytes = std::fs::read(&bin_path).unwrap();
+
+    let mut elf_container = ElfContainer::parse(&data_bytes).unwrap();
+
+    insta::assert_debug_snapshot!(elf_container.version_requirements());
+
+    elf_container
+        .replace_version_requirement("GLIBC_2.4", "GLIBC_2.3", Some("libc.so.6"))
+        .unwrap();
+    elf_container
+        .remove_version_requirement("GLIBC_2.3.4", None)
+        .unwrap();
+    assert!(elf_container
+        .remove_version_requirement("GLIBC_2.99", None)
+        .is_err());
+
+    let mut changed_elf_data = Vec::new();
+    elf_container.write(&mut changed_elf_data).unwrap();
+
+    let changed_elf = ElfContainer::parse(&changed_elf_data).unwrap();
+
+    assert_eq!(
+        changed_elf.version_requirements(),
+        vec![
+            VersionRequirement {
+                library: "libdl.so.2".to_string(),
+                versions: vec!["GLIBC_2.2.5".to_string()],
+            },
+            VersionRequirement {
+                library: "libc.so.6".to_string(),
+                versions: vec!["GLIBC_2.3".to_string(), "GLIBC_2.2.5".to_string()],
+            },
+        ]
+    );
+}
+
+/// This test verifies that symbol versioning can be stripped entirely.
+#[rstest]
+fn test_strip_symbol_versions(#[files("../../tests/data/elf/x64/exec/*")] bin_path: PathBuf) {
+    let data_bytes = std::fs::read(&bin_path).unwrap();
+
+    let mut elf_container = ElfContainer::parse(&data_bytes).unwrap();
+
+    elf_container.strip_symbol_versions().unwrap();
+
+    let mut changed_elf_data = Vec::new();
+    elf_container.write(&mut changed_elf_data).unwrap();
+
+    let changed_elf = Elf::parse(&changed_elf_data).unwrap();
+
+    assert!(changed_elf.versym.is_none());
+    assert!(changed_elf.verneed.is_none());
+    assert!(changed_elf.dynamic.unwrap().dyns.iter().all(|entry| {
+        entry.d_tag != goblin::elf::dynamic::DT_VERSYM
+            && entry.d_tag != goblin::elf::dynamic::DT_VERNEED
+    }));
+}
+
+/// This test verifies that unreferenced sections can be deleted and renamed.
+#[rstest]
+fn test_delete_and_rename_sections(
+    #[files("../../tests/data/elf/x64/libs/libversioned.so.1")] bin_path: PathBuf,
+) {
+    let data_bytes = std::fs::read(&bin_path).unwrap();
+
+    let mut elf_container = ElfContainer::parse(&data_bytes).unwrap();
+
+    // Referenced by the dynamic section.
+    assert!(elf_container.delete_sections(vec![".dynsym"]).is_err());
+    // Referenced by the DT_INIT_ARRAY entry and a PT_LOAD program header.
+    assert!(elf_container.delete_sections(vec![".init_array"]).is_err());
+    // Still used by the symbol table.
+    assert!(elf_container.delete_sections(vec![".strtab"]).is_err());
+    assert!(elf_container.delete_sections(vec![".missing"]).is_err());
+
+    elf_container
+        .delete_sections(vec![".comment", ".symtab"])
+        .unwrap();
+    elf_container
+        .rename_sections(&HashMap::from([(".strtab", ".names")]))
+        .unwrap();
+
+    let mut changed_elf_data = Vec::new();
+    elf_container.write(&mut changed_elf_data).unwrap();
+
+    let changed_elf = Elf::parse(&changed_elf_data).unwrap();
+    let section_names: Vec<_> = changed_elf
+        .section_headers
+        .iter()
+        .filter_map(|header| changed_elf.shdr_strtab.get_at(header.sh_name))
+        .collect();
+
+    assert!(!section_names.contains(&".comment"));
+    assert!(!section_names.contains(&".symtab"));
+    assert!(section_names.contains(&".names"));
+    assert!(changed_elf.syms.is_empty());
+}
+
+/// This test verifies that every section with a name is deleted when several sections
+/// share it, and that nothing is deleted if one of them is referenced.
+#[rstest]
+fn test_delete_duplicate_sections(
+    #[files("../../tests/data/elf/x64/libs/libversioned.so.1")] bin_path: PathBuf,
+) {
+    let data_bytes = std::fs::read(&bin_path).unwrap();
+
+    // The note section is referenced by the PT_NOTE program header.
+    let mut elf_container = ElfContainer::parse(&data_bytes).unwrap();
+    elf_container
+        .rename_sections(&HashMap::from([(".comment", ".note.gnu.build-id")]))
+        .unwrap();
+    assert!(elf_container
+        .delete_sections(vec![".note.gnu.build-id"])
+        .is_err());
+
+    let mut elf_container = ElfContainer::parse(&data_bytes).unwrap();
+    elf_container
+        .rename_sections(&HashMap::from([(".comment", ".symtab")]))
+        .unwrap();
+    elf_container.delete_sections(vec![".symtab"]).unwrap();
+
+    let mut changed_elf_data = Vec::new();
+    elf_container.write(&mut changed_elf_data).unwrap();
+
+    let changed_elf = Elf::parse(&changed_elf_data).unwrap();
+    let section_names: Vec<_> = changed_elf
+        .section_headers
+        .iter()
+        .filter_map(|header| changed_elf.shdr_strtab.get_at(header.sh_name))
+        .collect();
+
+    assert!(!section_names.contains(&".symtab"));
+    assert!(!section_names.contains(&".comment"));
+    assert!(changed_elf.syms.is_empty());
+    assert_eq!(
+        section_names.len() + 2,
+        Elf::parse(&data_bytes).unwrap().section_headers.len()
+    );
+}
+
+/// This test verifies that dynamic symbols are deleted unless dynamic relocations still
+/// refer to them, and that a refused deletion changes nothing.
+#[rstest]
+fn test_delete_dynamic_symbols(
+    #[files("../../tests/data/elf/x64/libs/libversioned.so.1")] bin_path: PathBuf,
+) {
+    let data_bytes = std::fs::read(&bin_path).unwrap();
+    let elf = Elf::parse(&data_bytes).unwrap();
+    let relocation_count = elf.dynrelas.len() + elf.pltrelocs.len();
+
+    let mut elf_container = ElfContainer::parse(&data_bytes).unwrap();
+
+    // `puts` is imported through a `.rela.plt` entry.
+    let error = elf_container
+        .delete_dynamic_symbols(vec!["versioned_answer", "puts"])
+        .unwrap_err();
+    assert!(matches!(&error, ElfError::Modify(message) if message.contains("puts")));
+    let error = elf_container.delete_symbols(vec!["puts"]).unwrap_err();
+    assert!(matches!(&error, ElfError::Modify(message) if message.contains("puts")));
+
+    elf_container
+        .delete_dynamic_symbols(vec!["versioned_answer"])
+        .unwrap();
+
+    let mut changed_elf_data = Vec::new();
+    elf_container.write(&mut changed_elf_data).unwrap();
+
+    let changed_elf = Elf::parse(&changed_elf_data).unwrap();
+    let dynamic_symbols: Vec<_> = changed_elf
+        .dynsyms
+        .iter()
+        .filter_map(|sym| changed_elf.dynstrtab.get_at(sym.st_name))
+        .collect();
+
+    assert!(!dynamic_symbols.contains(&"versioned_answer"));
+    assert!(dynamic_symbols.contains(&"versioned_hello"));
+    assert!(dynamic_symbols.contains(&"puts"));
+    assert_eq!(
+        changed_elf.dynrelas.len() + changed_elf.pltrelocs.len(),
+        relocation_count
+    );
+}
+
+/// This test verifies that symbols are deleted and renamed in the right symbol tables.
+#[rstest]
+fn test_delete_and_rename_symbols(
+    #[files("../../tests/data/elf/x64/libs/libversioned.so.1")] bin_path: PathBuf,
+) {
+    let data_bytes = std::fs::read(&bin_path).unwrap();
+
+    let mut elf_container = ElfContainer::parse(&data_bytes).unwrap();
+
+    elf_container
+        .rename_static_symbols(&HashMap::from([("versioned_hello", "static_hello")]))
+        .unwrap();
+    elf_container
+        .rename_symbols(&HashMap::from([("versioned_answer", "renamed_answer")]))
+        .unwrap();
+    elf_container
+        .delete_static_symbols(vec!["versioned.c"])
+        .unwrap();
+
+    let mut changed_elf_data = Vec::new();
+    elf_container.write(&mut changed_elf_data).unwrap();
+
+    let changed_elf = Elf::parse(&changed_elf_data).unwrap();
+    let symbols: Vec<_> = changed_elf
+        .syms
+        .iter()
+        .filter_map(|sym| changed_elf.strtab.get_at(sym.st_name))
+        .collect();
+    let dynamic_symbols: Vec<_> = changed_elf
+        .dynsyms
+        .iter()
+        .filter_map(|sym| changed_elf.dynstrtab.get_at(sym.st_name))
+        .collect();
+
+    assert!(symbols.contains(&"static_hello"));
+    assert!(symbols.contains(&"renamed_answer"));
+    assert!(!symbols.contains(&"versioned.c"));
+    assert!(dynamic_symbols.contains(&"versioned_hello"));
+    assert!(dynamic_symbols.contains(&"renamed_answer"));
+}
+
+/// This test verifies that added sections can be read back, and that
+/// allocated sections are mapped by a `PT_LOAD` segment.
+#[rstest]
+fn test_add_update_and_dump_section(
+    #[files("../../tests/data/elf/x64/libs/libversioned.so.1")] bin_path: PathBuf,
+) {
+    let data_bytes = std::fs::read(&bin_path).unwrap();
+
+    let mut elf_container = ElfContainer::parse(&data_bytes).unwrap();
+
+    elf_container
+        .add_section(".sbom", 0, b"sbom contents".to_vec(), false)
+        .unwrap();
+    elf_container
+        .add_section(".provenance", 0, vec![0xab; 0x2000], true)
+        .unwrap();
+    assert!(elf_container
+        .add_section(".sbom", 0, Vec::new(), false)
+        .is_err());
+    elf_container
+        .update_section(".sbom", b"updated sbom".to_vec())
+        .unwrap();
+    assert!(elf_container.section_data(".dynstr").is_err());
+
+    let mut changed_elf_data = Vec::new();
+    elf_container.write(&mut changed_elf_data).unwrap();
+
+    let mut changed_elf = ElfContainer::parse(&changed_elf_data).unwrap();
+    assert_eq!(
+        changed_elf.section_data(".sbom").unwrap(),
+        b"updated sbom".as_slice()
+    );
+    assert_eq!(
+        changed_elf.section_data(".provenance").unwrap(),
+        vec![0xab; 0x2000].as_slice()
+    );
+
+    let elf = Elf::parse(&changed_elf_data).unwrap();
+    let provenance = elf
+        .section_headers
+        .iter()
+        .find(|header| elf.shdr_strtab.get_at(header.sh_name) == Some(".provenance"))
+        .unwrap();
+    assert!(elf.program_headers.iter().any(|header| {
+        header.p_type == goblin::elf::program_header::PT_LOAD
+            && header.p_offset <= provenance.sh_offset
+            && provenance.sh_offset + provenance.sh_size <= header.p_offset + header.p_filesz
+            && header.p_vaddr <= provenance.sh_addr
+    }));
+
+    // The allocated section is now mapped and can't grow.
+    assert!(changed_elf
+        .update_section(".provenance", vec![0; 0x2001])
+        .is_err());
+}
+
+/// This test verifies that growing the dynamic string table over and over
+/// reuses existing segments instead of adding a new one on every edit.
+#[rstest]
+fn test_segment_count_is_stable_across_edits(
+    #[files("../../tests/data/elf/x64/libs/versioned-consumer")]
+    #[files("../../tests/data/elf/x64/exec/*")]
+    bin_path: PathBuf,
+) {
+    let count_load_segments = |data: &[u8]| {
+        let elf = Elf::parse(data).unwrap();
+        elf.program_headers
+            .iter()
+            .filter(|header| header.p_type == goblin::elf::program_header::PT_LOAD)
+            .count()
+    };
+
+    let mut data_bytes = std::fs::read(&bin_path).unwrap();
+    let original_count = count_load_segments(&data_bytes);
+    let mut runpath = String::from("/opt");
+    for index in 0..10 {
+        runpath.push_str(&format!(
+            ":/a/rather/long/runpath/entry/number/{index:0>64}"
+        ));
+
+        let mut elf_container = ElfContainer::parse(&data_bytes).unwrap();
+        elf_container.set_runpath(&runpath).unwrap();
+
+        let mut changed_elf_data = Vec::new();
+        elf_container.write(&mut changed_elf_data).unwrap();
+
+        // At most one segment is ever added, no matter how many edits are made.
+        assert!(count_load_segments(&changed_elf_data) <= original_count + 1);
+        data_bytes = changed_elf_data;
+    }
+
+    let elf_container = ElfContainer::parse(&data_bytes).unwrap();
+    assert_eq!(elf_container.get_rpath().join(":"), runpath);
+}
+
+/// This test verifies that a large relocation table right after `.dynstr` is moved
+/// when the program headers grow into it, that `DT_RELA` follows it, and that the
+/// loader still applies it for a consumer of the library.
+#[rstest]
+fn test_move_dynamic_relocations(
+    #[files("../../tests/data/elf/x64/libs/libbigrel.so.1")] bin_path: PathBuf,
+) {
+    let data_bytes = std::fs::read(&bin_path).unwrap();
+    let original_elf = Elf::parse(&data_bytes).unwrap();
+
+    let mut elf_container = ElfContainer::parse(&data_bytes).unwrap();
+    // Each of these needs its own segment, which needs room for three more program headers.
+    for (name, flags) in [
+        (".blob.r", 0),
+        (".blob.w", u64::from(goblin::elf::section_header::SHF_WRITE)),
+        (
+            ".blob.x",
+            u64::from(goblin::elf::section_header::SHF_EXECINSTR),
+        ),
+    ] {
+        elf_container
+            .add_section(name, flags, vec![0; 0x5000], true)
+            .unwrap();
+    }
+    elf_container.add_needed(vec!["libm.so.6"]).unwrap();
+
+    let mut changed_elf_data = Vec::new();
+    elf_container.write(&mut changed_elf_data).unwrap();
+
+    let changed_elf = Elf::parse(&changed_elf_data).unwrap();
+    let rela_dyn = changed_elf
+        .section_headers
+        .iter()
+        .find(|header| changed_elf.shdr_strtab.get_at(header.sh_name) == Some(".rela.dyn"))
+        .unwrap();
+    let dynamic_value = |elf: &Elf, tag| {
+        let dynamic = elf.dynamic.as_ref().unwrap();
+        dynamic
+            .dyns
+            .iter()
+            .find(|entry| entry.d_tag == tag)
+            .unwrap()
+            .d_val
+    };
+    assert_ne!(
+        dynamic_value(&changed_elf, DT_RELA),
+        dynamic_value(&original_elf, DT_RELA)
+    );
+    assert_eq!(dynamic_value(&changed_elf, DT_RELA), rela_dyn.sh_addr);
+    assert_eq!(dynamic_value(&changed_elf, DT_RELASZ), rela_dyn.sh_size);
+
+    let relocations = |elf: &Elf| {
+        elf.dynrelas
+            .iter()
+            .map(|rela| (rela.r_offset, rela.r_type, rela.r_addend))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(relocations(&changed_elf), relocations(&original_elf));
+
+    // The loader still applies the moved relocations.
+    #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let output_dir = tempfile::tempdir().unwrap();
+        let consumer_output = output_dir.path().join("bigrel-consumer");
+        std::fs::write(output_dir.path().join("libbigrel.so.1"), &changed_elf_data).unwrap();
+        std::fs::copy(bin_path.with_file_name("bigrel-consumer"), &consumer_output).unwrap();
+        std::fs::set_permissions(&consumer_output, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let output = std::process::Command::new(&consumer_output)
+            .env("LD_LIBRARY_PATH", output_dir.path())
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "relocations ok\n");
+    }
+}
+
+/// Write `data` to `path`, creating the parent directories.
+fn write_file(path: &Path, data: &[u8]) {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, data).unwrap();
+}
+
+/// This test verifies that dependencies are found through `ld.so.conf` and its includes
+/// inside a sysroot, and that libraries of another class are skipped.
+#[rstest]
+fn test_resolve_in_sysroot(
+    #[files("../../tests/data/elf/x64/libs/versioned-consumer")] bin_path: PathBuf,
+) {
+    let libs = bin_path.parent().unwrap();
+    let data_dir = libs.parent().unwrap().parent().unwrap();
+    let sysroot = tempfile::tempdir().unwrap();
+    let sysroot = sysroot.path();
+
+    write_file(
+        &sysroot.join("etc/ld.so.conf"),
+        b"# comment\ninclude ld.so.conf.d/*.conf\n",
+    );
+    write_file(
+        &sysroot.join("etc/ld.so.conf.d/versioned.conf"),
+        b"/opt/versioned/lib\n",
+    );
+    write_file(
+        &sysroot.join("opt/versioned/lib/libversioned.so.1"),
+        &std::fs::read(libs.join("libversioned.so.1")).unwrap(),
+    );
+    // A 32-bit library with the right name, earlier in the search order.
+    write_file(
+        &sysroot.join("lib32/libversioned.so.1"),
+        &std::fs::read(data_dir.join("x32/libs/linux-x32.so")).unwrap(),
+    );
+
+    let tree = Resolver::new()
+        .with_sysroot(sysroot)
+        .with_library_path("/lib32")
+        .resolve(&bin_path)
+        .unwrap();
+
+    let dependencies: Vec<_> = tree
+        .dependencies
+        .iter()
+        .map(|dependency| {
+            (
+                dependency.name.as_str(),
+                dependency.path.clone(),
+                dependency.source,
+            )
+        })
+        .collect();
+    assert_eq!(
+        dependencies,
+        vec![
+            (
+                "libversioned.so.1",
+                Some(sysroot.join("opt/versioned/lib/libversioned.so.1")),
+                Some(SearchSource::LdSoConf)
+            ),
+            ("libc.so.6", None, None),
+        ]
+    );
+    // libc.so.6 is needed by the library too, but it isn't found either.
+    assert_eq!(tree.dependencies[0].dependencies[0].name, "libc.so.6");
+    assert!(tree.dependencies[0].dependencies[0].path.is_none());
+
+    // With DF_1_NODEFLIB set, ld.so.conf and the default directories are not searched.
+    let data_bytes = std::fs::read(&bin_path).unwrap();
+    let mut elf_container = ElfContainer::parse(&data_bytes).unwrap();
+    elf_container.no_default_lib().unwrap();
+    let no_default_lib = sysroot.join("no-default-lib");
+    let mut output = std::fs::File::create(&no_default_lib).unwrap();
+    elf_container.write(&mut output).unwrap();
+
+    let tree = Resolver::new()
+        .with_sysroot(sysroot)
+        .resolve(&no_default_lib)
+        .unwrap();
+    assert!(tree
+        .dependencies
+        .iter()
+        .all(|dependency| dependency.path.is_none()));
+}
+
+/// This test verifies that `$ORIGIN` in a runpath is expanded relative to the binary,
+/// outside of the sysroot.
+#[rstest]
+fn test_resolve_origin_runpath(
+    #[files("../../tests/data/elf/x64/libs/versioned-consumer")] bin_path: PathBuf,
+) {
+    let prefix = tempfile::tempdir().unwrap();
+    let prefix = prefix.path();
+    let sysroot = tempfile::tempdir().unwrap();
+
+    let data_bytes = std::fs::read(&bin_path).unwrap();
+    let mut elf_container = ElfContainer::parse(&data_bytes).unwrap();
+    elf_container
+        .set_runpath("/nonexistent:${ORIGIN}/../lib")
+        .unwrap();
+    let consumer = prefix.join("bin/consumer");
+    std::fs::create_dir_all(consumer.parent().unwrap()).unwrap();
+    let mut output = std::fs::File::create(&consumer).unwrap();
+    elf_container.write(&mut output).unwrap();
+    write_file(
+        &prefix.join("lib/libversioned.so.1"),
+        &std::fs::read(bin_path.with_file_name("libversioned.so.1")).unwrap(),
+    );
+
+    let tree = Resolver::new()
+        .with_sysroot(sysroot.path())
+        .resolve(&consumer)
+        .unwrap();
+
+    let versioned = &tree.dependencies[0];
+    assert_eq!(versioned.name, "libversioned.so.1");
+    assert_eq!(
+        versioned.path,
+        Some(prefix.join("bin/../lib/libversioned.so.1"))
+    );
+    assert_eq!(versioned.source, Some(SearchSource::Runpath));
+    insta::assert_snapshot!(tree
+        .to_string()
+        .replace(&prefix.to_string_lossy().into_owned(), "$PREFIX"));
+}
+
+/// This test verifies that `$ORIGIN` is expanded once when the directory of the
+/// object itself contains `$ORIGIN`.
+#[rstest]
+fn test_resolve_origin_in_origin(
+    #[files("../../tests/data/elf/x64/libs/versioned-consumer")] bin_path: PathBuf,
+) {
+    let prefix = tempfile::tempdir().unwrap();
+    let origin = prefix.path().join("o$ORIGIN");
+    let sysroot = tempfile::tempdir().unwrap();
+
+    let data_bytes = std::fs::read(&bin_path).unwrap();
+    let mut elf_container = ElfContainer::parse(&data_bytes).unwrap();
+    elf_container.set_runpath("$ORIGIN/lib").unwrap();
+    let consumer = origin.join("consumer");
+    std::fs::create_dir_all(&origin).unwrap();
+    let mut output = std::fs::File::create(&consumer).unwrap();
+    elf_container.write(&mut output).unwrap();
+    write_file(
+        &origin.join("lib/libversioned.so.1"),
+        &std::fs::read(bin_path.with_file_name("libversioned.so.1")).unwrap(),
+    );
+
+    let tree = Resolver::new()
+        .with_sysroot(sysroot.path())
+        .resolve(&consumer)
+        .unwrap();
+
+    let versioned = &tree.dependencies[0];
+    assert_eq!(versioned.path, Some(origin.join("lib/libversioned.so.1")));
+    assert_eq!(versioned.source, Some(SearchSource::Runpath));
+}
+
+/// This test verifies that shrinking only keeps directories with a library of the same
+/// class, and that `$ORIGIN` is expanded relative to the file.
+#[rstest]
+#[case::x64(
+    "../../tests/data/elf/x64/libs/versioned-consumer",
+    "../../tests/data/elf/x64/libs/libversioned.so.1",
+    "../../tests/data/elf/x32/libs/linux-x32.so"
+)]
+#[case::x32(
+    "../../tests/data/elf/x32/exec/linux-x32-bash",
+    "../../tests/data/elf/x32/libs/linux-x32.so",
+    "../../tests/data/elf/x64/libs/libversioned.so.1"
+)]
+fn test_shrink_rpath(#[case] binary: &str, #[case] library: &str, #[case] other_class: &str) {
+    let prefix = tempfile::tempdir().unwrap();
+    let prefix = prefix.path();
+    let data_bytes = std::fs::read(binary).unwrap();
+    let mut elf_container = ElfContainer::parse(&data_bytes).unwrap();
+    let needed = Elf::parse(&data_bytes).unwrap().libraries[0].to_string();
+
+    write_file(
+        &prefix.join("lib").join(&needed),
+        &std::fs::read(library).unwrap(),
+    );
+    write_file(
+        &prefix.join("other").join(&needed),
+        &std::fs::read(other_class).unwrap(),
+    );
+    std::fs::create_dir(prefix.join("bin")).unwrap();
+    let other = prefix.join("other").to_string_lossy().into_owned();
+    elf_container
+        .set_runpath(format!("{other}:$ORIGIN/../lib:/nonexistent:relative"))
+        .unwrap();
+
+    let report = elf_container
+        .shrink_rpath(Vec::new(), Some(&prefix.join("bin")))
+        .unwrap();
+
+    let decisions: Vec<_> = report
+        .entries
+        .iter()
+        .map(|entry| (entry.path.as_str(), entry.kept, entry.reason.clone()))
+        .collect();
+    assert_eq!(
+        decisions,
+        vec![
+            (other.as_str(), false, ShrinkReason::NoNeededLibrary),
+            (
+                "$ORIGIN/../lib",
+                true,
+                ShrinkReason::ProvidesLibrary(needed.clone())
+            ),
+            ("/nonexistent", false, ShrinkReason::NoNeededLibrary),
+            ("relative", true, ShrinkReason::Unresolved),
+        ]
+    );
+    assert_eq!(
+        elf_container.runpaths()[0].entries,
+        vec!["$ORIGIN/../lib", "relative"]
+    );
+
+    // Without the location of the file, $ORIGIN entries are kept unchecked.
+    elf_container.set_runpath("$ORIGIN/../missing").unwrap();
+    let report = elf_container.shrink_rpath(Vec::new(), None).unwrap();
+    assert_eq!(report.entries[0].reason, ShrinkReason::UnknownOrigin);
+    assert_eq!(report.kept().count(), 1);
+
+    // An origin that contains `$ORIGIN` itself is expanded once.
+    let origin = prefix.join("o$ORIGIN");
+    write_file(
+        &origin.join("lib").join(&needed),
+        &std::fs::read(library).unwrap(),
+    );
+    elf_container.set_runpath("$ORIGIN/lib").unwrap();
+    let report = elf_container
+        .shrink_rpath(Vec::new(), Some(&origin))
+        .unwrap();
+    assert_eq!(
+        report.entries[0].reason,
+        ShrinkReason::ProvidesLibrary(needed.clone())
+    );
+}
+
+/// This test checks that `e_flags` are decoded per architecture.
+#[rstest]
+#[case::riscv_double_float(goblin::elf::header::EM_RISCV, 0x5, &["double-float", "rvc"])]
+#[case::riscv_soft_float(goblin::elf::header::EM_RISCV, 0x0, &["soft-float"])]
+#[case::arm_eabi5_hard_float(goblin::elf::header::EM_ARM, 0x0500_0400, &["eabi5", "hard-float"])]
+#[case::arm_old_abi(goblin::elf::header::EM_ARM, 0x0000_0004, &["interwork"])]
+#[case::mips_o32(goblin::elf::header::EM_MIPS, 0x7000_1007, &["mips32r2", "o32", "noreorder", "pic", "cpic"])]
+#[case::mips_unknown_bits(goblin::elf::header::EM_MIPS, 0x6000_0800, &["mips64", "0x800"])]
+#[case::x86_64(goblin::elf::header::EM_X86_64, 0x1234, &[])]
+fn test_decode_e_flags(#[case] e_machine: u16, #[case] e_flags: u32, #[case] expected: &[&str]) {
+    assert_eq!(decode_e_flags(e_machine, e_flags), expected);
+}
+
+/// This test verifies that the ABI version and `e_flags` can be set and read back.
+#[rstest]
+fn test_set_header_fields(
+    #[files("../../tests/data/elf/x64/libs/versioned-consumer")] bin_path: PathBuf,
+) {
+    let data_bytes = std::fs::read(&bin_path).unwrap();
+    let mut elf_container = ElfContainer::parse(&data_bytes).unwrap();
+
+    elf_container.set_abi_version(3);
+    elf_container.set_e_flags(0x0500_0400);
+
+    let mut changed_elf_data = Vec::new();
+    elf_container.write(&mut changed_elf_data).unwrap();
+
+    let changed_elf = Elf::parse(&changed_elf_data).unwrap();
+    assert_eq!(
+        changed_elf.header.e_ident[goblin::elf::header::EI_ABIVERSION],
+        3
+    );
+    assert_eq!(changed_elf.header.e_flags, 0x0500_0400);
+
+    let elf_container = ElfContainer::parse(&changed_elf_data).unwrap();
+    insta::assert_snapshot!(elf_container.header().to_string());
+}
+
+/// This test lists the program headers with the sections mapped into them.
+#[rstest]
+fn test_print_segments(
+    #[files("../../tests/data/elf/x64/libs/versioned-consumer")] bin_path: PathBuf,
+) {
+    let data_bytes = std::fs::read(&bin_path).unwrap();
+    let elf_container = ElfContainer::parse(&data_bytes).unwrap();
+
+    let segments = elf_container
+        .segments()
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    insta::assert_snapshot!(segments.join("\n"));
+}
+
+/// This test verifies that segment flags can be changed, and that mapping a section
+/// writable and executable needs to be forced.
+#[rstest]
+fn test_set_segment_flags(
+    #[files("../../tests/data/elf/x64/libs/versioned-consumer")] bin_path: PathBuf,
+) {
+    use goblin::elf::program_header::{PF_R, PF_W, PF_X, PT_GNU_RELRO, PT_LOAD};
+
+    let data_bytes = std::fs::read(&bin_path).unwrap();
+    let mut elf_container = ElfContainer::parse(&data_bytes).unwrap();
+    let segments = elf_container.segments();
+    let text = segments
+        .iter()
+        .find(|segment| segment.p_type == PT_LOAD && segment.p_flags & PF_X != 0)
+        .unwrap()
+        .index;
+    let relro = segments
+        .iter()
+        .find(|segment| segment.p_type == PT_GNU_RELRO)
+        .unwrap()
+        .index;
+
+    let error = elf_container
+        .set_segment_flags(text, PF_R | PF_W | PF_X, false)
+        .unwrap_err();
+    assert!(
+        matches!(error, ElfError::Modify(message) if message.contains("writable and executable"))
+    );
+    assert_eq!(elf_container.segments()[text].p_flags, PF_R | PF_X);
+
+    elf_container
+        .set_segment_flags(relro, PF_R | PF_W, false)
+        .unwrap();
+    elf_container
+        .set_segment_flags(text, PF_R | PF_W | PF_X, true)
+        .unwrap();
+
+    let mut changed_elf_data = Vec::new();
+    elf_container.write(&mut changed_elf_data).unwrap();
+
+    let changed_elf = Elf::parse(&changed_elf_data).unwrap();
+    assert_eq!(
+        changed_elf.program_headers[text].p_flags,
+        PF_R | PF_W | PF_X
+    );
+    assert_eq!(changed_elf.program_headers[relro].p_flags, PF_R | PF_W);
+}
+
+/// This test verifies that the CET features of the GNU property note are parsed and
+/// cleared, and that `PT_GNU_PROPERTY` still covers the note when sections move.
+#[rstest]
+fn test_clear_gnu_property(#[files("../../tests/data/elf/x64/libs/cet-hello")] bin_path: PathBuf) {
+    use goblin::elf::program_header::PT_GNU_PROPERTY;
+
+    let data_bytes = std::fs::read(&bin_path).unwrap();
+    let mut elf_container = ElfContainer::parse(&data_bytes).unwrap();
+    let properties = elf_container.gnu_properties();
+    let feature = properties
+        .iter()
+        .find(|property| property.name == "X86_FEATURE_1_AND")
+        .unwrap();
+    assert_eq!(feature.flags, ["IBT", "SHSTK"]);
+
+    let error = elf_container.clear_gnu_property("BTI").unwrap_err();
+    assert!(matches!(error, ElfError::Modify(message) if message.contains("Unknown property")));
+
+    elf_container.clear_gnu_property("shstk").unwrap();
+    elf_container
+        .add_runpath("/a/runpath/long/enough/to/move/the/dynamic/string/table")
+        .unwrap();
+
+    let mut changed_elf_data = Vec::new();
+    elf_container.write(&mut changed_elf_data).unwrap();
+
+    let changed_container = ElfContainer::parse(&changed_elf_data).unwrap();
+    let feature = changed_container
+        .gnu_properties()
+        .into_iter()
+        .find(|property| property.name == "X86_FEATURE_1_AND")
+        .unwrap();
+    assert_eq!(feature.flags, ["IBT"]);
+
+    let changed_elf = Elf::parse(&changed_elf_data).unwrap();
+    let section = changed_elf
+        .section_headers
+        .iter()
+        .find(|section| {
+            changed_elf.shdr_strtab.get_at(section.sh_name) == Some(".note.gnu.property")
+        })
+        .unwrap();
+    let segment = changed_elf
+        .program_headers
+        .iter()
+        .find(|segment| segment.p_type == PT_GNU_PROPERTY)
+        .unwrap();
+    assert_eq!(segment.p_offset, section.sh_offset);
+    assert_eq!(segment.p_vaddr, section.sh_addr);
+    assert_eq!(segment.p_filesz, section.sh_size);
+}
+
+/// Look up a dynamic symbol by name in each hash table of a 64-bit file, the way the
+/// dynamic loader does, and return whether each table found it.
+fn hash_table_lookups(data: &[u8], name: &str) -> Vec<(&'static str, bool)> {
+    use object::{
+        elf::{self, FileHeader64},
+        read::elf::{FileHeader, SectionHeader},
+        Endianness,
+    };
+
+    let header = FileHeader64::<Endianness>::parse(data).unwrap();
+    let endian = header.endian().unwrap();
+    let sections = header.sections(endian, data).unwrap();
+    let symbols = sections.symbols(endian, data, elf::SHT_DYNSYM).unwrap();
+    let versions = sections.versions(endian, data).unwrap().unwrap_or_default();
+    let name = name.as_bytes();
+
+    let mut lookups = Vec::new();
+    for section in sections.iter() {
+        if let Some((table, _)) = section.hash(endian, data).unwrap() {
+            let found = table.find(endian, name, elf::hash(name), None, &symbols, &versions);
+            lookups.push(("hash", found.is_some()));
+        }
+        if let Some((table, _)) = section.gnu_hash(endian, data).unwrap() {
+            let found = table.find(endian, name, elf::gnu_hash(name), None, &symbols, &versions);
+            lookups.push(("gnu_hash", found.is_some()));
+        }
+    }
+    lookups
+}
+
+/// This test verifies that the hash tables are regenerated after renaming dynamic
+/// symbols, so that the dynamic loader finds the new names and not the old ones.
+#[rstest]
+#[case::gnu_hash("libversioned.so.1", &["gnu_hash"])]
+#[case::sysv_hash("libsysvhash.so.1", &["hash"])]
+fn test_rename_dynamic_symbols_hash(#[case] library: &str, #[case] tables: &[&str]) {
+    let bin_path = Path::new("../../tests/data/elf/x64/libs").join(library);
+    let data_bytes = std::fs::read(&bin_path).unwrap();
+    let mut elf_container = ElfContainer::parse(&data_bytes).unwrap();
+
+    elf_container
+        .rename_dynamic_symbols(&HashMap::from([(
+            "versioned_answer",
+            "a_much_longer_name_for_the_answer",
+        )]))
+        .unwrap();
+
+    let mut changed_elf_data = Vec::new();
+    elf_container.write(&mut changed_elf_data).unwrap();
+
+    let found = |name| {
+        hash_table_lookups(&changed_elf_data, name)
+            .into_iter()
+            .filter(|(_, found)| *found)
+            .map(|(table, _)| table)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(found("a_much_longer_name_for_the_answer"), tables);
+    assert_eq!(found("versioned_hello"), tables);
+    assert!(found("versioned_answer").is_empty());
+}
+
+/// This test verifies that a `.gnu.hash` table can be added to a library that only
+/// has a `DT_HASH` table, and that both tables find every defined symbol.
+#[rstest]
+fn test_add_gnu_hash(#[files("../../tests/data/elf/x64/libs/libsysvhash.so.1")] bin_path: PathBuf) {
+    use goblin::elf::dynamic::DT_GNU_HASH;
+
+    let data_bytes = std::fs::read(&bin_path).unwrap();
+    assert_eq!(
+        hash_table_lookups(&data_bytes, "versioned_answer"),
+        [("hash", true)]
+    );
+
+    let mut elf_container = ElfContainer::parse(&data_bytes).unwrap();
+    elf_container.add_gnu_hash().unwrap();
+    // Adding it again does nothing.
+    elf_container.add_gnu_hash().unwrap();
+
+    let mut changed_elf_data = Vec::new();
+    elf_container.write(&mut changed_elf_data).unwrap();
+
+    let changed_elf = Elf::parse(&changed_elf_data).unwrap();
+    let dynamic = changed_elf.dynamic.as_ref().unwrap();
+    assert_eq!(
+        dynamic
+            .dyns
+            .iter()
+            .filter(|entry| entry.d_tag == DT_GNU_HASH)
+            .count(),
+        1
+    );
+
+    let defined_symbols: Vec<_> = changed_elf
+        .dynsyms
+        .iter()
+        .filter(|sym| sym.st_shndx != 0)
+        .filter_map(|sym| changed_elf.dynstrtab.get_at(sym.st_name))
+        .filter(|name| !name.is_empty())
+        .collect();
+    assert!(defined_symbols.contains(&"versioned_answer"));
+    for name in defined_symbols {
+        assert_eq!(
+            hash_table_lookups(&changed_elf_data, name),
+            [("hash", true), ("gnu_hash", true)],
+            "{name}"
+        );
+    }
+    assert_eq!(
+        hash_table_lookups(&changed_elf_data, "missing_symbol"),
+        [("hash", false), ("gnu_hash", false)]
+    );
+}
+
+/// This test verifies that defined dynamic symbols matching a pattern stop being
+/// exported, while imports and other symbols are left alone.
+#[rstest]
+#[case::localize(ExportChange::Localize, Some((STB_LOCAL, STV_DEFAULT)))]
+#[case::hide(ExportChange::Hide, Some((STB_GLOBAL, STV_HIDDEN)))]
+#[case::weaken(ExportChange::Weaken, Some((STB_WEAK, STV_DEFAULT)))]
+#[case::unexport(ExportChange::Unexport, None)]
+fn test_change_symbol_export(#[case] change: ExportChange, #[case] expected: Option<(u8, u8)>) {
+    let bin_path = Path::new("../../tests/data/elf/x64/libs/libversioned.so.1");
+    let data_bytes = std::fs::read(bin_path).unwrap();
+    let mut elf_container = ElfContainer::parse(&data_bytes).unwrap();
+
+    // `__cxa_finalize` is an import, so it doesn't match.
+    let changed = elf_container.change_symbol_export(&["versioned_a*", "__cxa_*"], change);
+    assert_eq!(changed, ["versioned_answer"]);
+
+    let mut changed_elf_data = Vec::new();
+    elf_container.write(&mut changed_elf_data).unwrap();
+
+    let changed_elf = Elf::parse(&changed_elf_data).unwrap();
+    let symbol = |name| {
+        changed_elf
+            .dynsyms
+            .iter()
+            .find(|sym| changed_elf.dynstrtab.get_at(sym.st_name) == Some(name))
+            .map(|sym| (sym.st_bind(), sym.st_visibility()))
+    };
+    assert_eq!(symbol("versioned_answer"), expected);
+    assert_eq!(symbol("versioned_hello"), Some((STB_GLOBAL, STV_DEFAULT)));
+
+    // Local symbols are not part of the GNU hash table.
+    let exported = expected.is_some_and(|(bind, _)| bind != STB_LOCAL);
+    assert_eq!(
+        hash_table_lookups(&changed_elf_data, "versioned_answer"),
+        [("gnu_hash", exported)]
+    );
+    assert_eq!(
+        hash_table_lookups(&changed_elf_data, "versioned_hello"),
+        [("gnu_hash", true)]
+    );
+}
+
+/// This test verifies that renaming a library updates its SONAME and base version
+/// definition, and the needed entry and version requirements of its consumers.
+#[rstest]
+fn test_rename_library(
+    #[files("../../tests/data/elf/x64/libs/libversioned.so.1")] library_path: PathBuf,
+) {
+    use goblin::elf::symver::VER_FLG_BASE;
+
+    let libs = library_path.parent().unwrap();
+    let library_bytes = std::fs::read(&library_path).unwrap();
+    let consumer_bytes = std::fs::read(libs.join("versioned-consumer")).unwrap();
+    let other_bytes = std::fs::read(libs.join("cet-hello")).unwrap();
+
+    let mut library = ElfContainer::parse(&library_bytes).unwrap();
+    library.set_soname("libvendored.so.1").unwrap();
+    library.set_version_base("libvendored.so.1").unwrap();
+    let mut changed_library = Vec::new();
+    library.write(&mut changed_library).unwrap();
+
+    let changed_elf = Elf::parse(&changed_library).unwrap();
+    assert_eq!(changed_elf.soname, Some("libvendored.so.1"));
+    let base = changed_elf
+        .verdef
+        .as_ref()
+        .unwrap()
+        .iter()
+        .find(|def| def.vd_flags & VER_FLG_BASE != 0)
+        .unwrap();
+    let base_name = base.iter().next().unwrap().vda_name;
+    assert_eq!(
+        changed_elf.dynstrtab.get_at(base_name),
+        Some("libvendored.so.1")
+    );
+
+    let mut consumer = ElfContainer::parse(&consumer_bytes).unwrap();
+    assert!(consumer
+        .rename_needed_library("libversioned.so.1", "libvendored.so.1")
+        .unwrap());
+    let mut changed_consumer = Vec::new();
+    consumer.write(&mut changed_consumer).unwrap();
+
+    let changed_elf = Elf::parse(&changed_consumer).unwrap();
+    assert_eq!(changed_elf.libraries, ["libvendored.so.1", "libc.so.6"]);
+    let files: Vec<_> = changed_elf
+        .verneed
+        .as_ref()
+        .unwrap()
+        .iter()
+        .map(|need| changed_elf.dynstrtab.get_at(need.vn_file).unwrap())
+        .collect();
+    assert_eq!(files, ["libvendored.so.1", "libc.so.6"]);
+
+    // A file that doesn't need the library is left alone.
+    let mut other = ElfContainer::parse(&other_bytes).unwrap();
+    assert!(!other
+        .rename_needed_library("libversioned.so.1", "libvendored.so.1")
+        .unwrap());
+}
+
+/// This test verifies that a versioned consumer still loads after its library was
+/// renamed with `set_soname` and `replace_needed`, which also rename the library in
+/// the version definitions and requirements.
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+#[rstest]
+fn test_replace_needed_versioned_run(
+    #[files("../../tests/data/elf/x64/libs/libversioned.so.1")] library_path: PathBuf,
+) {
+    use std::os::unix::fs::PermissionsExt;
+
+    let libs = library_path.parent().unwrap();
+    let library_bytes = std::fs::read(&library_path).unwrap();
+    let consumer_bytes = std::fs::read(libs.join("versioned-consumer")).unwrap();
+    let output_dir = tempfile::tempdir().unwrap();
+    let library_output = output_dir.path().join("libversioned_vendored.so.1");
+    let consumer_output = output_dir.path().join("versioned-consumer");
+
+    let mut library = ElfContainer::parse(&library_bytes).unwrap();
+    library.set_soname("libversioned_vendored.so.1").unwrap();
+    library.write_to_path(&library_output).unwrap();
+
+    let mut consumer = ElfContainer::parse(&consumer_bytes).unwrap();
+    consumer
+        .replace_needed(&HashMap::from([(
+            "libversioned.so.1",
+            "libversioned_vendored.so.1",
+        )]))
+        .unwrap();
+    consumer.write_to_path(&consumer_output).unwrap();
+    std::fs::set_permissions(&consumer_output, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let output = std::process::Command::new(&consumer_output)
+        .env("LD_LIBRARY_PATH", output_dir.path())
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Hello from libversioned!"));
+}
+
+/// This test verifies that the exports of a library and the matching imports of its
+/// consumer get the same prefix, and that their versions are kept.
+#[rstest]
+fn test_prefix_symbols(
+    #[files("../../tests/data/elf/x64/libs/libversioned.so.1")] library_path: PathBuf,
+) {
+    let libs = library_path.parent().unwrap();
+    let library_bytes = std::fs::read(&library_path).unwrap();
+    let consumer_bytes = std::fs::read(libs.join("versioned-consumer")).unwrap();
+    let other_bytes = std::fs::read(libs.join("cet-hello")).unwrap();
+
+    let mut library = ElfContainer::parse(&library_bytes).unwrap();
+    let renames = library.prefix_exports("myvendor_");
+    let mut names: Vec<_> = renames
+        .iter()
+        .map(|rename| {
+            (
+                rename.old.as_str(),
+                rename.new.as_str(),
+                rename.version.as_deref(),
+            )
+        })
+        .collect();
+    names.sort();
+    // The symbol naming the version definition is not an export.
+    assert_eq!(
+        names,
+        [
+            (
+                "versioned_answer",
+                "myvendor_versioned_answer",
+                Some("VERS_1.0")
+            ),
+            (
+                "versioned_hello",
+                "myvendor_versioned_hello",
+                Some("VERS_1.0")
+            ),
+        ]
+    );
+
+    let mut consumer = ElfContainer::parse(&consumer_bytes).unwrap();
+    let consumer_renames = consumer
+        .rename_imports("libversioned.so.1", &renames)
+        .unwrap();
+    assert_eq!(consumer_renames.len(), 2);
+
+    let mut other = ElfContainer::parse(&other_bytes).unwrap();
+    let error = other
+        .rename_imports("libversioned.so.1", &renames)
+        .unwrap_err();
+    assert!(matches!(error, ElfError::Modify(message) if message.contains("doesn't need")));
+
+    let mut changed_library = Vec::new();
+    library.write(&mut changed_library).unwrap();
+    let mut changed_consumer = Vec::new();
+    consumer.write(&mut changed_consumer).unwrap();
+
+    let changed_elf = Elf::parse(&changed_consumer).unwrap();
+    let versym = changed_elf.versym.as_ref().unwrap();
+    let verneed = changed_elf.verneed.as_ref().unwrap();
+    let mut prefixed = 0;
+    for (index, sym) in changed_elf.dynsyms.iter().enumerate() {
+        let name = changed_elf.dynstrtab.get_at(sym.st_name).unwrap();
+        if !name.starts_with("myvendor_") {
+            continue;
+        }
+        // The import still requires the version from the library.
+        let version = versym.get_at(index).unwrap().version();
+        let (file, aux) = verneed
+            .iter()
+            .find_map(|need| {
+                need.iter()
+                    .find(|aux| aux.vna_other == version)
+                    .map(|aux| (need.vn_file, aux.vna_name))
+            })
+            .unwrap();
+        assert_eq!(
+            changed_elf.dynstrtab.get_at(file),
+            Some("libversioned.so.1")
+        );
+        assert_eq!(changed_elf.dynstrtab.get_at(aux), Some("VERS_1.0"));
+        prefixed += 1;
+    }
+    assert_eq!(prefixed, 2);
+    assert_eq!(
+        hash_table_lookups(&changed_library, "myvendor_versioned_answer"),
+        [("gnu_hash", true)]
+    );
+
+    // Prefixing again changes nothing.
+    let mut library = ElfContainer::parse(&changed_library).unwrap();
+    assert!(library.prefix_exports("myvendor_").is_empty());
+}
+
+/// This test verifies that needed libraries can be added at a given position and
+/// reordered, which sets the order in which symbols are looked up.
+#[rstest]
+fn test_needed_order(
+    #[files("../../tests/data/elf/x64/libs/versioned-consumer")] bin_path: PathBuf,
+) {
+    let data_bytes = std::fs::read(&bin_path).unwrap();
+    let mut elf_container = ElfContainer::parse(&data_bytes).unwrap();
+    assert_eq!(elf_container.needed(), ["libversioned.so.1", "libc.so.6"]);
+
+    elf_container
+        .add_needed_at(
+            vec!["libm.so.6"],
+            NeededPosition::After(b"libversioned.so.1".to_vec()),
+        )
+        .unwrap();
+    elf_container
+        .add_needed_at(vec!["libz.so.1", "libdl.so.2"], NeededPosition::Index(3))
+        .unwrap();
+    elf_container
+        .add_needed_at(
+            vec!["libpthread.so.0", "libc.so.6"],
+            NeededPosition::Before(b"libm.so.6".to_vec()),
+        )
+        .unwrap();
+    assert_eq!(
+        elf_container.needed(),
+        [
+            "libversioned.so.1",
+            "libpthread.so.0",
+            "libm.so.6",
+            "libc.so.6",
+            "libz.so.1",
+            "libdl.so.2"
+        ]
+    );
+
+    let error = elf_container
+        .add_needed_at(vec!["libx.so"], NeededPosition::Index(7))
+        .unwrap_err();
+    assert!(matches!(error, ElfError::Modify(message) if message.contains("past the end")));
+    let error = elf_container
+        .add_needed_at(vec!["libx.so"], NeededPosition::After(b"liby.so".to_vec()))
+        .unwrap_err();
+    assert!(matches!(error, ElfError::Modify(message) if message.contains("is not needed")));
+    let error = elf_container
+        .reorder_needed(vec!["libc.so.6", "libc.so.6"])
+        .unwrap_err();
+    assert!(matches!(error, ElfError::Modify(message) if message.contains("listed twice")));
+
+    elf_container
+        .reorder_needed(vec!["libc.so.6", "libdl.so.2"])
+        .unwrap();
+
+    let mut changed_elf_data = Vec::new();
+    elf_container.write(&mut changed_elf_data).unwrap();
+
+    let changed_elf = Elf::parse(&changed_elf_data).unwrap();
+    assert_eq!(
+        changed_elf.libraries,
+        [
+            "libc.so.6",
+            "libdl.so.2",
+            "libversioned.so.1",
+            "libpthread.so.0",
+            "libm.so.6",
+            "libz.so.1"
+        ]
+    );
+}
+
+/// This test verifies that the files written after growing the dynamic strings and
+/// the interpreter pass the check.
+#[rstest]
+fn test_check_patched(#[files("../../tests/data/elf/*/exec/*")] bin_path: PathBuf) {
+    let data_bytes = std::fs::read(&bin_path).unwrap();
+    assert_eq!(arwen_elf::check(&data_bytes), []);
+
+    let mut elf_container = ElfContainer::parse(&data_bytes).unwrap();
+    elf_container.set_check_on_write(true);
+    elf_container
+        .set_interpreter("/a/very/long/path/to/the/dynamic/loader/ld-linux.so.2")
+        .unwrap();
+    elf_container
+        .add_runpath("$ORIGIN/../lib:$ORIGIN/../lib64:/opt/some/long/prefix/lib")
+        .unwrap();
+    elf_container.add_needed(vec!["libextra.so.1"]).unwrap();
+
+    let mut changed_elf_data = Vec::new();
+    elf_container.write(&mut changed_elf_data).unwrap();
+
+    assert_eq!(arwen_elf::check(&changed_elf_data), []);
+}
+
+/// This test verifies that the check reports corrupted segments, dynamic entries and
+/// interpreters, and that a file failing the check is not written.
+#[rstest]
+fn test_check_problems(
+    #[files("../../tests/data/elf/x64/libs/versioned-consumer")] bin_path: PathBuf,
+) {
+    use arwen_elf::Problem;
+    use goblin::elf::{
+        dynamic::DT_STRTAB,
+        program_header::{PT_INTERP, PT_LOAD},
+    };
+
+    let data_bytes = std::fs::read(&bin_path).unwrap();
+    let elf = Elf::parse(&data_bytes).unwrap();
+    let phoff = elf.header.e_phoff as usize;
+    let phentsize = usize::from(elf.header.e_phentsize);
+    let loads: Vec<_> = (0..elf.program_headers.len())
+        .filter(|&index| elf.program_headers[index].p_type == PT_LOAD)
+        .collect();
+    let interp = elf
+        .program_headers
+        .iter()
+        .find(|segment| segment.p_type == PT_INTERP)
+        .unwrap();
+    let interp_end = (interp.p_offset + interp.p_filesz) as usize;
+    let dynamic = elf.dynamic.as_ref().unwrap();
+    let strtab_index = dynamic
+        .dyns
+        .iter()
+        .position(|entry| entry.d_tag == DT_STRTAB)
+        .unwrap();
+    let dynamic_offset = elf
+        .program_headers
+        .iter()
+        .find(|segment| segment.p_type == goblin::elf::program_header::PT_DYNAMIC)
+        .unwrap()
+        .p_offset as usize;
+
+    let patch = |offset: usize, value: u64| {
+        let mut data = data_bytes.clone();
+        data[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+        data
+    };
+
+    // Move the second LOAD segment's file offset by one byte.
+    let second = &elf.program_headers[loads[1]];
+    let misaligned = patch(phoff + loads[1] * phentsize + 8, second.p_offset + 1);
+    assert_eq!(
+        arwen_elf::check(&misaligned),
+        [Problem::MisalignedSegment {
+            index: loads[1],
+            p_offset: second.p_offset + 1,
+            p_vaddr: second.p_vaddr,
+            p_align: second.p_align,
+        }]
+    );
+
+    // Grow the first LOAD segment over the second one.
+    let first = &elf.program_headers[loads[0]];
+    let overlapping = patch(
+        phoff + loads[0] * phentsize + 40,
+        second.p_vaddr - first.p_vaddr + 1,
+    );
+    assert!(
+        arwen_elf::check(&overlapping).contains(&Problem::OverlappingSegments {
+            first: loads[0],
+            second: loads[1],
+        })
+    );
+
+    // Point DT_STRTAB past the end of the mapped memory.
+    let last = &elf.program_headers[*loads.last().unwrap()];
+    let unmapped_address = last.p_vaddr + last.p_memsz + 0x10000;
+    let unmapped = patch(dynamic_offset + strtab_index * 16 + 8, unmapped_address);
+    let problems = arwen_elf::check(&unmapped);
+    assert!(problems.contains(&Problem::UnmappedDynamicEntry {
+        tag: "DT_STRTAB".to_string(),
+        address: unmapped_address,
+    }));
+    assert!(problems
+        .iter()
+        .any(|problem| matches!(problem, Problem::DynamicSectionMismatch { tag, .. } if tag == "DT_STRTAB")));
+
+    // Overwrite the NUL byte at the end of the interpreter.
+    let mut unterminated = data_bytes.clone();
+    unterminated[interp_end - 1] = b'x';
+    assert_eq!(
+        arwen_elf::check(&unterminated),
+        [Problem::UnterminatedInterpreter]
+    );
+
+    // The broken interpreter is copied by the rewriter, so the write is rejected.
+    let output_dir = tempfile::tempdir().unwrap();
+    let output_path = output_dir.path().join("versioned-consumer");
+    let mut elf_container = ElfContainer::parse(&unterminated).unwrap();
+    elf_container.set_check_on_write(true);
+    elf_container.add_needed(vec!["libextra.so.1"]).unwrap();
+    let error = elf_container.write_to_path(&output_path).unwrap_err();
+    assert!(
+        matches!(error, ElfError::Check(problems) if problems == [Problem::UnterminatedInterpreter])
+    );
+    assert!(!output_path.exists());
+}
+
+/// This test verifies that a runpath and an interpreter that fit in the space of the
+/// old ones are patched in place, leaving the rest of the file unchanged.
+#[rstest]
+fn test_patch_in_place(#[files("../../tests/data/elf/*/exec/*")] bin_path: PathBuf) {
+    use goblin::elf::program_header::PT_INTERP;
+
+    let data_bytes = std::fs::read(&bin_path).unwrap();
+    let elf = Elf::parse(&data_bytes).unwrap();
+    let dynstr = elf
+        .section_headers
+        .iter()
+        .find(|section| elf.shdr_strtab.get_at(section.sh_name) == Some(".dynstr"))
+        .unwrap()
+        .file_range()
+        .unwrap();
+    let interp = elf
+        .program_headers
+        .iter()
+        .find(|segment| segment.p_type == PT_INTERP)
+        .unwrap()
+        .file_range();
+    // The sizes of the interpreter are updated in the headers.
+    let header = &elf.header;
+    let phoff = header.e_phoff as usize;
+    let program_headers = phoff..phoff + usize::from(header.e_phnum * header.e_phentsize);
+    let shoff = header.e_shoff as usize;
+    let section_headers = shoff..shoff + usize::from(header.e_shnum * header.e_shentsize);
+
+    let mut elf_container = ElfContainer::parse(&data_bytes).unwrap();
+    elf_container.set_check_on_write(true);
+    elf_container.set_runpath("$ORIGIN").unwrap();
+    elf_container.set_interpreter("/lib/ld.so").unwrap();
+
+    let mut changed_elf_data = Vec::new();
+    elf_container.write(&mut changed_elf_data).unwrap();
+
+    assert_eq!(changed_elf_data.len(), data_bytes.len());
+    let changed_offsets: Vec<usize> = (0..data_bytes.len())
+        .filter(|&offset| data_bytes[offset] != changed_elf_data[offset])
+        .collect();
+    assert!(!changed_offsets.is_empty());
+    assert!(changed_offsets.iter().all(|offset| {
+        [&dynstr, &interp, &program_headers, &section_headers]
+            .iter()
+            .any(|range| range.contains(offset))
+    }));
+
+    let changed_elf = Elf::parse(&changed_elf_data).unwrap();
+    assert_eq!(changed_elf.runpaths, ["$ORIGIN"]);
+    assert_eq!(changed_elf.interpreter, Some("/lib/ld.so"));
+
+    // A longer runpath doesn't fit, so the file is rebuilt with both edits.
+    let mut elf_container = ElfContainer::parse(&data_bytes).unwrap();
+    elf_container.set_interpreter("/lib/ld.so").unwrap();
+    elf_container
+        .set_runpath("$ORIGIN/../lib:$ORIGIN/../lib64:/opt/some/long/prefix/lib")
+        .unwrap();
+
+    let mut changed_elf_data = Vec::new();
+    elf_container.write(&mut changed_elf_data).unwrap();
+
+    assert_ne!(changed_elf_data.len(), data_bytes.len());
+    assert_eq!(arwen_elf::check(&changed_elf_data), []);
+    let changed_elf = Elf::parse(&changed_elf_data).unwrap();
+    assert_eq!(
+        changed_elf.runpaths,
+        ["$ORIGIN/../lib:$ORIGIN/../lib64:/opt/some/long/prefix/lib"]
+    );
+    assert_eq!(changed_elf.interpreter, Some("/lib/ld.so"));
+}
+
+/// This test verifies that a memory-mapped file can be written back over itself, and
+/// that a write that fails leaves the file as it was.
+#[rstest]
+fn test_write_mapped_file(
+    #[files("../../tests/data/elf/x64/libs/versioned-consumer")] bin_path: PathBuf,
+) {
+    use goblin::elf::program_header::PT_INTERP;
+
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("versioned-consumer");
+    std::fs::copy(&bin_path, &path).unwrap();
+
+    let data_bytes = arwen_core::MappedFile::open(&path).unwrap();
+    let mut elf_container = ElfContainer::parse(&data_bytes).unwrap();
+    elf_container.add_needed(vec!["libextra.so.1"]).unwrap();
+    elf_container.write_to_path(&path).unwrap();
+    drop(elf_container);
+
+    let changed_elf_data = std::fs::read(&path).unwrap();
+    let changed_elf = Elf::parse(&changed_elf_data).unwrap();
+    assert_eq!(changed_elf.libraries[0], "libextra.so.1");
+
+    // Break the interpreter, so that the checked write fails.
+    let interp = changed_elf
+        .program_headers
+        .iter()
+        .find(|segment| segment.p_type == PT_INTERP)
+        .unwrap()
+        .file_range();
+    let mut broken_data = changed_elf_data.clone();
+    broken_data[interp.end - 1] = b'x';
+    std::fs::write(&path, &broken_data).unwrap();
+
+    let data_bytes = arwen_core::MappedFile::open(&path).unwrap();
+    let mut elf_container = ElfContainer::parse(&data_bytes).unwrap();
+    elf_container.set_check_on_write(true);
+    elf_container.remove_needed(vec!["libextra.so.1"]).unwrap();
+    assert!(matches!(
+        elf_container.write_to_path(&path),
+        Err(ElfError::Check(_))
+    ));
+    assert_eq!(std::fs::read(&path).unwrap(), broken_data);
+    assert_eq!(std::fs::read_dir(directory.path()).unwrap().count(), 1);
+}
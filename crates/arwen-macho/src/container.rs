@@ -1,8 +1,12 @@
+use std::path::Path;
+
+use arwen_core::{replace_prefix, PrefixReplacement};
 use goblin::{
     container,
     mach::{
         fat::{self, FatArch},
         header::{Header, SIZEOF_HEADER_32, SIZEOF_HEADER_64},
+        load_command::CommandVariant,
         parse_magic_and_ctx, peek, MachO, MultiArch, SingleArch,
     },
 };
@@ -10,26 +14,73 @@ use goblin::{
 use crate::{
     commands::{DlibCommandBuilder, RpathCommandBuilder},
     error::MachoError,
+    info::{
+        arch_name, dylib_info, load_command_info, read_lc_str, DylibInfo, LoadCommandInfo,
+        SliceInfo,
+    },
     patcher::{
         find_dylib_command, find_dylib_id, find_rpath_command, insert_command, remove_load_command,
     },
 };
 
+/// A Mach-O file, or one slice of a fat file.
+///
+/// The edits are made in `data`, the bytes of the Mach-O file. Load commands are
+/// inserted into the header padding, so its size doesn't change.
 pub struct SingleMachO<'a> {
     /// The parsed Mach-O file.
     pub inner: MachO<'a>,
 
-    /// The raw bytes of the Mach-O file.
-    pub data: Vec<u8>,
-
     /// The context of the container.
     /// This is used to determine the architecture of the Mach-O file.
     pub ctx: container::Ctx,
 }
 
 impl SingleMachO<'_> {
+    /// The name of the architecture, e.g. `x86_64`.
+    pub fn arch(&self) -> String {
+        arch_name(&self.inner.header)
+    }
+
+    /// The rpaths of the Mach-O file, in the order of their load commands.
+    pub fn rpaths(&self, data: &[u8]) -> Vec<String> {
+        self.inner
+            .load_commands
+            .iter()
+            .filter_map(|command| match &command.command {
+                CommandVariant::Rpath(rpath) => read_lc_str(data, command, rpath.path),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The dynamic libraries loaded by the Mach-O file, with how they are loaded.
+    pub fn libs(&self, data: &[u8]) -> Vec<DylibInfo> {
+        self.inner
+            .load_commands
+            .iter()
+            .filter_map(|command| dylib_info(data, command))
+            .collect()
+    }
+
+    /// The install id of the Mach-O file, if it is a shared library.
+    pub fn install_id(&self, data: &[u8]) -> Option<String> {
+        let (load_command, id_dylib) = find_dylib_id(&self.inner.load_commands)?;
+        read_lc_str(data, load_command, id_dylib.dylib.name)
+    }
+
+    /// The load commands of the Mach-O file.
+    pub fn load_commands(&self, data: &[u8]) -> Vec<LoadCommandInfo> {
+        self.inner
+            .load_commands
+            .iter()
+            .enumerate()
+            .map(|(index, command)| load_command_info(data, index, command))
+            .collect()
+    }
+
     /// Adds a new rpath to the Mach-O file.
-    pub fn add_rpath(&mut self, new_rpath: &str) -> Result<(), MachoError> {
+    pub fn add_rpath(&self, data: &mut [u8], new_rpath: &str) -> Result<(), MachoError> {
         let mut header = HeaderContainer::new(self.inner.header, self.ctx);
 
         let (new_rpath, new_rpath_command_buffer) =
@@ -38,7 +89,7 @@ impl SingleMachO<'_> {
         let offset_size = header.size() + header.inner.sizeofcmds as usize;
 
         insert_command(
-            &mut self.data,
+            data,
             &mut header,
             offset_size,
             new_rpath.cmdsize,
@@ -49,7 +100,12 @@ impl SingleMachO<'_> {
     }
 
     /// Changes the rpath of the Mach-O file.
-    pub fn change_rpath(&mut self, old_rpath: &str, new_rpath: &str) -> Result<(), MachoError> {
+    pub fn change_rpath(
+        &self,
+        data: &mut [u8],
+        old_rpath: &str,
+        new_rpath: &str,
+    ) -> Result<(), MachoError> {
         let mut header = HeaderContainer::new(self.inner.header, self.ctx);
 
         let old_rpath_index = self
@@ -64,13 +120,13 @@ impl SingleMachO<'_> {
             find_rpath_command(&self.inner.load_commands, old_rpath_index)
                 .ok_or(MachoError::RpathMissing(old_rpath.to_string()))?;
 
-        remove_load_command(&mut self.data, &mut header, load_command)?;
+        remove_load_command(data, &mut header, load_command)?;
 
         let (new_rpath, new_rpath_command_buffer) =
             RpathCommandBuilder::new(new_rpath, self.ctx).build()?;
 
         insert_command(
-            &mut self.data,
+            data,
             &mut header,
             load_command.offset,
             new_rpath.cmdsize,
@@ -81,7 +137,7 @@ impl SingleMachO<'_> {
     }
 
     /// Removes an rpath from the Mach-O file.
-    pub fn remove_rpath(&mut self, old_rpath: &str) -> Result<(), MachoError> {
+    pub fn remove_rpath(&self, data: &mut [u8], old_rpath: &str) -> Result<(), MachoError> {
         let mut header = HeaderContainer::new(self.inner.header, self.ctx);
 
         let old_rpath_index = self
@@ -96,26 +152,26 @@ impl SingleMachO<'_> {
             find_rpath_command(&self.inner.load_commands, old_rpath_index)
                 .ok_or(MachoError::RpathMissing(old_rpath.to_string()))?;
 
-        remove_load_command(&mut self.data, &mut header, load_command)?;
+        remove_load_command(data, &mut header, load_command)?;
 
         Ok(())
     }
 
     /// Changes the install id of the Mach-O file.
-    pub fn change_install_id(&mut self, new_id: &str) -> Result<(), MachoError> {
+    pub fn change_install_id(&self, data: &mut [u8], new_id: &str) -> Result<(), MachoError> {
         let mut header = HeaderContainer::new(self.inner.header, self.ctx);
 
         // now based on the index, we need to find the RpathCommand from the load commands
         let (load_command, old_dylib) =
             find_dylib_id(&self.inner.load_commands).ok_or(MachoError::DylibIdMissing)?;
 
-        remove_load_command(&mut self.data, &mut header, load_command)?;
+        remove_load_command(data, &mut header, load_command)?;
 
         let (new_dylib, new_dylib_command_buffer) =
             DlibCommandBuilder::new(new_id, *old_dylib, self.ctx).build()?;
 
         insert_command(
-            &mut self.data,
+            data,
             &mut header,
             load_command.offset,
             new_dylib.cmdsize,
@@ -127,7 +183,8 @@ impl SingleMachO<'_> {
 
     /// Changes the install name of the Mach-O file.
     pub fn change_install_name(
-        &mut self,
+        &self,
+        data: &mut [u8],
         old_name: &str,
         new_name: &str,
     ) -> Result<(), MachoError> {
@@ -147,13 +204,13 @@ impl SingleMachO<'_> {
             find_dylib_command(&self.inner.load_commands, old_dylib - 1)
                 .ok_or(MachoError::DylibNameMissing(old_name.to_string()))?;
 
-        remove_load_command(&mut self.data, &mut header, load_command)?;
+        remove_load_command(data, &mut header, load_command)?;
 
         let (new_dylib, new_dylib_command_buffer) =
             DlibCommandBuilder::new(new_name, *old_dylib, self.ctx).build()?;
 
         insert_command(
-            &mut self.data,
+            data,
             &mut header,
             load_command.offset,
             new_dylib.cmdsize,
@@ -173,9 +230,6 @@ pub struct FatMacho<'a> {
 pub struct FatMachoContainer<'a> {
     /// The parsed Mach-O file.
     pub archs: Vec<FatMacho<'a>>,
-
-    /// Data of all the Mach-O files
-    pub data: Vec<u8>,
 }
 
 #[allow(clippy::large_enum_variant)]
@@ -188,148 +242,151 @@ pub struct MachoContainer<'a> {
     /// The constructed Mach-O file.
     pub inner: MachoType<'a>,
 
-    /// The raw bytes of the Mach-O file.
+    /// The raw bytes of the Mach-O file, the only copy that is edited.
     pub data: Vec<u8>,
 }
 
 impl MachoContainer<'_> {
     pub fn add_rpath(&mut self, new_rpath: &str) -> Result<(), MachoError> {
-        match &mut self.inner {
-            MachoType::SingleArch(single) => {
-                single.add_rpath(new_rpath)?;
-
-                // save back changed data
-                // TODO: think how to overcome cloning again
-                self.data = single.data.clone();
-            }
-            MachoType::Fat(fat) => {
-                for macho in &mut fat.archs {
-                    macho.inner.add_rpath(new_rpath)?;
-
-                    // save back changed data
-                    // by writing one piece of macho back into the archive
+        self.edit_each(|macho, data| macho.add_rpath(data, new_rpath))
+    }
 
-                    let arch = macho.arch;
-                    self.data.splice(
-                        arch.offset as usize..arch.offset as usize + arch.size as usize,
-                        macho.inner.data.clone(),
-                    );
-                }
-            }
-        }
+    pub fn change_rpath(&mut self, old_rpath: &str, new_rpath: &str) -> Result<(), MachoError> {
+        self.edit_each(|macho, data| macho.change_rpath(data, old_rpath, new_rpath))
+    }
 
-        Ok(())
+    pub fn remove_rpath(&mut self, old_rpath: &str) -> Result<(), MachoError> {
+        self.edit_each(|macho, data| macho.remove_rpath(data, old_rpath))
     }
 
-    pub fn change_rpath(&mut self, old_rpath: &str, new_rpath: &str) -> Result<(), MachoError> {
-        match &mut self.inner {
-            MachoType::SingleArch(single) => {
-                single.change_rpath(old_rpath, new_rpath)?;
+    pub fn change_install_id(&mut self, new_id: &str) -> Result<(), MachoError> {
+        self.edit_each(|macho, data| macho.change_install_id(data, new_id))
+    }
 
-                // save back changed data
-                // TODO: think how to overcome cloning again
-                self.data = single.data.clone();
-            }
-            MachoType::Fat(fat) => {
-                for macho in &mut fat.archs {
-                    macho.inner.change_rpath(old_rpath, new_rpath)?;
+    pub fn change_install_name(
+        &mut self,
+        old_name: &str,
+        new_name: &str,
+    ) -> Result<(), MachoError> {
+        self.edit_each(|macho, data| macho.change_install_name(data, old_name, new_name))
+    }
 
-                    // save back changed data
-                    // by writing one piece of macho back into the archive
-                    let arch = macho.arch;
-                    self.data.splice(
-                        arch.offset as usize..arch.offset as usize + arch.size as usize,
-                        macho.inner.data.clone(),
-                    );
-                }
-            }
-        }
+    /// The rpaths of every slice.
+    pub fn rpaths(&self) -> Result<Vec<SliceInfo<Vec<String>>>, MachoError> {
+        self.read_each(|macho, data| macho.rpaths(data))
+    }
 
-        Ok(())
+    /// The dynamic libraries loaded by every slice.
+    pub fn libs(&self) -> Result<Vec<SliceInfo<Vec<DylibInfo>>>, MachoError> {
+        self.read_each(|macho, data| macho.libs(data))
     }
 
-    pub fn remove_rpath(&mut self, old_rpath: &str) -> Result<(), MachoError> {
-        match &mut self.inner {
-            MachoType::SingleArch(single) => {
-                single.remove_rpath(old_rpath)?;
+    /// The install id of every slice, `None` for slices that aren't shared libraries.
+    pub fn install_id(&self) -> Result<Vec<SliceInfo<Option<String>>>, MachoError> {
+        self.read_each(|macho, data| macho.install_id(data))
+    }
 
-                // save back changed data
-                // TODO: think how to overcome cloning again
-                self.data = single.data.clone();
-            }
-            MachoType::Fat(fat) => {
-                for macho in &mut fat.archs {
-                    macho.inner.remove_rpath(old_rpath)?;
+    /// The load commands of every slice.
+    pub fn load_commands(&self) -> Result<Vec<SliceInfo<Vec<LoadCommandInfo>>>, MachoError> {
+        self.read_each(|macho, data| macho.load_commands(data))
+    }
 
-                    // save back changed data
-                    // by writing one piece of macho back into the archive
+    /// Read a value from every Mach-O file, in its slice of `data` for fat files.
+    ///
+    /// Every file is parsed again from `data`, so the values include earlier edits.
+    fn read_each<T>(
+        &self,
+        read: impl Fn(&SingleMachO, &[u8]) -> T,
+    ) -> Result<Vec<SliceInfo<T>>, MachoError> {
+        let read_slice = |ctx, data: &[u8]| -> Result<SliceInfo<T>, MachoError> {
+            let macho = SingleMachO {
+                inner: MachO::parse(data, 0)?,
+                ctx,
+            };
+            Ok(SliceInfo {
+                arch: macho.arch(),
+                value: read(&macho, data),
+            })
+        };
+        match &self.inner {
+            MachoType::SingleArch(single) => Ok(vec![read_slice(single.ctx, &self.data)?]),
+            MachoType::Fat(fat) => fat
+                .archs
+                .iter()
+                .map(|macho| {
                     let arch = macho.arch;
-                    self.data.splice(
-                        arch.offset as usize..arch.offset as usize + arch.size as usize,
-                        macho.inner.data.clone(),
-                    );
-                }
-            }
+                    let range = arch.offset as usize..arch.offset as usize + arch.size as usize;
+                    read_slice(macho.inner.ctx, &self.data[range])
+                })
+                .collect(),
         }
-        Ok(())
     }
 
-    pub fn change_install_id(&mut self, new_id: &str) -> Result<(), MachoError> {
-        match &mut self.inner {
-            MachoType::SingleArch(single) => {
-                single.change_install_id(new_id)?;
-
-                // save back changed data
-                // TODO: think how to overcome cloning again
-                self.data = single.data.clone();
-            }
+    /// Apply an edit to every Mach-O file, in its slice of `data` for fat files.
+    fn edit_each(
+        &mut self,
+        mut edit: impl FnMut(&SingleMachO, &mut [u8]) -> Result<(), MachoError>,
+    ) -> Result<(), MachoError> {
+        match &self.inner {
+            MachoType::SingleArch(single) => edit(single, &mut self.data),
             MachoType::Fat(fat) => {
-                for macho in &mut fat.archs {
-                    macho.inner.change_install_id(new_id)?;
-
-                    // save back changed data
-                    // by writing one piece of macho back into the archive
-
+                for macho in &fat.archs {
                     let arch = macho.arch;
-                    self.data.splice(
-                        arch.offset as usize..arch.offset as usize + arch.size as usize,
-                        macho.inner.data.clone(),
-                    );
+                    let range = arch.offset as usize..arch.offset as usize + arch.size as usize;
+                    edit(&macho.inner, &mut self.data[range])?;
                 }
+                Ok(())
             }
         }
+    }
+
+    /// Write the file to an output stream.
+    pub fn write<W: std::io::Write>(&self, mut w: W) -> Result<(), MachoError> {
+        w.write_all(&self.data)?;
         Ok(())
     }
 
-    pub fn change_install_name(
-        &mut self,
-        old_name: &str,
-        new_name: &str,
-    ) -> Result<(), MachoError> {
-        match &mut self.inner {
-            MachoType::SingleArch(single) => {
-                single.change_install_name(old_name, new_name)?;
+    /// Write the file to a path.
+    ///
+    /// The file is written to a temporary file that replaces `path` once it is
+    /// complete, so `path` is left untouched if writing fails, and can be the file
+    /// that was read.
+    pub fn write_to_path(&self, path: &Path) -> Result<(), MachoError> {
+        arwen_core::write_atomically(path, |w| self.write(w))
+    }
 
-                // save back changed data
-                // TODO: think how to overcome cloning again
-                self.data = single.data.clone();
+    /// Replaces a prefix embedded in NUL terminated strings, in place.
+    ///
+    /// The offsets of the replacements are relative to the start of the file,
+    /// also for fat files. Signed files need to be signed again afterwards.
+    pub fn replace_prefix(
+        &mut self,
+        old_prefix: &str,
+        new_prefix: &str,
+    ) -> Result<Vec<PrefixReplacement>, MachoError> {
+        let mut replacements = Vec::new();
+        match &self.inner {
+            MachoType::SingleArch(_) => {
+                replacements =
+                    replace_prefix(&mut self.data, old_prefix.as_bytes(), new_prefix.as_bytes())?;
             }
             MachoType::Fat(fat) => {
-                for macho in &mut fat.archs {
-                    macho.inner.change_install_name(old_name, new_name)?;
-
-                    // save back changed data
-                    // by writing one piece of macho back into the archive
-
+                for macho in &fat.archs {
                     let arch = macho.arch;
-                    self.data.splice(
-                        arch.offset as usize..arch.offset as usize + arch.size as usize,
-                        macho.inner.data.clone(),
-                    );
+                    let range = arch.offset as usize..arch.offset as usize + arch.size as usize;
+                    let slice_replacements = replace_prefix(
+                        &mut self.data[range],
+                        old_prefix.as_bytes(),
+                        new_prefix.as_bytes(),
+                    )?;
+                    replacements.extend(slice_replacements.into_iter().map(|mut replacement| {
+                        replacement.offset += u64::from(arch.offset);
+                        replacement
+                    }));
                 }
             }
         }
-        Ok(())
+        Ok(replacements)
     }
 }
 
@@ -369,11 +426,7 @@ impl<'a> MachoContainer<'a> {
                         return Err(MachoError::UnknownEndian);
                     };
 
-                    let single_mach = SingleMachO {
-                        inner: mach_o,
-                        data: data.to_vec(),
-                        ctx,
-                    };
+                    let single_mach = SingleMachO { inner: mach_o, ctx };
 
                     let fat_macho = FatMacho {
                         inner: single_mach,
@@ -384,10 +437,7 @@ impl<'a> MachoContainer<'a> {
                     machos.push(fat_macho);
                 }
 
-                let container = FatMachoContainer {
-                    archs: machos,
-                    data: bytes_of_file.to_vec(),
-                };
+                let container = FatMachoContainer { archs: machos };
                 Ok(MachoContainer {
                     inner: MachoType::Fat(container),
                     data: bytes_of_file.to_vec(),
@@ -403,11 +453,7 @@ impl<'a> MachoContainer<'a> {
                     return Err(MachoError::UnknownEndian);
                 };
 
-                let mach_type = MachoType::SingleArch(SingleMachO {
-                    inner: mach_o,
-                    data: bytes_of_file.to_vec(),
-                    ctx,
-                });
+                let mach_type = MachoType::SingleArch(SingleMachO { inner: mach_o, ctx });
 
                 Ok(MachoContainer {
                     inner: mach_type,
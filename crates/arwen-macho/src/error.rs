@@ -32,4 +32,10 @@ pub enum MachoError {
 
     #[error("codesign section is missing")]
     CodesignMissing,
+
+    #[error("I/O error while writing the file: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("error while replacing prefix: {0}")]
+    Prefix(#[from] arwen_core::PrefixError),
 }
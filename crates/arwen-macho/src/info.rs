@@ -0,0 +1,161 @@
+use goblin::mach::{
+    cputype::{get_arch_name_from_types, CPU_SUBTYPE_MASK},
+    header::Header,
+    load_command::{CommandVariant, LcStr, LoadCommand},
+};
+use serde::Serialize;
+
+/// A value read from one slice of a Mach-O file.
+///
+/// Files with a single architecture have one slice.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SliceInfo<T> {
+    /// The architecture of the slice, e.g. `x86_64`.
+    pub arch: String,
+    /// The value read from the slice.
+    pub value: T,
+}
+
+/// How a dynamic library is loaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DylibKind {
+    /// `LC_LOAD_DYLIB`
+    Normal,
+    /// `LC_LOAD_WEAK_DYLIB`, the library may be missing at runtime.
+    Weak,
+    /// `LC_REEXPORT_DYLIB`, the symbols of the library are exported too.
+    Reexport,
+    /// `LC_LOAD_UPWARD_DYLIB`
+    Upward,
+    /// `LC_LAZY_LOAD_DYLIB`
+    Lazy,
+}
+
+impl std::fmt::Display for DylibKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            DylibKind::Normal => "normal",
+            DylibKind::Weak => "weak",
+            DylibKind::Reexport => "reexport",
+            DylibKind::Upward => "upward",
+            DylibKind::Lazy => "lazy",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A dynamic library loaded by a Mach-O file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DylibInfo {
+    /// The install name of the library.
+    pub name: String,
+    /// The load command used to load the library.
+    pub kind: DylibKind,
+    /// The current version of the library, e.g. `1.2.3`.
+    pub current_version: String,
+    /// The compatibility version of the library.
+    pub compatibility_version: String,
+}
+
+/// A load command of a Mach-O file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LoadCommandInfo {
+    /// The index of the load command.
+    pub index: usize,
+    /// The command type, one of the `LC_*` constants.
+    pub cmd: u32,
+    /// The name of the command type, e.g. `LC_RPATH`.
+    pub name: String,
+    /// The size of the command, including its strings.
+    pub cmdsize: usize,
+    /// The offset of the command in its slice.
+    pub offset: usize,
+    /// The path, library or segment name held by the command, if any.
+    pub detail: Option<String>,
+}
+
+/// The name of the architecture in a header, e.g. `arm64`.
+pub(crate) fn arch_name(header: &Header) -> String {
+    get_arch_name_from_types(header.cputype, header.cpusubtype & !CPU_SUBTYPE_MASK)
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("{:#x}/{:#x}", header.cputype, header.cpusubtype))
+}
+
+/// Format a packed `xxxx.yy.zz` version, as used by the dylib commands.
+pub(crate) fn format_version(version: u32) -> String {
+    format!(
+        "{}.{}.{}",
+        version >> 16,
+        (version >> 8) & 0xff,
+        version & 0xff
+    )
+}
+
+/// Read the string at `name`, an offset from the start of `command`.
+pub(crate) fn read_lc_str(data: &[u8], command: &LoadCommand, name: LcStr) -> Option<String> {
+    let start = command.offset + name as usize;
+    let end = command.offset + command.command.cmdsize();
+    let bytes = data.get(start..end)?;
+    let len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    Some(String::from_utf8_lossy(&bytes[..len]).into_owned())
+}
+
+/// The name of a segment, as stored in its fixed size field.
+fn segment_name(segname: &[u8; 16]) -> String {
+    let len = segname
+        .iter()
+        .position(|&b| b == 0)
+        .unwrap_or(segname.len());
+    String::from_utf8_lossy(&segname[..len]).into_owned()
+}
+
+/// Describe a load command.
+pub(crate) fn load_command_info(
+    data: &[u8],
+    index: usize,
+    command: &LoadCommand,
+) -> LoadCommandInfo {
+    let cmd = command.command.cmd();
+    let detail = match &command.command {
+        CommandVariant::Rpath(rpath) => read_lc_str(data, command, rpath.path),
+        CommandVariant::IdDylib(dylib)
+        | CommandVariant::LoadDylib(dylib)
+        | CommandVariant::LoadWeakDylib(dylib)
+        | CommandVariant::ReexportDylib(dylib)
+        | CommandVariant::LoadUpwardDylib(dylib)
+        | CommandVariant::LazyLoadDylib(dylib) => read_lc_str(data, command, dylib.dylib.name),
+        CommandVariant::LoadDylinker(dylinker)
+        | CommandVariant::IdDylinker(dylinker)
+        | CommandVariant::DyldEnvironment(dylinker) => read_lc_str(data, command, dylinker.name),
+        CommandVariant::Segment32(segment) => Some(segment_name(&segment.segname)),
+        CommandVariant::Segment64(segment) => Some(segment_name(&segment.segname)),
+        _ => None,
+    };
+    LoadCommandInfo {
+        index,
+        cmd,
+        name: goblin::mach::load_command::cmd_to_str(cmd).to_string(),
+        cmdsize: command.command.cmdsize(),
+        offset: command.offset,
+        detail,
+    }
+}
+
+/// Describe a dynamic library loaded by a load command, or `None` for other commands.
+pub(crate) fn dylib_info(data: &[u8], command: &LoadCommand) -> Option<DylibInfo> {
+    let (dylib, kind) = match &command.command {
+        CommandVariant::LoadDylib(dylib) => (dylib, DylibKind::Normal),
+        CommandVariant::LoadWeakDylib(dylib) => (dylib, DylibKind::Weak),
+        CommandVariant::ReexportDylib(dylib) => (dylib, DylibKind::Reexport),
+        CommandVariant::LoadUpwardDylib(dylib) => (dylib, DylibKind::Upward),
+        CommandVariant::LazyLoadDylib(dylib) => (dylib, DylibKind::Lazy),
+        _ => return None,
+    };
+    Some(DylibInfo {
+        name: read_lc_str(data, command, dylib.dylib.name)?,
+        kind,
+        current_version: format_version(dylib.dylib.current_version),
+        compatibility_version: format_version(dylib.dylib.compatibility_version),
+    })
+}
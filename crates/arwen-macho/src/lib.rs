@@ -1,13 +1,16 @@
 //! Mach-O binary patching library.
 //!
 //! This crate provides tools for reading and modifying Mach-O binaries,
-//! including operations on rpaths, install names, and dylib IDs.
+//! including operations on rpaths, install names, and dylib IDs, and for
+//! listing their load commands.
 
 pub mod commands;
 pub mod container;
 pub mod error;
+pub mod info;
 pub mod patcher;
 mod utils;
 
 pub use container::*;
 pub use error::MachoError;
+pub use info::*;
@@ -1,5 +1,3 @@
-use std::vec;
-
 use goblin::mach::load_command::{self, LoadCommand, RpathCommand};
 use goblin::mach::load_command::{CommandVariant::*, DylibCommand};
 
@@ -9,43 +7,33 @@ use crate::{container::HeaderContainer, error::MachoError};
 
 /// Removes a load command from the buffer.
 ///
+/// The load commands after it move up, and the freed space at the end of the
+/// load commands is zeroed, so the size of the buffer doesn't change.
+///
 /// # Arguments
 /// * `buffer` - Mutable byte buffer representing the Mach-O file.
 /// * `header` - Header of the macho. It will be updated after removing the load command.
 /// * `load_command` - Load Command to remove.
 pub fn remove_load_command(
-    buffer: &mut Vec<u8>,
+    buffer: &mut [u8],
     header: &mut HeaderContainer,
     load_command: &LoadCommand,
 ) -> Result<(), MachoError> {
-    // Remove entire command from the buffer
-    let drain_offset = load_command.offset + load_command.command.cmdsize();
-    buffer.drain(load_command.offset..drain_offset);
+    let cmdsize = load_command.command.cmdsize();
+    let commands_end = header.size() + header.inner.sizeofcmds as usize;
+
+    // Move the following commands over the removed one
+    buffer.copy_within(
+        load_command.offset + cmdsize..commands_end,
+        load_command.offset,
+    );
 
     // Update the header
     header.inner.ncmds -= 1;
-    header.inner.sizeofcmds -= load_command.command.cmdsize() as u32;
-
-    // Insert padding after the remaining load commands
-    let padding_offset = header.size() + header.inner.sizeofcmds as usize;
-    let padding_size = load_command.command.cmdsize();
-
-    // Ensure there's enough space for the padding
-    if padding_offset + padding_size > buffer.len() {
-        buffer.resize(padding_offset + padding_size, 0);
-    }
-
-    // Write zero bytes as padding
-    let mut zeroing_buffer = vec![0u8; padding_size];
-    zeroing_buffer.fill(0);
-
-    let tail = buffer.split_off(padding_offset);
-
-    // Extend with the new slice
-    buffer.extend(&zeroing_buffer);
+    header.inner.sizeofcmds -= cmdsize as u32;
 
-    // Add back the tail
-    buffer.extend(tail);
+    // Write zero bytes as padding after the remaining load commands
+    buffer[commands_end - cmdsize..commands_end].fill(0);
 
     buffer.pwrite_with(header.inner, 0, header.ctx)?;
 
@@ -54,6 +42,9 @@ pub fn remove_load_command(
 
 /// Insert a new load command at the given offset.
 ///
+/// The load commands after the offset move down into the header padding, so the
+/// size of the buffer doesn't change.
+///
 /// # Arguments
 /// * `buffer` - Mutable byte buffer representing the Mach-O file.
 /// * `header` - Header of the macho. It will be updated after removing the load command.
@@ -61,29 +52,24 @@ pub fn remove_load_command(
 /// * `new_cmd_size` - Size of the new command.
 /// * `load_command` - Load Command raw data to insert.
 pub fn insert_command(
-    buffer: &mut Vec<u8>,
+    buffer: &mut [u8],
     header: &mut HeaderContainer,
     offset: usize,
     new_cmd_size: u32,
     load_data: Vec<u8>,
 ) -> Result<(), MachoError> {
+    let commands_end = header.size() + header.inner.sizeofcmds as usize;
+
     // update the header
     header.inner.ncmds += 1;
     header.inner.sizeofcmds += new_cmd_size;
 
-    // write new command
-    let tail = buffer.split_off(offset);
-
-    // Extend with the new slice
-    buffer.extend(&load_data);
-
-    // Add back the tail
-    buffer.extend(tail);
-
-    // We need to drain surplus header pad that is present in macho-file
+    // We overwrite surplus header pad that is present in macho-file
     // when compiling with -headerpad_max_install_names
-    let drain_offset = header.size() + header.inner.sizeofcmds as usize + new_cmd_size as usize;
-    buffer.drain(header.size() + header.inner.sizeofcmds as usize..drain_offset);
+    buffer.copy_within(offset..commands_end, offset + load_data.len());
+
+    // write new command
+    buffer[offset..offset + load_data.len()].copy_from_slice(&load_data);
 
     buffer.pwrite_with(header.inner, 0, header.ctx)?;
 
@@ -1,5 +1,8 @@
 use arwen_macho::MachoContainer;
-use goblin::mach::MachO;
+use goblin::mach::{
+    load_command::{LC_LOAD_DYLIB, LC_LOAD_WEAK_DYLIB},
+    MachO,
+};
 use rstest::rstest;
 use std::path::PathBuf;
 
@@ -106,3 +109,169 @@ fn test_change_dylib_name(#[files("../../tests/data/macho/x64/exec/*")] bin_path
     insta::assert_debug_snapshot!(changed_macho.libs);
     insta::assert_snapshot!(macho_container.data.len());
 }
+
+/// This test verifies that a prefix is replaced in thin and fat Mach-O files without
+/// changing their size, and that the offsets are relative to the whole file.
+#[rstest]
+fn test_replace_prefix(
+    #[files("../../tests/data/macho/x64/exec/hello_with_rpath")]
+    #[files("../../tests/data/macho/fat/exec/*")]
+    bin_path: PathBuf,
+) {
+    let data_bytes = std::fs::read(&bin_path).unwrap();
+
+    let mut macho_container = MachoContainer::parse(&data_bytes).unwrap();
+
+    let replacements = macho_container.replace_prefix("path_graf", "/opt").unwrap();
+
+    assert!(!replacements.is_empty());
+    assert_eq!(macho_container.data.len(), data_bytes.len());
+    for replacement in &replacements {
+        assert_eq!(replacement.new, "/opt");
+        let offset = replacement.offset as usize;
+        assert_eq!(&macho_container.data[offset..offset + 9], b"/opt\0\0\0\0\0");
+    }
+    let changed_data = MachoContainer::parse(&macho_container.data).unwrap();
+    let rpaths = match &changed_data.inner {
+        arwen_macho::MachoType::SingleArch(single) => single.inner.rpaths.clone(),
+        arwen_macho::MachoType::Fat(fat) => fat
+            .archs
+            .iter()
+            .flat_map(|arch| arch.inner.inner.rpaths.clone())
+            .collect(),
+    };
+    assert_eq!(
+        rpaths.iter().filter(|rpath| **rpath == "/opt").count(),
+        replacements.len()
+    );
+    assert!(!rpaths.contains(&"path_graf"));
+}
+
+/// This test verifies that a memory-mapped file can be edited and written back over
+/// itself, with every slice of a fat file changed in place.
+#[rstest]
+fn test_write_mapped_file(
+    #[files("../../tests/data/macho/x64/exec/hello_with_rpath")]
+    #[files("../../tests/data/macho/fat/exec/*")]
+    bin_path: PathBuf,
+) {
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join(bin_path.file_name().unwrap());
+    std::fs::copy(&bin_path, &path).unwrap();
+    let original_size = std::fs::metadata(&path).unwrap().len();
+
+    let data_bytes = arwen_core::MappedFile::open(&path).unwrap();
+    let mut macho_container = MachoContainer::parse(&data_bytes).unwrap();
+    macho_container.add_rpath("/opt/new/rpath").unwrap();
+    macho_container.write_to_path(&path).unwrap();
+
+    let changed_bytes = std::fs::read(&path).unwrap();
+    assert_eq!(changed_bytes.len() as u64, original_size);
+    let changed_data = MachoContainer::parse(&changed_bytes).unwrap();
+    let slices = match &changed_data.inner {
+        arwen_macho::MachoType::SingleArch(single) => vec![single],
+        arwen_macho::MachoType::Fat(fat) => fat.archs.iter().map(|arch| &arch.inner).collect(),
+    };
+    for slice in slices {
+        assert!(slice.inner.rpaths.contains(&"/opt/new/rpath"));
+        assert!(slice.inner.rpaths.contains(&"path_graf"));
+    }
+}
+
+/// This test verifies that the rpaths, libraries, install id and load commands are
+/// read from every slice, and match what goblin parses.
+#[rstest]
+fn test_read_slices(
+    #[files("../../tests/data/macho/x64/exec/hello_with_rpath")]
+    #[files("../../tests/data/macho/x64/libs/*")]
+    #[files("../../tests/data/macho/fat/*/*")]
+    bin_path: PathBuf,
+) {
+    let data_bytes = std::fs::read(&bin_path).unwrap();
+
+    let macho_container = MachoContainer::parse(&data_bytes).unwrap();
+
+    let slices = match &macho_container.inner {
+        arwen_macho::MachoType::SingleArch(single) => vec![(single, &data_bytes[..])],
+        arwen_macho::MachoType::Fat(fat) => fat
+            .archs
+            .iter()
+            .map(|arch| (&arch.inner, arch.arch.slice(&data_bytes)))
+            .collect(),
+    };
+    let rpaths = macho_container.rpaths().unwrap();
+    let libs = macho_container.libs().unwrap();
+    let install_id = macho_container.install_id().unwrap();
+    let load_commands = macho_container.load_commands().unwrap();
+    assert_eq!(rpaths.len(), slices.len());
+    for (index, (slice, data)) in slices.into_iter().enumerate() {
+        let macho = MachO::parse(data, 0).unwrap();
+        assert_eq!(rpaths[index].arch, slice.arch());
+        assert_eq!(rpaths[index].value, macho.rpaths);
+        let names = libs[index]
+            .value
+            .iter()
+            .map(|lib| lib.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, macho.libs[1..]);
+        assert_eq!(install_id[index].value.as_deref(), macho.name);
+        assert_eq!(load_commands[index].value.len(), macho.load_commands.len());
+        for (info, command) in load_commands[index].value.iter().zip(&macho.load_commands) {
+            assert_eq!(info.cmd, command.command.cmd());
+            assert_eq!(info.offset, command.offset);
+        }
+    }
+}
+
+/// This test verifies that the libraries are listed with their load kind and versions.
+#[rstest]
+fn test_read_libs_kind(
+    #[files("../../tests/data/macho/x64/exec/hello_with_rpath")] bin_path: PathBuf,
+) {
+    let mut data_bytes = std::fs::read(&bin_path).unwrap();
+
+    let macho_container = MachoContainer::parse(&data_bytes).unwrap();
+    let libs = macho_container.libs().unwrap();
+    assert_eq!(libs.len(), 1);
+    let lib = &libs[0].value[0];
+    assert_eq!(lib.name, "/usr/lib/libSystem.B.dylib");
+    assert_eq!(lib.kind, arwen_macho::DylibKind::Normal);
+    assert_eq!(lib.current_version, "1319.100.3");
+    assert_eq!(lib.compatibility_version, "1.0.0");
+
+    // Turn the LC_LOAD_DYLIB into an LC_LOAD_WEAK_DYLIB.
+    let offset = MachO::parse(&data_bytes, 0)
+        .unwrap()
+        .load_commands
+        .iter()
+        .find(|command| command.command.cmd() == LC_LOAD_DYLIB)
+        .unwrap()
+        .offset;
+    data_bytes[offset..offset + 4].copy_from_slice(&LC_LOAD_WEAK_DYLIB.to_le_bytes());
+
+    let macho_container = MachoContainer::parse(&data_bytes).unwrap();
+    let libs = macho_container.libs().unwrap();
+    assert_eq!(libs[0].value[0].name, "/usr/lib/libSystem.B.dylib");
+    assert_eq!(libs[0].value[0].kind, arwen_macho::DylibKind::Weak);
+}
+
+/// This test verifies that the read methods see the edits made before them.
+#[rstest]
+fn test_read_after_edit(
+    #[files("../../tests/data/macho/x64/exec/hello_with_rpath")] bin_path: PathBuf,
+) {
+    let data_bytes = std::fs::read(&bin_path).unwrap();
+
+    let mut macho_container = MachoContainer::parse(&data_bytes).unwrap();
+    let load_command_count = macho_container.load_commands().unwrap()[0].value.len();
+
+    macho_container.remove_rpath("path_graf").unwrap();
+    assert_eq!(
+        macho_container.rpaths().unwrap()[0].value,
+        Vec::<String>::new()
+    );
+    assert_eq!(
+        macho_container.load_commands().unwrap()[0].value.len(),
+        load_command_count - 1
+    );
+}
@@ -1,8 +1,12 @@
 use std::path::PathBuf;
 
+use arwen_elf::NeededPosition;
 use clap::Parser;
 
 /// Add dependencies on a dynamic library  DT_NEEDED
+///
+/// The new entries are added before the existing ones, unless a position is given.
+/// The order of DT_NEEDED entries is the order in which symbols are looked up.
 #[derive(Parser, Debug)]
 pub struct Args {
     /// Path to the file to change
@@ -10,19 +14,34 @@ pub struct Args {
 
     /// New DT_NEEDED to add
     pub dt_needed: Vec<String>,
+
+    /// Insert before the DT_NEEDED entry at this index
+    #[arg(long, conflicts_with_all = ["before", "after"])]
+    pub position: Option<usize>,
+
+    /// Insert right before this library
+    #[arg(long, conflicts_with = "after")]
+    pub before: Option<String>,
+
+    /// Insert right after this library
+    #[arg(long)]
+    pub after: Option<String>,
 }
 
 pub fn execute(args: Args) -> Result<(), arwen_elf::ElfError> {
-    let bytes_of_file = std::fs::read(&args.path_to_binary).unwrap();
+    let bytes_of_file = arwen_core::MappedFile::open(&args.path_to_binary)?;
 
     let mut elf = arwen_elf::ElfContainer::parse(&bytes_of_file)?;
 
-    elf.add_needed(args.dt_needed)?;
-
-    let output_file =
-        std::fs::File::create(format!("{}", args.path_to_binary.to_string_lossy())).unwrap();
+    let position = match (args.position, args.before, args.after) {
+        (Some(index), _, _) => NeededPosition::Index(index),
+        (_, Some(library), _) => NeededPosition::Before(library.into_bytes()),
+        (_, _, Some(library)) => NeededPosition::After(library.into_bytes()),
+        _ => NeededPosition::default(),
+    };
+    elf.add_needed_at(args.dt_needed, position)?;
 
-    elf.write(&output_file)?;
+    elf.write_to_path(&args.path_to_binary)?;
 
     Ok(())
 }
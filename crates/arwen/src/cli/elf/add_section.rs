@@ -0,0 +1,77 @@
+use std::{error::Error, path::PathBuf};
+
+use clap::{Parser, ValueEnum};
+use object::elf;
+
+/// Parse a single key-value pair
+fn parse_key_val(s: &str) -> Result<(String, PathBuf), Box<dyn Error + Send + Sync + 'static>> {
+    let pos = s
+        .find('=')
+        .ok_or_else(|| format!("invalid KEY=value: no `=` found in `{s}`"))?;
+    let key = s[..pos].to_string();
+    let value = PathBuf::from(&s[pos + 1..]);
+    Ok((key, value))
+}
+
+/// Section flags that can be set on a new section
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum SectionFlag {
+    /// The section is writable at runtime (`SHF_WRITE`)
+    Write,
+    /// The section contains executable code (`SHF_EXECINSTR`)
+    Exec,
+    /// The section contents may be merged (`SHF_MERGE`)
+    Merge,
+    /// The section contains NUL-terminated strings (`SHF_STRINGS`)
+    Strings,
+}
+
+impl SectionFlag {
+    fn bits(self) -> u64 {
+        let bits = match self {
+            SectionFlag::Write => elf::SHF_WRITE,
+            SectionFlag::Exec => elf::SHF_EXECINSTR,
+            SectionFlag::Merge => elf::SHF_MERGE,
+            SectionFlag::Strings => elf::SHF_STRINGS,
+        };
+        u64::from(bits)
+    }
+}
+
+/// Add a section to the ELF file with the contents of a file
+///
+/// Allocated sections are loaded into memory by a new PT_LOAD segment,
+/// other sections are appended to the end of the file.
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Path to the file to change
+    pub path_to_binary: PathBuf,
+
+    /// The section to add, as NAME=FILE
+    #[arg(value_parser = parse_key_val)]
+    pub section: (String, PathBuf),
+
+    /// Load the section into memory when the binary runs
+    #[arg(long)]
+    pub alloc: bool,
+
+    /// Additional section flags
+    #[arg(long, value_delimiter = ',')]
+    pub flags: Vec<SectionFlag>,
+}
+
+pub fn execute(args: Args) -> Result<(), arwen_elf::ElfError> {
+    let bytes_of_file = arwen_core::MappedFile::open(&args.path_to_binary)?;
+
+    let mut elf = arwen_elf::ElfContainer::parse(&bytes_of_file)?;
+
+    let (name, path) = args.section;
+    let data = std::fs::read(path)?;
+    let flags = args.flags.iter().fold(0, |flags, flag| flags | flag.bits());
+
+    elf.add_section(&name, flags, data, args.alloc)?;
+
+    elf.write_to_path(&args.path_to_binary)?;
+
+    Ok(())
+}
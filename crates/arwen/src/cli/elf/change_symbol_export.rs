@@ -0,0 +1,69 @@
+use std::path::PathBuf;
+
+use arwen_elf::ExportChange;
+use clap::{ArgGroup, Parser};
+
+use super::read_names_file;
+
+/// Stop exporting dynamic symbols, like `objcopy --localize-symbols` but for `.dynsym`
+///
+/// Symbols may be given as names or as patterns with the `*` and `?` wildcards. Only
+/// symbols defined by the file are changed; imports are left alone.
+#[derive(Parser, Debug)]
+#[command(group(ArgGroup::new("change").required(true).args(["localize", "hide", "weaken", "unexport"])))]
+pub struct Args {
+    /// Path to the file to change
+    pub path_to_binary: PathBuf,
+
+    /// Symbols to change
+    pub symbols: Vec<String>,
+
+    /// Read additional symbols to change from a file, one per line
+    #[arg(long)]
+    pub from_file: Option<PathBuf>,
+
+    /// Make the symbols local
+    #[arg(long)]
+    pub localize: bool,
+
+    /// Make the symbols hidden
+    #[arg(long)]
+    pub hide: bool,
+
+    /// Make global symbols weak
+    #[arg(long)]
+    pub weaken: bool,
+
+    /// Remove the symbols from the dynamic symbol table, or make them local if
+    /// relocations still refer to them
+    #[arg(long)]
+    pub unexport: bool,
+}
+
+pub fn execute(args: Args) -> Result<(), arwen_elf::ElfError> {
+    let bytes_of_file = arwen_core::MappedFile::open(&args.path_to_binary)?;
+
+    let mut elf = arwen_elf::ElfContainer::parse(&bytes_of_file)?;
+
+    let mut symbols = args.symbols;
+    if let Some(path) = &args.from_file {
+        symbols.extend(read_names_file(path)?);
+    }
+
+    let change = if args.localize {
+        ExportChange::Localize
+    } else if args.hide {
+        ExportChange::Hide
+    } else if args.weaken {
+        ExportChange::Weaken
+    } else {
+        ExportChange::Unexport
+    };
+    for name in elf.change_symbol_export(&symbols, change) {
+        println!("{change} {name}");
+    }
+
+    elf.write_to_path(&args.path_to_binary)?;
+
+    Ok(())
+}
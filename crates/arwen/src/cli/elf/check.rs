@@ -0,0 +1,47 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+/// Check the layout of the file, e.g. after patching it.
+///
+/// Reports overlapping or misaligned LOAD segments, an unmapped PHDR segment, dynamic
+/// entries pointing outside the mapped memory, dynamic entries that disagree with the
+/// section headers, and an unterminated interpreter. With `--page-size`, also checks
+/// that the file loads on a system with that page size. Exits with status 1 if any
+/// problem is found.
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Path to the file to check
+    pub path_to_binary: PathBuf,
+
+    /// Print the problems as JSON
+    #[arg(long)]
+    pub json: bool,
+
+    /// Also check that the file loads with pages of this many bytes, e.g. 16384
+    #[arg(long)]
+    pub page_size: Option<u64>,
+}
+
+pub fn execute(args: Args) -> Result<(), arwen_elf::ElfError> {
+    let bytes_of_file = arwen_core::MappedFile::open(&args.path_to_binary)?;
+
+    let problems = match args.page_size {
+        Some(page_size) => arwen_elf::check_page_size(&bytes_of_file, page_size),
+        None => arwen_elf::check(&bytes_of_file),
+    };
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&problems).unwrap());
+    } else {
+        for problem in &problems {
+            println!("{problem}");
+        }
+    }
+
+    if !problems.is_empty() {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,28 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+/// Clear feature bits of the GNU property notes, e.g. IBT or SHSTK on x86 and BTI or PAC on AArch64
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Path to the file to change
+    pub path_to_binary: PathBuf,
+
+    /// Features to clear
+    #[arg(required = true)]
+    pub features: Vec<String>,
+}
+
+pub fn execute(args: Args) -> Result<(), arwen_elf::ElfError> {
+    let bytes_of_file = arwen_core::MappedFile::open(&args.path_to_binary)?;
+
+    let mut elf = arwen_elf::ElfContainer::parse(&bytes_of_file)?;
+
+    for feature in &args.features {
+        elf.clear_gnu_property(feature)?;
+    }
+
+    elf.write_to_path(&args.path_to_binary)?;
+
+    Ok(())
+}
@@ -13,16 +13,13 @@ pub struct Args {
 }
 
 pub fn execute(args: Args) -> Result<(), arwen_elf::ElfError> {
-    let bytes_of_file = std::fs::read(&args.path_to_binary).unwrap();
+    let bytes_of_file = arwen_core::MappedFile::open(&args.path_to_binary)?;
 
     let mut elf = arwen_elf::ElfContainer::parse(&bytes_of_file)?;
 
     elf.clear_version_symbol(&args.symbol)?;
 
-    let output_file =
-        std::fs::File::create(format!("{}", args.path_to_binary.to_string_lossy())).unwrap();
-
-    elf.write(&output_file)?;
+    elf.write_to_path(&args.path_to_binary)?;
 
     Ok(())
 }
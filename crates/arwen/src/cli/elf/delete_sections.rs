@@ -0,0 +1,38 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use super::read_names_file;
+
+/// Delete sections from the ELF file
+///
+/// Sections referenced by the dynamic section or by a program header can't be deleted.
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Path to the file to change
+    pub path_to_binary: PathBuf,
+
+    /// Sections to delete
+    pub sections: Vec<String>,
+
+    /// Read additional sections to delete from a file, one per line
+    #[arg(long)]
+    pub from_file: Option<PathBuf>,
+}
+
+pub fn execute(args: Args) -> Result<(), arwen_elf::ElfError> {
+    let bytes_of_file = arwen_core::MappedFile::open(&args.path_to_binary)?;
+
+    let mut elf = arwen_elf::ElfContainer::parse(&bytes_of_file)?;
+
+    let mut sections = args.sections;
+    if let Some(path) = &args.from_file {
+        sections.extend(read_names_file(path)?);
+    }
+
+    elf.delete_sections(sections)?;
+
+    elf.write_to_path(&args.path_to_binary)?;
+
+    Ok(())
+}
@@ -0,0 +1,50 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use super::read_names_file;
+
+/// Delete symbols from the symbol table and the dynamic symbol table
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Path to the file to change
+    pub path_to_binary: PathBuf,
+
+    /// Symbols to delete
+    pub symbols: Vec<String>,
+
+    /// Read additional symbols to delete from a file, one per line
+    #[arg(long)]
+    pub from_file: Option<PathBuf>,
+
+    /// Only delete symbols from the symbol table
+    #[arg(long, conflicts_with = "dynamic_only")]
+    pub static_only: bool,
+
+    /// Only delete symbols from the dynamic symbol table
+    #[arg(long)]
+    pub dynamic_only: bool,
+}
+
+pub fn execute(args: Args) -> Result<(), arwen_elf::ElfError> {
+    let bytes_of_file = arwen_core::MappedFile::open(&args.path_to_binary)?;
+
+    let mut elf = arwen_elf::ElfContainer::parse(&bytes_of_file)?;
+
+    let mut symbols = args.symbols;
+    if let Some(path) = &args.from_file {
+        symbols.extend(read_names_file(path)?);
+    }
+
+    if args.static_only {
+        elf.delete_static_symbols(symbols)?;
+    } else if args.dynamic_only {
+        elf.delete_dynamic_symbols(symbols)?;
+    } else {
+        elf.delete_symbols(symbols)?;
+    }
+
+    elf.write_to_path(&args.path_to_binary)?;
+
+    Ok(())
+}
@@ -0,0 +1,37 @@
+use std::{error::Error, path::PathBuf};
+
+use clap::Parser;
+
+/// Parse a single key-value pair
+fn parse_key_val(s: &str) -> Result<(String, PathBuf), Box<dyn Error + Send + Sync + 'static>> {
+    let pos = s
+        .find('=')
+        .ok_or_else(|| format!("invalid KEY=value: no `=` found in `{s}`"))?;
+    let key = s[..pos].to_string();
+    let value = PathBuf::from(&s[pos + 1..]);
+    Ok((key, value))
+}
+
+/// Write the contents of sections in the ELF file to files
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Path to the file to read
+    pub path_to_binary: PathBuf,
+
+    /// The sections to dump, as NAME=FILE
+    #[arg(value_parser = parse_key_val, required = true)]
+    pub sections: Vec<(String, PathBuf)>,
+}
+
+pub fn execute(args: Args) -> Result<(), arwen_elf::ElfError> {
+    let bytes_of_file = arwen_core::MappedFile::open(&args.path_to_binary)?;
+
+    let elf = arwen_elf::ElfContainer::parse(&bytes_of_file)?;
+
+    for (name, path) in args.sections {
+        let data = elf.section_data(&name)?;
+        std::fs::write(path, data)?;
+    }
+
+    Ok(())
+}
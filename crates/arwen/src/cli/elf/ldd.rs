@@ -0,0 +1,53 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+/// Print the libraries the dynamic loader would load, without running the binary.
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Path to the file to inspect
+    pub path_to_binary: PathBuf,
+
+    /// Resolve absolute paths inside this directory instead of `/`
+    #[arg(long)]
+    pub sysroot: Option<PathBuf>,
+
+    /// Colon separated directories to search, like `LD_LIBRARY_PATH`.
+    /// Defaults to the value of `LD_LIBRARY_PATH`.
+    #[arg(long)]
+    pub library_path: Option<String>,
+
+    /// Value of `$PLATFORM`, derived from the machine of the binary by default
+    #[arg(long)]
+    pub platform: Option<String>,
+
+    /// Print the dependency tree as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+pub fn execute(args: Args) -> Result<(), arwen_elf::ElfError> {
+    let mut resolver = arwen_elf::Resolver::new();
+    if let Some(sysroot) = args.sysroot {
+        resolver = resolver.with_sysroot(sysroot);
+    }
+    let library_path = args
+        .library_path
+        .or_else(|| std::env::var("LD_LIBRARY_PATH").ok());
+    if let Some(library_path) = library_path {
+        resolver = resolver.with_library_path(&library_path);
+    }
+    if let Some(platform) = args.platform {
+        resolver = resolver.with_platform(platform);
+    }
+
+    let tree = resolver.resolve(&args.path_to_binary)?;
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&tree).unwrap());
+    } else {
+        print!("{tree}");
+    }
+
+    Ok(())
+}
@@ -1,31 +1,62 @@
+use std::path::Path;
+
 use arwen_elf::ElfError;
 
 use super::ElfCommand;
 
 pub mod add_debug_tag;
+pub mod add_gnu_hash;
 pub mod add_needed;
 pub mod add_rpath;
+pub mod add_section;
+pub mod change_symbol_export;
+pub mod check;
 pub mod clear_execstack;
+pub mod clear_flags;
+pub mod clear_property;
 pub mod clear_version_symbol;
+pub mod delete_sections;
+pub mod delete_symbols;
+pub mod dump_section;
 pub mod force_rpath;
+pub mod ldd;
 pub mod no_default_lib;
+pub mod prefix_symbols;
+pub mod print_dynamic;
 pub mod print_execstack;
+pub mod print_flags;
+pub mod print_header;
 pub mod print_interpreter;
 pub mod print_needed;
 pub mod print_os_abi;
+pub mod print_properties;
 pub mod print_rpath;
+pub mod print_segments;
 pub mod print_soname;
+pub mod print_version_requirements;
 pub mod remove_needed;
 pub mod remove_rpath;
+pub mod remove_version_requirement;
 pub mod rename_dynamic_symbols;
+pub mod rename_library;
+pub mod rename_sections;
+pub mod rename_symbols;
+pub mod reorder_needed;
 pub mod replace_needed;
+pub mod replace_version_requirement;
+pub mod set_abi_version;
+pub mod set_e_flags;
 pub mod set_execstack;
+pub mod set_flags;
 pub mod set_interpreter;
 pub mod set_os_abi;
 pub mod set_page_size;
 pub mod set_rpath;
+pub mod set_segment_flags;
 pub mod set_soname;
 pub mod shrink_rpath;
+pub mod strip_symbol_versions;
+pub mod update_section;
 
 pub fn execute(elf: ElfCommand) -> Result<(), ElfError> {
     match elf {
@@ -53,5 +84,67 @@ pub fn execute(elf: ElfCommand) -> Result<(), ElfError> {
         ElfCommand::SetExecStack(args) => set_execstack::execute(args),
         ElfCommand::PrintExecStack(args) => print_execstack::execute(args),
         ElfCommand::SetPageSize(args) => set_page_size::execute(args),
+        ElfCommand::PrintDynamic(args) => print_dynamic::execute(args),
+        ElfCommand::SetFlags(args) => set_flags::execute(args),
+        ElfCommand::ClearFlags(args) => clear_flags::execute(args),
+        ElfCommand::PrintFlags(args) => print_flags::execute(args),
+        ElfCommand::PrintVersionRequirements(args) => print_version_requirements::execute(args),
+        ElfCommand::RemoveVersionRequirement(args) => remove_version_requirement::execute(args),
+        ElfCommand::ReplaceVersionRequirement(args) => replace_version_requirement::execute(args),
+        ElfCommand::StripSymbolVersions(args) => strip_symbol_versions::execute(args),
+        ElfCommand::DeleteSymbols(args) => delete_symbols::execute(args),
+        ElfCommand::RenameSymbols(args) => rename_symbols::execute(args),
+        ElfCommand::DeleteSections(args) => delete_sections::execute(args),
+        ElfCommand::RenameSections(args) => rename_sections::execute(args),
+        ElfCommand::AddSection(args) => add_section::execute(args),
+        ElfCommand::UpdateSection(args) => update_section::execute(args),
+        ElfCommand::DumpSection(args) => dump_section::execute(args),
+        ElfCommand::Ldd(args) => ldd::execute(args),
+        ElfCommand::SetAbiVersion(args) => set_abi_version::execute(args),
+        ElfCommand::SetEFlags(args) => set_e_flags::execute(args),
+        ElfCommand::PrintHeader(args) => print_header::execute(args),
+        ElfCommand::PrintSegments(args) => print_segments::execute(args),
+        ElfCommand::SetSegmentFlags(args) => set_segment_flags::execute(args),
+        ElfCommand::PrintProperties(args) => print_properties::execute(args),
+        ElfCommand::ClearProperty(args) => clear_property::execute(args),
+        ElfCommand::AddGnuHash(args) => add_gnu_hash::execute(args),
+        ElfCommand::ChangeSymbolExport(args) => change_symbol_export::execute(args),
+        ElfCommand::PrefixSymbols(args) => prefix_symbols::execute(args),
+        ElfCommand::ReorderNeeded(args) => reorder_needed::execute(args),
+        ElfCommand::Check(args) => check::execute(args),
+        ElfCommand::RenameLibrary(args) => rename_library::execute(args),
+    }
+}
+
+/// Read a list of names from a file, one per line.
+///
+/// Empty lines and lines starting with `#` are ignored.
+pub(crate) fn read_names_file(path: &Path) -> Result<Vec<String>, ElfError> {
+    let content = std::fs::read_to_string(path)?;
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+/// Read a list of `OLD NEW` or `OLD=NEW` pairs from a file, one per line.
+///
+/// Empty lines and lines starting with `#` are ignored.
+pub(crate) fn read_pairs_file(path: &Path) -> Result<Vec<(String, String)>, ElfError> {
+    let mut pairs = Vec::new();
+    for line in read_names_file(path)? {
+        let pair = line
+            .split_once('=')
+            .or_else(|| line.split_once(char::is_whitespace));
+        let Some((old, new)) = pair else {
+            return Err(ElfError::Modify(format!(
+                "invalid line `{line}` in {}: expected OLD NEW or OLD=NEW",
+                path.display()
+            )));
+        };
+        pairs.push((old.trim().to_string(), new.trim().to_string()));
     }
+    Ok(pairs)
 }
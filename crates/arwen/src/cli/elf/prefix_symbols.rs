@@ -0,0 +1,92 @@
+use std::path::PathBuf;
+
+use arwen_elf::{ElfContainer, SymbolRename};
+use clap::Parser;
+use serde::Serialize;
+
+/// Add a prefix to every symbol exported by a library and to the matching imports of
+/// its consumers, so that two versions of the library can be loaded side by side.
+///
+/// The library is matched in the consumers by its SONAME, or by its file name if it
+/// has none. Symbol versions are kept. No file is changed if any of them fails.
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Prefix to add, e.g. `myvendor_`
+    #[arg(long)]
+    pub prefix: String,
+
+    /// Path to the library
+    pub library: PathBuf,
+
+    /// Paths to the files that link against the library
+    pub consumers: Vec<PathBuf>,
+
+    /// Print the renamed symbols as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Serialize)]
+struct FileReport {
+    path: PathBuf,
+    symbols: Vec<SymbolRename>,
+}
+
+pub fn execute(args: Args) -> Result<(), arwen_elf::ElfError> {
+    let library_bytes = arwen_core::MappedFile::open(&args.library)?;
+    let consumer_bytes = args
+        .consumers
+        .iter()
+        .map(arwen_core::MappedFile::open)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut library = ElfContainer::parse(&library_bytes)?;
+    let library_name = library.soname().unwrap_or_else(|| {
+        args.library
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .into_owned()
+    });
+    let renames = library.prefix_exports(&args.prefix);
+
+    let mut consumers = Vec::new();
+    let mut reports = vec![FileReport {
+        path: args.library.clone(),
+        symbols: renames.clone(),
+    }];
+    for (path, bytes) in args.consumers.iter().zip(&consumer_bytes) {
+        let mut consumer = ElfContainer::parse(bytes)?;
+        let symbols = consumer.rename_imports(&library_name, &renames)?;
+        reports.push(FileReport {
+            path: path.clone(),
+            symbols,
+        });
+        consumers.push(consumer);
+    }
+
+    // Only write the files once all of them were rewritten.
+    let mut outputs = vec![(&args.library, Vec::new())];
+    library.write(&mut outputs[0].1)?;
+    for (path, consumer) in args.consumers.iter().zip(consumers) {
+        let mut output = Vec::new();
+        consumer.write(&mut output)?;
+        outputs.push((path, output));
+    }
+    for (path, output) in outputs {
+        arwen_core::write_atomically(path, |w| w.write_all(&output))?;
+    }
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&reports).unwrap());
+    } else {
+        for report in reports {
+            println!("{}", report.path.display());
+            for symbol in report.symbols {
+                println!("    {symbol}");
+            }
+        }
+    }
+
+    Ok(())
+}
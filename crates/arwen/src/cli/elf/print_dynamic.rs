@@ -0,0 +1,29 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+/// Print all entries of the dynamic section.
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Path to the file to inspect
+    pub path_to_binary: PathBuf,
+
+    /// Print the entries as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+pub fn execute(args: Args) -> Result<(), arwen_elf::ElfError> {
+    let bytes_of_file = arwen_core::MappedFile::open(&args.path_to_binary)?;
+
+    let elf = arwen_elf::ElfContainer::parse(&bytes_of_file)?;
+
+    if args.json {
+        let entries = elf.dynamic_entries();
+        println!("{}", serde_json::to_string_pretty(&entries).unwrap());
+    } else {
+        elf.print_dynamic();
+    }
+
+    Ok(())
+}
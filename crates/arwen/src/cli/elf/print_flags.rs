@@ -0,0 +1,20 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+/// Print the decoded DT_FLAGS and DT_FLAGS_1 bits of the ELF file
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Path to the file to inspect
+    pub path_to_binary: PathBuf,
+}
+
+pub fn execute(args: Args) -> Result<(), arwen_elf::ElfError> {
+    let bytes_of_file = arwen_core::MappedFile::open(&args.path_to_binary)?;
+
+    let elf = arwen_elf::ElfContainer::parse(&bytes_of_file)?;
+
+    elf.print_flags();
+
+    Ok(())
+}
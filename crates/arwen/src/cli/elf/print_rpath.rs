@@ -7,14 +7,22 @@ use clap::Parser;
 pub struct Args {
     /// Path to the file to change
     pub path_to_binary: PathBuf,
+
+    /// Print every DT_RPATH and DT_RUNPATH entry, prefixed with its tag
+    #[arg(long)]
+    pub show_kind: bool,
 }
 
 pub fn execute(args: Args) -> Result<(), arwen_elf::ElfError> {
-    let bytes_of_file = std::fs::read(&args.path_to_binary).unwrap();
+    let bytes_of_file = arwen_core::MappedFile::open(&args.path_to_binary)?;
 
     let mut elf = arwen_elf::ElfContainer::parse(&bytes_of_file)?;
 
-    elf.print_runpath();
+    if args.show_kind {
+        elf.print_runpath_kind();
+    } else {
+        elf.print_runpath();
+    }
 
     Ok(())
 }
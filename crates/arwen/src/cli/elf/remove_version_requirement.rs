@@ -0,0 +1,29 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+/// Remove a symbol version requirement, e.g. GLIBC_2.34
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Path to the file to change
+    pub path_to_binary: PathBuf,
+
+    /// The version to remove
+    pub version: String,
+
+    /// Only remove the requirement on this library, e.g. libc.so.6
+    #[arg(long)]
+    pub library: Option<String>,
+}
+
+pub fn execute(args: Args) -> Result<(), arwen_elf::ElfError> {
+    let bytes_of_file = arwen_core::MappedFile::open(&args.path_to_binary)?;
+
+    let mut elf = arwen_elf::ElfContainer::parse(&bytes_of_file)?;
+
+    elf.remove_version_requirement(&args.version, args.library.as_deref())?;
+
+    elf.write_to_path(&args.path_to_binary)?;
+
+    Ok(())
+}
@@ -0,0 +1,239 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use arwen_core::StagedFile;
+use arwen_elf::{ElfContainer, ElfError};
+use clap::Parser;
+use serde::Serialize;
+
+/// Rename a shared library and every reference to it in a directory tree.
+///
+/// The library whose SONAME is OLD, or that has no SONAME and is named OLD, gets the
+/// SONAME NEW, and its base version definition is renamed to match. Every file that
+/// needs OLD gets its DT_NEEDED entry and the file name of its symbol version
+/// requirements renamed. No file is changed if any of them can't be rewritten, and
+/// errors name the file they occurred in. If writing stops partway, the error lists
+/// the files that were already changed.
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Current name of the library, e.g. `libfoo.so.1`
+    pub old: String,
+
+    /// New name of the library, e.g. `libfoo_vendored.so.1`
+    pub new: String,
+
+    /// Directory to search for the library and its consumers
+    pub dir: PathBuf,
+
+    /// Also rename the library file or symlink named OLD, and point the symlinks
+    /// to it at the new name
+    #[arg(long)]
+    pub rename_files: bool,
+
+    /// Print the changed files as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Serialize, Default)]
+struct Report {
+    libraries: Vec<PathBuf>,
+    consumers: Vec<PathBuf>,
+    renamed_files: Vec<(PathBuf, PathBuf)>,
+    symlinks: Vec<PathBuf>,
+}
+
+pub fn execute(args: Args) -> Result<(), ElfError> {
+    let mut files = Vec::new();
+    let mut symlinks = Vec::new();
+    walk(&args.dir, &mut files, &mut symlinks)?;
+
+    // Stage every rewritten file next to the file it replaces; dropping them on an
+    // error deletes them again.
+    let mut report = Report::default();
+    let mut staged = Vec::new();
+    for path in files {
+        let file =
+            edit_file(&path, &args, &mut report).map_err(|source| file_error(&path, source))?;
+        staged.extend(file);
+    }
+    if report.libraries.is_empty() {
+        return Err(ElfError::Modify(format!(
+            "No library named {} found in {}",
+            args.old,
+            args.dir.display()
+        )));
+    }
+
+    // Find the files to rename before changing anything.
+    let mut renames = Vec::new();
+    if args.rename_files {
+        for library in &report.libraries {
+            let directory = library.parent().unwrap_or(Path::new("."));
+            let old_path = directory.join(&args.old);
+            let new_path = directory.join(&args.new);
+            if fs::symlink_metadata(&old_path).is_err()
+                || renames.contains(&(old_path.clone(), new_path.clone()))
+            {
+                continue;
+            }
+            if fs::symlink_metadata(&new_path).is_ok() {
+                return Err(ElfError::Modify(format!(
+                    "Can't rename {}: {} already exists",
+                    old_path.display(),
+                    new_path.display()
+                )));
+            }
+            renames.push((old_path, new_path));
+        }
+    }
+    let mut relinks = Vec::new();
+    if args.rename_files {
+        for link in symlinks {
+            let target = fs::read_link(&link)?;
+            if target
+                .file_name()
+                .is_some_and(|name| name == args.old.as_str())
+            {
+                relinks.push((link, target.with_file_name(&args.new)));
+            }
+        }
+    }
+
+    // Only replace the files once all of them were rewritten.
+    let mut replaced = Vec::new();
+    for file in staged {
+        let path = file.path().to_path_buf();
+        file.replace()
+            .map_err(|source| partial_error(&path, source, &replaced))?;
+        replaced.push(path);
+    }
+    for (old_path, new_path) in renames {
+        fs::rename(&old_path, &new_path)
+            .map_err(|source| partial_error(&old_path, source, &replaced))?;
+        replaced.push(new_path.clone());
+        report.renamed_files.push((old_path, new_path));
+    }
+    for (link, target) in relinks {
+        relink(&link, &target).map_err(|source| partial_error(&link, source, &replaced))?;
+        replaced.push(link.clone());
+        report.symlinks.push(link);
+    }
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+    } else {
+        for path in &report.libraries {
+            println!("renamed library {}", path.display());
+        }
+        for path in &report.consumers {
+            println!("updated consumer {}", path.display());
+        }
+        for (old_path, new_path) in &report.renamed_files {
+            println!("moved {} to {}", old_path.display(), new_path.display());
+        }
+        for path in &report.symlinks {
+            println!("updated symlink {}", path.display());
+        }
+    }
+
+    Ok(())
+}
+
+/// Rename the library in one file, if it is the library or one of its consumers.
+///
+/// Returns the rewritten file staged next to it, or `None` if the file doesn't change.
+fn edit_file(
+    path: &Path,
+    args: &Args,
+    report: &mut Report,
+) -> Result<Option<StagedFile>, ElfError> {
+    let bytes = arwen_core::MappedFile::open(path)?;
+    if !bytes.starts_with(b"\x7fELF") {
+        return Ok(None);
+    }
+    let mut elf = ElfContainer::parse(&bytes)?;
+    let file_name = path.file_name().map(|name| name.to_string_lossy());
+    let is_library = match elf.soname() {
+        Some(soname) => soname == args.old,
+        None => file_name.as_deref() == Some(args.old.as_str()),
+    };
+    let mut changed = false;
+    if is_library {
+        elf.set_soname(&args.new)?;
+        report.libraries.push(path.to_path_buf());
+        changed = true;
+    }
+    if elf.rename_needed_library(&args.old, &args.new)? {
+        report.consumers.push(path.to_path_buf());
+        changed = true;
+    }
+    if !changed {
+        return Ok(None);
+    }
+    let file = StagedFile::write(path, |w| elf.write(w))?;
+    Ok(Some(file))
+}
+
+/// Attach the path of the file an error occurred in.
+fn file_error(path: &Path, source: ElfError) -> ElfError {
+    ElfError::File {
+        path: path.to_path_buf(),
+        source: Box::new(source),
+    }
+}
+
+/// Attach the path of the file an error occurred in, and the files that were already
+/// changed when it stopped.
+fn partial_error(path: &Path, source: std::io::Error, replaced: &[PathBuf]) -> ElfError {
+    if replaced.is_empty() {
+        return file_error(path, source.into());
+    }
+    let replaced = replaced
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    file_error(
+        path,
+        ElfError::Modify(format!(
+            "{source}; stopped after these files were already changed: {replaced}"
+        )),
+    )
+}
+
+/// Collect the regular files and the symlinks under `dir`, without following
+/// symlinks to directories.
+fn walk(dir: &Path, files: &mut Vec<PathBuf>, symlinks: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    let mut entries = fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            walk(&path, files, symlinks)?;
+        } else if file_type.is_symlink() {
+            symlinks.push(path);
+        } else if file_type.is_file() {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Point the symlink at `link` to `target`.
+#[cfg(unix)]
+fn relink(link: &Path, target: &Path) -> std::io::Result<()> {
+    fs::remove_file(link)?;
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(not(unix))]
+fn relink(link: &Path, _target: &Path) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        format!("Can't update the symlink {}", link.display()),
+    ))
+}
@@ -0,0 +1,50 @@
+use std::{collections::HashMap, error::Error, path::PathBuf};
+
+use clap::Parser;
+
+use super::read_pairs_file;
+
+/// Parse a single key-value pair
+fn parse_key_val(s: &str) -> Result<(String, String), Box<dyn Error + Send + Sync + 'static>> {
+    let pos = s
+        .find('=')
+        .ok_or_else(|| format!("invalid KEY=value: no `=` found in `{s}`"))?;
+    let key = s[..pos].to_string();
+    let value = s[pos + 1..].to_string();
+    Ok((key, value))
+}
+
+/// Rename sections in the ELF file
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Path to the file to change
+    pub path_to_binary: PathBuf,
+
+    /// Sections to rename, as OLD=NEW
+    #[arg(value_parser = parse_key_val)]
+    pub rename_sections: Vec<(String, String)>,
+
+    /// Read additional sections to rename from a file, one `OLD NEW` or `OLD=NEW` pair per line
+    #[arg(long)]
+    pub from_file: Option<PathBuf>,
+}
+
+pub fn execute(args: Args) -> Result<(), arwen_elf::ElfError> {
+    let bytes_of_file = arwen_core::MappedFile::open(&args.path_to_binary)?;
+
+    let mut elf = arwen_elf::ElfContainer::parse(&bytes_of_file)?;
+
+    let mut rename_sections = HashMap::new();
+    for (key, value) in args.rename_sections {
+        rename_sections.insert(key, value);
+    }
+    if let Some(path) = &args.from_file {
+        rename_sections.extend(read_pairs_file(path)?);
+    }
+
+    elf.rename_sections(&rename_sections)?;
+
+    elf.write_to_path(&args.path_to_binary)?;
+
+    Ok(())
+}
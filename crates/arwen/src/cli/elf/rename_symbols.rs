@@ -0,0 +1,64 @@
+use std::{collections::HashMap, error::Error, path::PathBuf};
+
+use clap::Parser;
+
+use super::read_pairs_file;
+
+/// Parse a single key-value pair
+fn parse_key_val(s: &str) -> Result<(String, String), Box<dyn Error + Send + Sync + 'static>> {
+    let pos = s
+        .find('=')
+        .ok_or_else(|| format!("invalid KEY=value: no `=` found in `{s}`"))?;
+    let key = s[..pos].to_string();
+    let value = s[pos + 1..].to_string();
+    Ok((key, value))
+}
+
+/// Rename symbols in the symbol table and the dynamic symbol table
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Path to the file to change
+    pub path_to_binary: PathBuf,
+
+    /// Symbols to rename, as OLD=NEW
+    #[arg(value_parser = parse_key_val)]
+    pub rename_symbols: Vec<(String, String)>,
+
+    /// Read additional symbols to rename from a file, one `OLD NEW` or `OLD=NEW` pair per line
+    #[arg(long)]
+    pub from_file: Option<PathBuf>,
+
+    /// Only rename symbols in the symbol table
+    #[arg(long, conflicts_with = "dynamic_only")]
+    pub static_only: bool,
+
+    /// Only rename symbols in the dynamic symbol table
+    #[arg(long)]
+    pub dynamic_only: bool,
+}
+
+pub fn execute(args: Args) -> Result<(), arwen_elf::ElfError> {
+    let bytes_of_file = arwen_core::MappedFile::open(&args.path_to_binary)?;
+
+    let mut elf = arwen_elf::ElfContainer::parse(&bytes_of_file)?;
+
+    let mut rename_symbols = HashMap::new();
+    for (key, value) in args.rename_symbols {
+        rename_symbols.insert(key, value);
+    }
+    if let Some(path) = &args.from_file {
+        rename_symbols.extend(read_pairs_file(path)?);
+    }
+
+    if args.static_only {
+        elf.rename_static_symbols(&rename_symbols)?;
+    } else if args.dynamic_only {
+        elf.rename_dynamic_symbols(&rename_symbols)?;
+    } else {
+        elf.rename_symbols(&rename_symbols)?;
+    }
+
+    elf.write_to_path(&args.path_to_binary)?;
+
+    Ok(())
+}
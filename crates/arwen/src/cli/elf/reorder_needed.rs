@@ -0,0 +1,29 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+/// Reorder the DT_NEEDED entries, which sets the order in which symbols are looked up
+///
+/// The given libraries come first, in the given order, followed by the other needed
+/// libraries in their current order.
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Path to the file to change
+    pub path_to_binary: PathBuf,
+
+    /// Needed libraries in their new order
+    #[arg(required = true)]
+    pub dt_needed: Vec<String>,
+}
+
+pub fn execute(args: Args) -> Result<(), arwen_elf::ElfError> {
+    let bytes_of_file = arwen_core::MappedFile::open(&args.path_to_binary)?;
+
+    let mut elf = arwen_elf::ElfContainer::parse(&bytes_of_file)?;
+
+    elf.reorder_needed(args.dt_needed)?;
+
+    elf.write_to_path(&args.path_to_binary)?;
+
+    Ok(())
+}
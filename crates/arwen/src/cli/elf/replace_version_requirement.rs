@@ -0,0 +1,42 @@
+use std::{error::Error, path::PathBuf};
+
+use clap::Parser;
+
+/// Parse a single key-value pair
+fn parse_key_val(s: &str) -> Result<(String, String), Box<dyn Error + Send + Sync + 'static>> {
+    let pos = s
+        .find('=')
+        .ok_or_else(|| format!("invalid KEY=value: no `=` found in `{s}`"))?;
+    let key = s[..pos].to_string();
+    let value = s[pos + 1..].to_string();
+    Ok((key, value))
+}
+
+/// Remap symbol version requirements, e.g. GLIBC_2.34=GLIBC_2.17
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Path to the file to change
+    pub path_to_binary: PathBuf,
+
+    /// Versions to replace, as OLD=NEW
+    #[arg(value_parser = parse_key_val, required = true)]
+    pub versions: Vec<(String, String)>,
+
+    /// Only replace the requirements on this library, e.g. libc.so.6
+    #[arg(long)]
+    pub library: Option<String>,
+}
+
+pub fn execute(args: Args) -> Result<(), arwen_elf::ElfError> {
+    let bytes_of_file = arwen_core::MappedFile::open(&args.path_to_binary)?;
+
+    let mut elf = arwen_elf::ElfContainer::parse(&bytes_of_file)?;
+
+    for (old_version, new_version) in &args.versions {
+        elf.replace_version_requirement(old_version, new_version, args.library.as_deref())?;
+    }
+
+    elf.write_to_path(&args.path_to_binary)?;
+
+    Ok(())
+}
@@ -0,0 +1,25 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+/// Change the ABI version (EI_ABIVERSION) of the elf file
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// ABI version to set
+    pub abi_version: u8,
+
+    /// Path to the file to change
+    pub path_to_binary: PathBuf,
+}
+
+pub fn execute(args: Args) -> Result<(), arwen_elf::ElfError> {
+    let bytes_of_file = arwen_core::MappedFile::open(&args.path_to_binary)?;
+
+    let mut elf = arwen_elf::ElfContainer::parse(&bytes_of_file)?;
+
+    elf.set_abi_version(args.abi_version);
+
+    elf.write_to_path(&args.path_to_binary)?;
+
+    Ok(())
+}
@@ -0,0 +1,34 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+/// Change the processor-specific flags (e_flags) of the elf file
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Flags to set, in decimal or as hexadecimal with a `0x` prefix
+    #[arg(value_parser = parse_flags)]
+    pub e_flags: u32,
+
+    /// Path to the file to change
+    pub path_to_binary: PathBuf,
+}
+
+pub fn execute(args: Args) -> Result<(), arwen_elf::ElfError> {
+    let bytes_of_file = arwen_core::MappedFile::open(&args.path_to_binary)?;
+
+    let mut elf = arwen_elf::ElfContainer::parse(&bytes_of_file)?;
+
+    elf.set_e_flags(args.e_flags);
+
+    elf.write_to_path(&args.path_to_binary)?;
+
+    Ok(())
+}
+
+fn parse_flags(s: &str) -> Result<u32, String> {
+    let parsed = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    parsed.map_err(|e| format!("invalid flags `{s}`: {e}"))
+}
@@ -0,0 +1,28 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+/// Set DT_FLAGS and DT_FLAGS_1 bits in the ELF file
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Path to the file to change
+    pub path_to_binary: PathBuf,
+
+    /// Flags to set, e.g. BIND_NOW, NOW, ORIGIN, NODELETE, NOOPEN, PIE or INITFIRST
+    ///
+    /// Prefix a name with DF_ or DF_1_ to select the DT_FLAGS or DT_FLAGS_1 bit explicitly.
+    #[arg(required = true)]
+    pub flags: Vec<String>,
+}
+
+pub fn execute(args: Args) -> Result<(), arwen_elf::ElfError> {
+    let bytes_of_file = arwen_core::MappedFile::open(&args.path_to_binary)?;
+
+    let mut elf = arwen_elf::ElfContainer::parse(&bytes_of_file)?;
+
+    elf.set_dynamic_flags(&args.flags)?;
+
+    elf.write_to_path(&args.path_to_binary)?;
+
+    Ok(())
+}
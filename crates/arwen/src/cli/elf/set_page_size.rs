@@ -3,6 +3,10 @@ use std::path::PathBuf;
 use clap::Parser;
 
 /// Set the page size for ELF file segment alignment
+///
+/// The LOAD segments are shifted in the file so that the file loads with pages of
+/// that size. Fails if segments share a page that can't be split without linking the
+/// file again.
 #[derive(Parser, Debug)]
 pub struct Args {
     /// Page size in bytes (must be power of 2 and >= 1024)
@@ -13,16 +17,13 @@ pub struct Args {
 }
 
 pub fn execute(args: Args) -> Result<(), arwen_elf::ElfError> {
-    let bytes_of_file = std::fs::read(&args.path_to_binary).unwrap();
+    let bytes_of_file = arwen_core::MappedFile::open(&args.path_to_binary)?;
 
     let mut elf = arwen_elf::ElfContainer::parse(&bytes_of_file)?;
 
     elf.set_page_size(args.page_size)?;
 
-    let output_file =
-        std::fs::File::create(format!("{}", args.path_to_binary.to_string_lossy())).unwrap();
-
-    elf.write(&output_file)?;
+    elf.write_to_path(&args.path_to_binary)?;
 
     Ok(())
 }
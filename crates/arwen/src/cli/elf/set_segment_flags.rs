@@ -0,0 +1,83 @@
+use std::path::PathBuf;
+
+use arwen_elf::ElfError;
+use clap::{ArgGroup, Parser};
+
+/// Change the permissions of a segment.
+///
+/// The segment is selected by its index, as shown by `print-segments`, or by its type
+/// if there is only one segment of that type. Mapping a section both writable and
+/// executable is refused unless `--force` is given.
+#[derive(Parser, Debug)]
+#[command(group(ArgGroup::new("segment").required(true).args(["index", "segment_type"])))]
+#[command(group(ArgGroup::new("change").required(true).multiple(true).args(["flags", "add", "remove"])))]
+pub struct Args {
+    /// Path to the file to change
+    pub path_to_binary: PathBuf,
+
+    /// Index of the segment in the program header table
+    #[arg(long)]
+    pub index: Option<usize>,
+
+    /// Type of the segment, e.g. `GNU_RELRO`
+    #[arg(long = "type")]
+    pub segment_type: Option<String>,
+
+    /// New permissions, e.g. `r-x`
+    #[arg(long, conflicts_with_all = ["add", "remove"], value_parser = parse_flags)]
+    pub flags: Option<u32>,
+
+    /// Permissions to add, e.g. `x`
+    #[arg(long, value_parser = parse_flags)]
+    pub add: Option<u32>,
+
+    /// Permissions to remove, e.g. `w`
+    #[arg(long, value_parser = parse_flags)]
+    pub remove: Option<u32>,
+
+    /// Allow mapping sections writable and executable
+    #[arg(long)]
+    pub force: bool,
+}
+
+pub fn execute(args: Args) -> Result<(), ElfError> {
+    let bytes_of_file = arwen_core::MappedFile::open(&args.path_to_binary)?;
+
+    let mut elf = arwen_elf::ElfContainer::parse(&bytes_of_file)?;
+
+    let segments = elf.segments();
+    let segment = match (args.index, args.segment_type) {
+        (Some(index), _) => segments.get(index),
+        (None, Some(segment_type)) => {
+            let segment_type = segment_type.to_uppercase();
+            let segment_type = segment_type.strip_prefix("PT_").unwrap_or(&segment_type);
+            let mut matching = segments
+                .iter()
+                .filter(|segment| segment.type_name == segment_type);
+            let segment = matching.next();
+            if matching.next().is_some() {
+                return Err(ElfError::Modify(format!(
+                    "There are several {segment_type} segments; select one with --index"
+                )));
+            }
+            segment
+        }
+        (None, None) => unreachable!("clap requires a segment"),
+    }
+    .ok_or_else(|| ElfError::Modify("No such segment".to_string()))?;
+
+    let p_flags = match args.flags {
+        Some(flags) => flags,
+        None => (segment.p_flags | args.add.unwrap_or(0)) & !args.remove.unwrap_or(0),
+    };
+    elf.set_segment_flags(segment.index, p_flags, args.force)?;
+
+    elf.write_to_path(&args.path_to_binary)?;
+
+    Ok(())
+}
+
+fn parse_flags(s: &str) -> Result<u32, String> {
+    arwen_elf::parse_segment_flags(s)
+        .ok_or_else(|| format!("invalid permissions `{s}`: expected letters from `rwx`"))
+}
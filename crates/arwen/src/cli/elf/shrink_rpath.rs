@@ -12,22 +12,30 @@ pub struct Args {
     /// option to allow rpath prefixes
     #[clap(long, short)]
     pub allowed_rpath_prefixes: Vec<String>,
+
+    /// Print the kept and removed directories as JSON
+    #[arg(long)]
+    pub json: bool,
 }
 
 pub fn execute(args: Args) -> Result<(), arwen_elf::ElfError> {
     // For instance, if an executable references one library libfoo.so,
     // has an RPATH "/lib:/usr/lib:/foo/lib", and libfoo.so can only be found in /foo/lib, then the new
     // RPATH will be "/foo/lib".
-    let bytes_of_file = std::fs::read(&args.path_to_binary).unwrap();
+    let bytes_of_file = arwen_core::MappedFile::open(&args.path_to_binary)?;
 
     let mut elf = arwen_elf::ElfContainer::parse(&bytes_of_file)?;
 
-    elf.shrink_rpath(args.allowed_rpath_prefixes)?;
+    let path = std::path::absolute(&args.path_to_binary)?;
+    let report = elf.shrink_rpath(args.allowed_rpath_prefixes, path.parent())?;
 
-    let output_file =
-        std::fs::File::create(format!("{}", args.path_to_binary.to_string_lossy())).unwrap();
+    elf.write_to_path(&args.path_to_binary)?;
 
-    elf.write(&output_file)?;
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+    } else {
+        print!("{report}");
+    }
 
     Ok(())
 }
@@ -0,0 +1,22 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+/// Remove all symbol versioning information from the ELF file
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Path to the file to change
+    pub path_to_binary: PathBuf,
+}
+
+pub fn execute(args: Args) -> Result<(), arwen_elf::ElfError> {
+    let bytes_of_file = arwen_core::MappedFile::open(&args.path_to_binary)?;
+
+    let mut elf = arwen_elf::ElfContainer::parse(&bytes_of_file)?;
+
+    elf.strip_symbol_versions()?;
+
+    elf.write_to_path(&args.path_to_binary)?;
+
+    Ok(())
+}
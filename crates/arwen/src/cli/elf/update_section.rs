@@ -0,0 +1,41 @@
+use std::{error::Error, path::PathBuf};
+
+use clap::Parser;
+
+/// Parse a single key-value pair
+fn parse_key_val(s: &str) -> Result<(String, PathBuf), Box<dyn Error + Send + Sync + 'static>> {
+    let pos = s
+        .find('=')
+        .ok_or_else(|| format!("invalid KEY=value: no `=` found in `{s}`"))?;
+    let key = s[..pos].to_string();
+    let value = PathBuf::from(&s[pos + 1..]);
+    Ok((key, value))
+}
+
+/// Replace the contents of sections in the ELF file with the contents of files
+///
+/// Allocated sections can't grow past their current size.
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Path to the file to change
+    pub path_to_binary: PathBuf,
+
+    /// The sections to update, as NAME=FILE
+    #[arg(value_parser = parse_key_val, required = true)]
+    pub sections: Vec<(String, PathBuf)>,
+}
+
+pub fn execute(args: Args) -> Result<(), arwen_elf::ElfError> {
+    let bytes_of_file = arwen_core::MappedFile::open(&args.path_to_binary)?;
+
+    let mut elf = arwen_elf::ElfContainer::parse(&bytes_of_file)?;
+
+    for (name, path) in args.sections {
+        let data = std::fs::read(path)?;
+        elf.update_section(&name, data)?;
+    }
+
+    elf.write_to_path(&args.path_to_binary)?;
+
+    Ok(())
+}
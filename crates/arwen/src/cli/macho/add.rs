@@ -15,13 +15,13 @@ pub struct Args {
 }
 
 pub fn execute(args: Args) -> Result<(), MachoError> {
-    let bytes_of_file = std::fs::read(&args.path_to_binary).unwrap();
+    let bytes_of_file = arwen_core::MappedFile::open(&args.path_to_binary)?;
 
     let mut macho = MachoContainer::parse(&bytes_of_file)?;
 
     macho.add_rpath(&args.new_rpath)?;
 
-    std::fs::write(args.path_to_binary, macho.data).unwrap();
+    macho.write_to_path(&args.path_to_binary)?;
 
     Ok(())
 }
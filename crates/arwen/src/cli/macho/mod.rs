@@ -4,9 +4,14 @@ pub mod codesign;
 pub mod delete;
 pub mod install_id;
 pub mod install_name;
+pub mod print_id;
+pub mod print_libs;
+pub mod print_load_commands;
+pub mod print_rpaths;
 
 use super::MachoCommand;
-use arwen_macho::MachoError;
+use arwen_macho::{MachoError, SliceInfo};
+use serde::Serialize;
 
 pub fn execute(macho: MachoCommand) -> Result<(), MachoError> {
     match macho {
@@ -16,5 +21,26 @@ pub fn execute(macho: MachoCommand) -> Result<(), MachoError> {
         MachoCommand::ChangeInstallName(args) => install_name::execute(args),
         MachoCommand::ChangeInstallId(args) => install_id::execute(args),
         MachoCommand::AdhocSign(args) => codesign::execute(args),
+        MachoCommand::PrintRpaths(args) => print_rpaths::execute(args),
+        MachoCommand::PrintLibs(args) => print_libs::execute(args),
+        MachoCommand::PrintId(args) => print_id::execute(args),
+        MachoCommand::PrintLoadCommands(args) => print_load_commands::execute(args),
+    }
+}
+
+/// Print a value read from every architecture, as JSON or with `print`.
+///
+/// Fat files get a line with the name of each architecture, and `print` is given an
+/// indent for the lines under it.
+fn print_slices<T: Serialize>(slices: &[SliceInfo<T>], json: bool, print: impl Fn(&T, &str)) {
+    if json {
+        println!("{}", serde_json::to_string_pretty(slices).unwrap());
+    } else if let [slice] = slices {
+        print(&slice.value, "");
+    } else {
+        for slice in slices {
+            println!("{}:", slice.arch);
+            print(&slice.value, "\t");
+        }
     }
 }
@@ -0,0 +1,31 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use arwen_macho::{MachoContainer, MachoError};
+
+/// Print the install id of every architecture. Prints nothing for files that aren't
+/// shared libraries.
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Path to the file to inspect
+    pub path_to_binary: PathBuf,
+
+    /// Print the install ids as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+pub fn execute(args: Args) -> Result<(), MachoError> {
+    let bytes_of_file = arwen_core::MappedFile::open(&args.path_to_binary)?;
+
+    let macho = MachoContainer::parse(&bytes_of_file)?;
+
+    super::print_slices(&macho.install_id()?, args.json, |id, indent| {
+        if let Some(id) = id {
+            println!("{indent}{id}");
+        }
+    });
+
+    Ok(())
+}
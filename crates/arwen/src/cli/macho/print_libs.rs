@@ -0,0 +1,34 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use arwen_macho::{MachoContainer, MachoError};
+
+/// Print the dynamic libraries of every architecture, with how they are loaded and
+/// their versions.
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Path to the file to inspect
+    pub path_to_binary: PathBuf,
+
+    /// Print the libraries as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+pub fn execute(args: Args) -> Result<(), MachoError> {
+    let bytes_of_file = arwen_core::MappedFile::open(&args.path_to_binary)?;
+
+    let macho = MachoContainer::parse(&bytes_of_file)?;
+
+    super::print_slices(&macho.libs()?, args.json, |libs, indent| {
+        for lib in libs {
+            println!(
+                "{indent}{} ({}, compatibility version {}, current version {})",
+                lib.name, lib.kind, lib.compatibility_version, lib.current_version
+            );
+        }
+    });
+
+    Ok(())
+}
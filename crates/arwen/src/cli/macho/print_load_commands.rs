@@ -0,0 +1,38 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use arwen_macho::{MachoContainer, MachoError};
+
+/// Print the load commands of every architecture.
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Path to the file to inspect
+    pub path_to_binary: PathBuf,
+
+    /// Print the load commands as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+pub fn execute(args: Args) -> Result<(), MachoError> {
+    let bytes_of_file = arwen_core::MappedFile::open(&args.path_to_binary)?;
+
+    let macho = MachoContainer::parse(&bytes_of_file)?;
+
+    super::print_slices(&macho.load_commands()?, args.json, |commands, indent| {
+        for command in commands {
+            let line = format!(
+                "{:>3} {:<28} offset {:#06x} size {:>5} {}",
+                command.index,
+                command.name,
+                command.offset,
+                command.cmdsize,
+                command.detail.as_deref().unwrap_or_default()
+            );
+            println!("{indent}{}", line.trim_end());
+        }
+    });
+
+    Ok(())
+}
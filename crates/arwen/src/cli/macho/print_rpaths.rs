@@ -0,0 +1,30 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use arwen_macho::{MachoContainer, MachoError};
+
+/// Print the rpaths of every architecture.
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Path to the file to inspect
+    pub path_to_binary: PathBuf,
+
+    /// Print the rpaths as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+pub fn execute(args: Args) -> Result<(), MachoError> {
+    let bytes_of_file = arwen_core::MappedFile::open(&args.path_to_binary)?;
+
+    let macho = MachoContainer::parse(&bytes_of_file)?;
+
+    super::print_slices(&macho.rpaths()?, args.json, |rpaths, indent| {
+        for rpath in rpaths {
+            println!("{indent}{rpath}");
+        }
+    });
+
+    Ok(())
+}
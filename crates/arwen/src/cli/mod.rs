@@ -1,8 +1,11 @@
+use std::path::PathBuf;
+
 use clap::Parser;
 use thiserror::Error;
 
 pub mod elf;
 pub mod macho;
+pub mod replace_prefix;
 
 #[derive(Parser, Debug)]
 /// The `arwen`
@@ -13,6 +16,7 @@ pub enum Command {
     #[command(subcommand)]
     /// ELF commands
     Elf(ElfCommand),
+    ReplacePrefix(replace_prefix::Args),
 }
 
 #[derive(Debug, Parser)]
@@ -23,6 +27,10 @@ pub enum MachoCommand {
     ChangeInstallName(macho::install_name::Args),
     ChangeInstallId(macho::install_id::Args),
     AdhocSign(macho::codesign::Args),
+    PrintRpaths(macho::print_rpaths::Args),
+    PrintLibs(macho::print_libs::Args),
+    PrintId(macho::print_id::Args),
+    PrintLoadCommands(macho::print_load_commands::Args),
 }
 
 #[derive(Debug, Parser)]
@@ -51,6 +59,35 @@ pub enum ElfCommand {
     SetExecStack(elf::set_execstack::Args),
     PrintExecStack(elf::print_execstack::Args),
     SetPageSize(elf::set_page_size::Args),
+    PrintDynamic(elf::print_dynamic::Args),
+    SetFlags(elf::set_flags::Args),
+    ClearFlags(elf::clear_flags::Args),
+    PrintFlags(elf::print_flags::Args),
+    PrintVersionRequirements(elf::print_version_requirements::Args),
+    RemoveVersionRequirement(elf::remove_version_requirement::Args),
+    ReplaceVersionRequirement(elf::replace_version_requirement::Args),
+    StripSymbolVersions(elf::strip_symbol_versions::Args),
+    DeleteSymbols(elf::delete_symbols::Args),
+    RenameSymbols(elf::rename_symbols::Args),
+    DeleteSections(elf::delete_sections::Args),
+    RenameSections(elf::rename_sections::Args),
+    AddSection(elf::add_section::Args),
+    UpdateSection(elf::update_section::Args),
+    DumpSection(elf::dump_section::Args),
+    Ldd(elf::ldd::Args),
+    SetAbiVersion(elf::set_abi_version::Args),
+    SetEFlags(elf::set_e_flags::Args),
+    PrintHeader(elf::print_header::Args),
+    PrintSegments(elf::print_segments::Args),
+    SetSegmentFlags(elf::set_segment_flags::Args),
+    PrintProperties(elf::print_properties::Args),
+    ClearProperty(elf::clear_property::Args),
+    AddGnuHash(elf::add_gnu_hash::Args),
+    ChangeSymbolExport(elf::change_symbol_export::Args),
+    PrefixSymbols(elf::prefix_symbols::Args),
+    ReorderNeeded(elf::reorder_needed::Args),
+    Check(elf::check::Args),
+    RenameLibrary(elf::rename_library::Args),
 }
 
 #[derive(Parser, Debug)]
@@ -66,6 +103,7 @@ pub fn execute() -> Result<(), ArwenError> {
     match args.command {
         Command::Macho(args) => macho::execute(args).map_err(ArwenError::Macho),
         Command::Elf(elf) => elf::execute(elf).map_err(ArwenError::Elf),
+        Command::ReplacePrefix(args) => replace_prefix::execute(args),
     }
 }
 
@@ -76,4 +114,13 @@ pub enum ArwenError {
 
     #[error("error while patching ELF file")]
     Elf(#[from] arwen_elf::ElfError),
+
+    #[error("error while replacing prefix")]
+    Prefix(#[from] arwen_core::PrefixError),
+
+    #[error("{0} is neither an ELF nor a Mach-O file")]
+    UnsupportedFormat(PathBuf),
+
+    #[error("I/O error while reading or writing a file")]
+    Io(#[from] std::io::Error),
 }
@@ -0,0 +1,85 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use object::FileKind;
+use serde::Serialize;
+
+use super::ArwenError;
+
+/// Replace a prefix embedded in the strings of ELF and Mach-O files, in place.
+///
+/// Every NUL terminated string containing OLD_PREFIX is rewritten and padded with NULs.
+/// NEW_PREFIX must not be longer than OLD_PREFIX.
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Prefix to replace
+    pub old_prefix: String,
+
+    /// Prefix to replace it with
+    pub new_prefix: String,
+
+    /// Paths to the files to change
+    #[arg(required = true)]
+    pub paths_to_binaries: Vec<PathBuf>,
+
+    /// Print the patched offsets as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Serialize)]
+struct FileReport {
+    path: PathBuf,
+    replacements: Vec<arwen_core::PrefixReplacement>,
+}
+
+pub fn execute(args: Args) -> Result<(), ArwenError> {
+    let mut reports = Vec::new();
+    for path in args.paths_to_binaries {
+        let bytes_of_file = arwen_core::MappedFile::open(&path)?;
+
+        let replacements = match FileKind::parse(&*bytes_of_file) {
+            Ok(FileKind::Elf32 | FileKind::Elf64) => {
+                let mut data = bytes_of_file.to_vec();
+                let replacements = arwen_core::replace_prefix(
+                    &mut data,
+                    args.old_prefix.as_bytes(),
+                    args.new_prefix.as_bytes(),
+                )?;
+                if !replacements.is_empty() {
+                    arwen_core::write_atomically(&path, |w| w.write_all(&data))?;
+                }
+                replacements
+            }
+            Ok(
+                FileKind::MachO32 | FileKind::MachO64 | FileKind::MachOFat32 | FileKind::MachOFat64,
+            ) => {
+                let mut macho = arwen_macho::MachoContainer::parse(&bytes_of_file)?;
+                let replacements = macho.replace_prefix(&args.old_prefix, &args.new_prefix)?;
+                if !replacements.is_empty() {
+                    macho.write_to_path(&path)?;
+                }
+                replacements
+            }
+            _ => return Err(ArwenError::UnsupportedFormat(path)),
+        };
+
+        reports.push(FileReport { path, replacements });
+    }
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&reports).unwrap());
+    } else {
+        for report in reports {
+            println!("{}", report.path.display());
+            for replacement in report.replacements {
+                println!(
+                    "    {:#x}: {} => {}",
+                    replacement.offset, replacement.old, replacement.new
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
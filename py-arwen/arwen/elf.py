@@ -5,12 +5,25 @@ This module provides functionality for working with ELF binary files,
 allowing operations like modifying runpaths, changing interpreters, and more.
 """
 
-from typing import Optional, Dict, List
+from typing import Optional, Dict, List, NamedTuple
 
 
 from arwen.arwen import PyElfContainer
 
 
+class RunPath(NamedTuple):
+    """
+    A DT_RPATH or DT_RUNPATH entry of an ELF file.
+
+    Attributes:
+        kind: The tag the entry was read from, either "RPATH" or "RUNPATH"
+        entries: The colon separated directories of the entry
+    """
+
+    kind: str
+    entries: List[str]
+
+
 class ElfContainer:
     """
     Python wrapper for manipulating ELF binary files.
@@ -71,14 +84,18 @@ class ElfContainer:
         """
         self._inner.remove_runpath()
 
-    def get_runpath(self) -> Optional[str]:
+    def get_runpath(self) -> List[RunPath]:
         """
-        Get the current runpath from the ELF file.
+        Get the DT_RPATH and DT_RUNPATH entries from the ELF file.
+
+        Both tags are reported when both exist, in the order they appear
+        in the dynamic section. The dynamic linker ignores DT_RPATH when
+        DT_RUNPATH is present.
 
         Returns:
-            The current runpath, or None if no runpath is set
+            The runpath entries, or an empty list if no runpath is set
         """
-        return self._inner.get_runpath()
+        return [RunPath(kind, entries) for kind, entries in self._inner.get_runpath()]
 
     def force_rpath(self) -> None:
         """
@@ -230,6 +247,58 @@ class ElfContainer:
         """
         self._inner.clear_version_symbol(symbol_name)
 
+    def get_version_requirements(self) -> Dict[str, List[str]]:
+        """
+        Get the symbol versions required from each library.
+
+        Returns:
+            A dictionary mapping library names (e.g. "libc.so.6") to the
+            versions required from them (e.g. ["GLIBC_2.34"])
+        """
+        return dict(self._inner.get_version_requirements())
+
+    def remove_version_requirement(
+        self, version: str, library: Optional[str] = None
+    ) -> None:
+        """
+        Remove a symbol version requirement.
+
+        Symbols bound to the version become unversioned.
+
+        Args:
+            version: The version to remove, e.g. "GLIBC_2.34"
+            library: Only remove the requirement on this library
+
+        Raises:
+            ElfError: If the version requirement cannot be found
+        """
+        self._inner.remove_version_requirement(version, library)
+
+    def replace_version_requirement(
+        self, old_version: str, new_version: str, library: Optional[str] = None
+    ) -> None:
+        """
+        Remap a symbol version requirement to another version.
+
+        Args:
+            old_version: The version to replace, e.g. "GLIBC_2.34"
+            new_version: The version to use instead, e.g. "GLIBC_2.17"
+            library: Only replace the requirement on this library
+
+        Raises:
+            ElfError: If the version requirement cannot be found
+        """
+        self._inner.replace_version_requirement(old_version, new_version, library)
+
+    def strip_symbol_versions(self) -> None:
+        """
+        Remove all symbol versioning information from the ELF file.
+
+        Raises:
+            ElfError: If the versioning information cannot be removed
+        """
+        self._inner.strip_symbol_versions()
+
     def add_debug_tag(self) -> None:
         """
         Add a debug tag to the ELF file.
@@ -1,8 +1,8 @@
 use arwen_elf::ElfContainer;
+use arwen_core::MappedFile;
 use pyo3::exceptions::PyException;
 use pyo3::prelude::*;
 use std::collections::HashMap;
-use std::fs;
 use std::path::Path;
 use std::path::PathBuf;
 
@@ -22,12 +22,12 @@ impl PyElfContainer {
     #[pyo3(text_signature = "(path)")]
     fn from_path(path: &str) -> PyResult<Self> {
         let file_path = Path::new(path);
-        let data = fs::read(file_path).map_err(|e| {
+        let data = MappedFile::open(file_path).map_err(|e| {
             PyErr::new::<PyException, _>(format!("Failed to read file {}: {}", path, e))
         })?;
 
-        // Note: This is safe because we're storing the data in the struct
-        let static_data = Box::leak(data.into_boxed_slice());
+        // Note: This is safe because the mapping is never dropped
+        let static_data: &'static [u8] = Box::leak(Box::new(data));
 
         let container = ElfContainer::parse(static_data).map_err(PyMachoError::from)?;
 
@@ -58,11 +58,15 @@ impl PyElfContainer {
         Ok(self.inner.remove_runpath().map_err(PyMachoError::from)?)
     }
 
-    /// Get the current runpath from the ELF file
+    /// Get all DT_RPATH and DT_RUNPATH entries from the ELF file as (kind, entries) pairs
     #[pyo3(text_signature = "($self)")]
-    fn get_runpath(&mut self) -> PyResult<Option<String>> {
-        let runpath = self.inner.inner.elf_runpath();
-        Ok(runpath.map(|rp| String::from_utf8_lossy(rp).to_string()))
+    fn get_runpath(&mut self) -> PyResult<Vec<(String, Vec<String>)>> {
+        Ok(self
+            .inner
+            .runpaths()
+            .into_iter()
+            .map(|runpath| (runpath.kind.to_string(), runpath.entries))
+            .collect())
     }
 
     /// Force the ELF file to use DT_RPATH instead of DT_RUNPATH
@@ -76,7 +80,8 @@ impl PyElfContainer {
     fn shrink_rpath(&mut self, rpath_prefixes: Vec<String>) -> PyResult<()> {
         Ok(self
             .inner
-            .shrink_rpath(rpath_prefixes)
+            .shrink_rpath(rpath_prefixes, None)
+            .map(|_| ())
             .map_err(PyMachoError::from)?)
     }
 
@@ -173,6 +178,52 @@ impl PyElfContainer {
             .map_err(PyMachoError::from)?)
     }
 
+    /// Get the symbol versions required from each library as (library, versions) pairs
+    #[pyo3(text_signature = "($self)")]
+    fn get_version_requirements(&mut self) -> PyResult<Vec<(String, Vec<String>)>> {
+        Ok(self
+            .inner
+            .version_requirements()
+            .into_iter()
+            .map(|requirement| (requirement.library, requirement.versions))
+            .collect())
+    }
+
+    /// Remove a symbol version requirement, optionally only the one on the given library
+    #[pyo3(signature = (version, library=None), text_signature = "($self, version, library=None)")]
+    fn remove_version_requirement(&mut self, version: &str, library: Option<&str>) -> PyResult<()> {
+        Ok(self
+            .inner
+            .remove_version_requirement(version, library)
+            .map_err(PyMachoError::from)?)
+    }
+
+    /// Replace a symbol version requirement, optionally only the one on the given library
+    #[pyo3(
+        signature = (old_version, new_version, library=None),
+        text_signature = "($self, old_version, new_version, library=None)"
+    )]
+    fn replace_version_requirement(
+        &mut self,
+        old_version: &str,
+        new_version: &str,
+        library: Option<&str>,
+    ) -> PyResult<()> {
+        Ok(self
+            .inner
+            .replace_version_requirement(old_version, new_version, library)
+            .map_err(PyMachoError::from)?)
+    }
+
+    /// Remove all symbol versioning information from the ELF file
+    #[pyo3(text_signature = "($self)")]
+    fn strip_symbol_versions(&mut self) -> PyResult<()> {
+        Ok(self
+            .inner
+            .strip_symbol_versions()
+            .map_err(PyMachoError::from)?)
+    }
+
     /// Add a debug tag to the ELF file
     #[pyo3(text_signature = "($self)")]
     fn add_debug_tag(&mut self) -> PyResult<()> {
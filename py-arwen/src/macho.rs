@@ -1,7 +1,7 @@
 use arwen_macho::MachoContainer;
+use arwen_core::MappedFile;
 use pyo3::exceptions::PyException;
 use pyo3::prelude::*;
-use std::fs;
 use std::path::Path;
 
 use crate::error::PyMachoError;
@@ -20,12 +20,12 @@ impl PyMachoContainer {
     #[pyo3(text_signature = "(path)")]
     fn from_path(path: &str) -> PyResult<Self> {
         let file_path = Path::new(path);
-        let data = fs::read(file_path).map_err(|e| {
+        let data = MappedFile::open(file_path).map_err(|e| {
             PyErr::new::<PyException, _>(format!("Failed to read file {}: {}", path, e))
         })?;
 
-        // Note: This is safe because we're storing the data in the struct
-        let static_data = Box::leak(data.into_boxed_slice());
+        // Note: This is safe because the mapping is never dropped
+        let static_data: &'static [u8] = Box::leak(Box::new(data));
 
         let container = MachoContainer::parse(static_data).map_err(PyMachoError::from)?;
 
@@ -80,7 +80,7 @@ impl PyMachoContainer {
     /// Save the modified Mach-O file to a path
     #[pyo3(text_signature = "($self, path)")]
     fn save(&self, path: &str) -> PyResult<()> {
-        fs::write(path, &self.inner.data).map_err(|e| {
+        self.inner.write_to_path(Path::new(path)).map_err(|e| {
             PyErr::new::<PyException, _>(format!("Failed to write file {}: {}", path, e))
         })
     }
@@ -30,7 +30,7 @@ def test_get_runpath(elf_bin):
     elf = ElfContainer.from_path(elf_bin)
     runpath = elf.get_runpath()
     assert runpath is not None
-    # Add assertions to verify the runpath
+    assert [entry.kind for entry in runpath] == ["RUNPATH"]
 
 
 def test_force_rpath(elf_bin):
@@ -139,6 +139,19 @@ def test_rename_dynamic_symbols(elf_bin):
     elf.rename_dynamic_symbols({"old_symbol": "new_symbol"})
 
 
+def test_version_requirements(elf_bin):
+    elf = ElfContainer.from_path(elf_bin)
+    requirements = elf.get_version_requirements()
+    assert "GLIBC_2.4" in requirements["libc.so.6"]
+    elf.replace_version_requirement("GLIBC_2.4", "GLIBC_2.3", "libc.so.6")
+    elf.remove_version_requirement("GLIBC_2.3.4")
+
+
+def test_strip_symbol_versions(elf_bin):
+    elf = ElfContainer.from_path(elf_bin)
+    elf.strip_symbol_versions()
+
+
 def test_save(elf_bin, tmp_files):
     elf = ElfContainer.from_path(elf_bin)
     elf.save(os.path.join(tmp_files, "modified_elf"))
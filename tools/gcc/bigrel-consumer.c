@@ -0,0 +1,8 @@
+#include <stdio.h>
+
+int bigrel_check(void);
+
+int main() {
+    printf("relocations %s\n", bigrel_check() ? "ok" : "broken");
+    return 0;
+}
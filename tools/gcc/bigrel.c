@@ -0,0 +1,23 @@
+/* A shared library with a large table of relative relocations right after .dynstr. */
+
+static int values[4096];
+
+#define ENTRY(i) &values[i],
+#define ENTRIES_4(i) ENTRY(i) ENTRY(i + 1) ENTRY(i + 2) ENTRY(i + 3)
+#define ENTRIES_16(i) ENTRIES_4(i) ENTRIES_4(i + 4) ENTRIES_4(i + 8) ENTRIES_4(i + 12)
+#define ENTRIES_64(i) ENTRIES_16(i) ENTRIES_16(i + 16) ENTRIES_16(i + 32) ENTRIES_16(i + 48)
+#define ENTRIES_256(i) ENTRIES_64(i) ENTRIES_64(i + 64) ENTRIES_64(i + 128) ENTRIES_64(i + 192)
+#define ENTRIES_1024(i) ENTRIES_256(i) ENTRIES_256(i + 256) ENTRIES_256(i + 512) ENTRIES_256(i + 768)
+
+int *const bigrel_table[] = {
+    ENTRIES_1024(0) ENTRIES_1024(1024) ENTRIES_1024(2048) ENTRIES_1024(3072)
+};
+
+int bigrel_check(void) {
+    for (int i = 0; i < 4096; i++) {
+        if (bigrel_table[i] != &values[i]) {
+            return 0;
+        }
+    }
+    return 1;
+}
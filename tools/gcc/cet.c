@@ -0,0 +1,6 @@
+#include <stdio.h>
+
+int main(void) {
+    printf("cet ok\n");
+    return 0;
+}
@@ -0,0 +1,11 @@
+#include <stdio.h>
+
+// Declare the library functions
+int versioned_answer(void);
+void versioned_hello(void);
+
+int main(void) {
+    versioned_hello();
+    printf("%d\n", versioned_answer());
+    return 0;
+}
@@ -0,0 +1,9 @@
+#include <stdio.h>
+
+int versioned_answer(void) {
+    return 42;
+}
+
+void versioned_hello(void) {
+    printf("Hello from libversioned!\n");
+}